@@ -0,0 +1,33 @@
+//! Compares a power-of-two capacity (masked indexing) with a nearby capacity that is not
+//! (compare-and-wrap indexing) on the same push/read workload.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use std::hint::black_box;
+use strlinebuf::LineBuffer;
+
+const LINE: &[u8] = b"$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47\n";
+const LINES_PER_ITER: usize = 256;
+
+fn push_and_read<const CAPACITY: usize>(line_buffer: &mut LineBuffer<CAPACITY>) {
+    let mut aux_buffer = [0u8; CAPACITY];
+    for _ in 0..LINES_PER_ITER {
+        line_buffer.push_bytes(black_box(LINE)).unwrap();
+        black_box(line_buffer.read_line_bytes(&mut aux_buffer).unwrap());
+    }
+}
+
+fn bench_indexing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("indexing");
+    group.throughput(Throughput::Bytes((LINE.len() * LINES_PER_ITER) as u64));
+
+    let mut pow2 = LineBuffer::<4096>::new();
+    group.bench_function("capacity_4096", |b| b.iter(|| push_and_read(&mut pow2)));
+
+    let mut non_pow2 = LineBuffer::<4000>::new();
+    group.bench_function("capacity_4000", |b| b.iter(|| push_and_read(&mut non_pow2)));
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_indexing);
+criterion_main!(benches);
@@ -0,0 +1,197 @@
+//! Benchmarks for the push, scan and read paths, run on the host with std.
+//!
+//! Each scenario is measured over a couple of capacities, and where the layout matters, with the
+//! buffered data both contiguous and wrapped around the end of the storage.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+use strlinebuf::{CrlfTerminator, LineBuffer, LineBufferConfig, Terminator};
+
+/// Move `start` to the middle of the storage so subsequent data wraps around the end.
+fn wrapped<const CAPACITY: usize>() -> LineBuffer<CAPACITY> {
+    let mut line_buffer = LineBuffer::<CAPACITY>::new();
+    let mut aux_buffer = [0u8; CAPACITY];
+    for _ in 0..CAPACITY / 2 - 1 {
+        line_buffer.push_byte(b'x').unwrap();
+    }
+    line_buffer.push_byte(b'\n').unwrap();
+    line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+    line_buffer
+}
+
+fn layout<const CAPACITY: usize>(wrap: bool) -> LineBuffer<CAPACITY> {
+    if wrap {
+        wrapped()
+    } else {
+        LineBuffer::new()
+    }
+}
+
+fn bench_push_bytes<const CAPACITY: usize>(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("push_bytes/{CAPACITY}"));
+    let data = [b'a'; 256];
+
+    for chunk in [1, 16, 256] {
+        group.throughput(Throughput::Bytes(chunk as u64));
+        for wrap in [false, true] {
+            let id = BenchmarkId::new(if wrap { "wrapped" } else { "contiguous" }, chunk);
+            group.bench_function(id, |b| {
+                b.iter_batched_ref(
+                    || layout::<CAPACITY>(wrap),
+                    |line_buffer| line_buffer.push_bytes(black_box(&data[..chunk])).unwrap(),
+                    criterion::BatchSize::SmallInput,
+                )
+            });
+        }
+    }
+
+    group.finish();
+}
+
+fn bench_read_line_bytes<const CAPACITY: usize>(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("read_line_bytes/{CAPACITY}"));
+    let mut line = [b'a'; 1025];
+
+    for line_len in [8, 1024] {
+        line[line_len] = b'\n';
+        group.throughput(Throughput::Bytes(line_len as u64));
+        for wrap in [false, true] {
+            let id = BenchmarkId::new(if wrap { "wrapped" } else { "contiguous" }, line_len);
+            group.bench_function(id, |b| {
+                b.iter_batched_ref(
+                    || {
+                        let mut line_buffer = layout::<CAPACITY>(wrap);
+                        line_buffer.push_bytes(&line[..=line_len]).unwrap();
+                        (line_buffer, [0u8; CAPACITY])
+                    },
+                    |(line_buffer, aux_buffer)| {
+                        black_box(line_buffer.read_line_bytes(aux_buffer).unwrap())
+                    },
+                    criterion::BatchSize::SmallInput,
+                )
+            });
+        }
+        line[line_len] = b'a';
+    }
+
+    group.finish();
+}
+
+fn bench_has_complete_line<const CAPACITY: usize>(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("has_complete_line/{CAPACITY}"));
+
+    for wrap in [false, true] {
+        // Half full with no terminator, the worst case for a poll.
+        let mut line_buffer = layout::<CAPACITY>(wrap);
+        for _ in 0..CAPACITY / 2 {
+            line_buffer.push_byte(b'a').unwrap();
+        }
+        let id = if wrap { "wrapped" } else { "contiguous" };
+        group.bench_function(id, |b| {
+            b.iter(|| black_box(&line_buffer).has_complete_line())
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_poll_slow_line<const CAPACITY: usize>(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("poll_slow_line/{CAPACITY}"));
+    group.throughput(Throughput::Bytes(CAPACITY as u64));
+
+    // A line arriving a byte at a time, with a read attempted after every byte.
+    group.bench_function("byte_at_a_time", |b| {
+        b.iter_batched_ref(
+            || (LineBuffer::<CAPACITY>::new(), [0u8; CAPACITY]),
+            |(line_buffer, aux_buffer)| {
+                for _ in 0..CAPACITY - 1 {
+                    line_buffer.push_byte(b'a').unwrap();
+                    black_box(line_buffer.read_line_bytes(aux_buffer).is_ok());
+                }
+                line_buffer.push_byte(b'\n').unwrap();
+                black_box(line_buffer.read_line_bytes(aux_buffer).unwrap())
+            },
+            criterion::BatchSize::SmallInput,
+        )
+    });
+
+    group.finish();
+}
+
+fn bench_scan_line<const CAPACITY: usize>(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("scan_line/{CAPACITY}"));
+    let mut line = [b'a'; 1024];
+    line[1023] = b'\n';
+    group.throughput(Throughput::Bytes(line.len() as u64));
+
+    // Counting the terminators on push and finding them on read, for a 1 KiB line.
+    // Compare with `--features fast-scan` to see the word-at-a-time search.
+    for wrap in [false, true] {
+        let id = if wrap { "wrapped" } else { "contiguous" };
+        group.bench_function(id, |b| {
+            b.iter_batched_ref(
+                || (layout::<CAPACITY>(wrap), [0u8; CAPACITY]),
+                |(line_buffer, aux_buffer)| {
+                    line_buffer.push_bytes(black_box(&line)).unwrap();
+                    black_box(line_buffer.read_line_bytes(aux_buffer).unwrap())
+                },
+                criterion::BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_scan_terminator<const CAPACITY: usize>(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("scan_terminator/{CAPACITY}"));
+    let mut lines = [b'a'; 1024];
+    for end in (32..=lines.len()).step_by(32) {
+        lines[end - 2..end].copy_from_slice(b"\r\n");
+    }
+    group.throughput(Throughput::Bytes(lines.len() as u64));
+
+    // `\r\n` from the runtime config against the same terminator fixed by the type.
+    group.bench_function("config", |b| {
+        b.iter_batched_ref(
+            || {
+                LineBuffer::<CAPACITY>::new_with_config(&LineBufferConfig {
+                    terminator: Terminator::CarriageReturnNewline,
+                    ..LineBufferConfig::DEFAULT
+                })
+            },
+            |line_buffer| {
+                line_buffer.push_bytes(black_box(&lines)).unwrap();
+                black_box(line_buffer.line_count())
+            },
+            criterion::BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("typed", |b| {
+        b.iter_batched_ref(
+            LineBuffer::<CAPACITY, usize, CrlfTerminator>::new,
+            |line_buffer| {
+                line_buffer.push_bytes(black_box(&lines)).unwrap();
+                black_box(line_buffer.line_count())
+            },
+            criterion::BatchSize::SmallInput,
+        )
+    });
+
+    group.finish();
+}
+
+fn benches(c: &mut Criterion) {
+    bench_push_bytes::<2048>(c);
+    bench_push_bytes::<4096>(c);
+    bench_read_line_bytes::<2048>(c);
+    bench_read_line_bytes::<4096>(c);
+    bench_has_complete_line::<2048>(c);
+    bench_has_complete_line::<4096>(c);
+    bench_poll_slow_line::<2048>(c);
+    bench_scan_line::<2048>(c);
+    bench_scan_terminator::<2048>(c);
+}
+
+criterion_group!(line_buffer, benches);
+criterion_main!(line_buffer);
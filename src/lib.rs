@@ -1,5 +1,17 @@
 #![no_std]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+use core::cell::RefCell;
+#[cfg(target_has_atomic = "8")]
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::ops::Range;
+#[cfg(target_has_atomic = "8")]
+use core::sync::atomic::{AtomicBool, Ordering};
+
 /// Terminator is an enum that represents the different types of terminators that can be used to determine the end of a line.
 /// The terminator can be one of the following:
 /// - None: No terminator (anything remaining in the buffer is considered part of the line)
@@ -8,9 +20,15 @@
 /// - NULL: `\0` (NULL)
 /// - CarriageReturnNewline: `\r\n` (Carriage Return + Newline)
 /// - NewlineCarriageReturn: `\n\r` (Newline + Carriage Return)
+/// - Sequence: any other byte sequence
+/// - SequenceCI: any other byte sequence, ignoring ASCII case
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Terminator {
     /// No terminator, meaning anything remaining in the buffer is considered part of the line.
+    ///
+    /// There is no framing: each read returns exactly the bytes buffered at the time of the call,
+    /// less any escape bytes, and leaves the buffer empty. Reads never fail with
+    /// `LineBufferRxError::NoLines`, only with `LineBufferRxError::BufferEmpty` until more arrives.
     None,
     /// `\r` (Carriage Return) as the terminator.
     CarriageReturn,
@@ -23,545 +41,11086 @@ pub enum Terminator {
     CarriageReturnNewline,
     /// `\n\r` (Newline + Carriage Return) as the terminator.
     NewlineCarriageReturn,
+    /// An arbitrary byte sequence as the terminator, e.g. `Sequence(b"END")`.
+    /// An empty sequence behaves like `None`.
+    Sequence(&'static [u8]),
+    /// Like `Sequence`, but matched ignoring ASCII case, so `SequenceCI(b"end")` also ends a line
+    /// at `END` or `End`. Only the ASCII letters `a`-`z` and `A`-`Z` are folded; other bytes must
+    /// match exactly.
+    SequenceCI(&'static [u8]),
 }
 
-/// Configuration for the LineBuffer.
-pub struct LineBufferConfig {
-    /// The terminator character(s) that determines the end of a line.
-    pub terminator: Terminator,
-}
-
-impl Default for LineBufferConfig {
-    fn default() -> Self {
-        Self {
-            terminator: Terminator::Newline,
+impl Terminator {
+    /// The byte sequence that makes up the terminator.
+    /// Empty for `Terminator::None`.
+    const fn bytes(&self) -> &'static [u8] {
+        match self {
+            Terminator::None => b"",
+            Terminator::CarriageReturn => b"\r",
+            Terminator::Newline => b"\n",
+            Terminator::NULL => b"\0",
+            Terminator::CarriageReturnNewline => b"\r\n",
+            Terminator::NewlineCarriageReturn => b"\n\r",
+            Terminator::Sequence(bytes) | Terminator::SequenceCI(bytes) => bytes,
         }
     }
+
+    /// Whether the terminator is matched ignoring ASCII case.
+    const fn ignores_case(&self) -> bool {
+        matches!(self, Terminator::SequenceCI(_))
+    }
 }
 
-#[derive(Debug)]
-pub enum LineBufferTxError {
-    BufferFull,
+impl TryFrom<&[u8]> for Terminator {
+    type Error = UnknownTerminator;
+
+    /// Map a byte string to the built-in terminator it spells, e.g. `b"\r\n"` to `CarriageReturnNewline`.
+    /// An empty string maps to `Terminator::None`.
+    /// Other sequences aren't recognized, since `Terminator::Sequence` needs a `'static` slice.
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        match bytes {
+            b"" => Ok(Terminator::None),
+            b"\r" => Ok(Terminator::CarriageReturn),
+            b"\n" => Ok(Terminator::Newline),
+            b"\0" => Ok(Terminator::NULL),
+            b"\r\n" => Ok(Terminator::CarriageReturnNewline),
+            b"\n\r" => Ok(Terminator::NewlineCarriageReturn),
+            _ => Err(UnknownTerminator),
+        }
+    }
 }
 
-#[derive(Debug)]
-pub enum LineBufferRxError {
-    BufferEmpty,
-    NoLines,
+impl From<Terminator> for &'static [u8] {
+    fn from(terminator: Terminator) -> Self {
+        terminator.bytes()
+    }
 }
 
-/// `LineBuffer` is a simple ring buffer that can be used to store bytes until a line terminator is reached.
+/// Fixes the terminator of a [`RawLineBuffer`] at compile time, as its third type parameter.
+///
+/// With the terminator known up front, the line scan is specialized for it instead of going through
+/// `config.terminator` for every byte. `config.terminator` is then ignored.
 /// ```rust
-/// use strlinebuf::LineBuffer;
+/// use strlinebuf::{CrlfTerminator, LineBuffer};
 ///
-/// let mut line_buffer = LineBuffer::<10>::new();
-/// line_buffer.push_bytes(b"Hello\n").unwrap();
+/// let mut line_buffer = LineBuffer::<32, usize, CrlfTerminator>::new();
+/// line_buffer.push_bytes(b"AT\r\nOK\r\n").unwrap();
 ///
-/// let mut aux_buffer = [0u8; 10];
-/// let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
-/// let line = core::str::from_utf8(&aux_buffer[..bytes_read]).unwrap();
-/// // line == "Hello"
+/// let mut aux_buffer = [0u8; 32];
+/// let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+/// assert_eq!(&aux_buffer[..len], b"AT");
 /// ```
-pub struct LineBuffer<const CAPACITY: usize> {
-    pub buffer: [u8; CAPACITY],
-    pub config: LineBufferConfig,
-    start: usize,
-    end: usize,
-    empty: bool,
+pub trait TerminatorSpec {
+    /// The terminator bytes, or `None` to take them from `config.terminator` at runtime.
+    /// Empty bytes behave like `Terminator::None`.
+    const BYTES: Option<&'static [u8]>;
 }
 
-impl<const CAPACITY: usize> LineBuffer<CAPACITY> {
-    /// Create a new LineBuffer with the specified capacity and terminator.
-    /// Example:
-    /// ```rust
-    /// use strlinebuf::{LineBuffer, LineBufferConfig, Terminator};
-    ///
-    /// let line_buffer = LineBuffer::<10>::new();
-    /// ```
-    /// The above example creates a new LineBuffer with a capacity of 10 and a newline terminator.
-    pub fn new() -> Self {
-        Self {
-            buffer: [0u8; CAPACITY],
-            config: LineBufferConfig::default(),
-            start: 0,
-            end: 0,
-            empty: true,
-        }
+/// The default [`TerminatorSpec`]: the terminator is whatever `config.terminator` says.
+pub struct ConfigTerminator;
+
+impl TerminatorSpec for ConfigTerminator {
+    const BYTES: Option<&'static [u8]> = None;
+}
+
+/// `\n` as a compile-time terminator.
+pub struct NewlineTerminator;
+
+impl TerminatorSpec for NewlineTerminator {
+    const BYTES: Option<&'static [u8]> = Some(b"\n");
+}
+
+/// `\r` as a compile-time terminator.
+pub struct CarriageReturnTerminator;
+
+impl TerminatorSpec for CarriageReturnTerminator {
+    const BYTES: Option<&'static [u8]> = Some(b"\r");
+}
+
+/// `\r\n` as a compile-time terminator.
+pub struct CrlfTerminator;
+
+impl TerminatorSpec for CrlfTerminator {
+    const BYTES: Option<&'static [u8]> = Some(b"\r\n");
+}
+
+/// `\0` as a compile-time terminator.
+pub struct NullTerminator;
+
+impl TerminatorSpec for NullTerminator {
+    const BYTES: Option<&'static [u8]> = Some(b"\0");
+}
+
+/// Error returned when a byte string isn't one of the built-in terminators.
+#[derive(Debug, PartialEq)]
+pub struct UnknownTerminator;
+
+impl core::fmt::Display for UnknownTerminator {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("not a recognized terminator sequence")
     }
+}
 
-    /// Create a new LineBuffer with the specified capacity and terminator.
-    /// Example:
+impl core::error::Error for UnknownTerminator {}
+
+/// Configuration for the LineBuffer.
+pub struct LineBufferConfig {
+    /// The terminator character(s) that determines the end of a line.
+    pub terminator: Terminator,
+    /// Optional escape byte. An escape byte makes the byte following it literal, so an escaped
+    /// terminator is kept as part of the line rather than ending it.
+    /// Escape bytes are stripped from the lines that are read, so `\\` yields a single `\`.
+    pub escape: Option<u8>,
+    /// The separator placed after each line copied out by `read_lines`.
+    pub batch_separator: BatchSeparator,
+    /// What ends each line copied out by `read_paragraph`.
+    pub paragraph_line_end: ParagraphLineEnd,
+    /// What the push methods do when the buffer is full.
+    pub overflow: OverflowPolicy,
+    /// Longest line, in buffered bytes without the terminator, that reads return as it is.
+    /// Longer lines are handled according to `line_too_long`.
+    pub max_line_length: Option<usize>,
+    /// What reads do with a line longer than `max_line_length`.
+    pub line_too_long: LineTooLongPolicy,
+    /// Make `LineTooLongPolicy::Truncate` cut a line before any UTF-8 character that would be
+    /// split, keeping up to 3 bytes fewer, so what's left is still valid text.
+    pub truncate_at_char_boundary: bool,
+    /// With a `\n` terminator, also drop a `\r` right before it from the lines that are read,
+    /// so CRLF data reads the same as LF data.
+    pub strip_trailing_cr: bool,
+    /// Check that the lines copied out by reads such as `read_line_bytes` are valid UTF-8, and
+    /// handle those that aren't according to `invalid_utf8`. Byte-level access such as
+    /// `with_line` and `drain_to` is unaffected, so it can still take a line left in the buffer.
+    pub require_utf8: bool,
+    /// What reads do with a line that isn't valid UTF-8, if `require_utf8` is set.
+    pub invalid_utf8: InvalidUtf8Policy,
+    /// Have reads drop empty lines and read the next one instead. The terminator left behind by
+    /// `LineBuffer::read_line_leave_terminator` is not dropped.
+    pub skip_empty_lines: bool,
+    /// Have reads drop lines whose first byte other than ASCII whitespace is one of these, such
+    /// as `b"#;"` for comments, and read the next one instead. An escaped byte doesn't count.
+    pub skip_comment_prefixes: Option<&'static [u8]>,
+    /// Fill levels at which to ask the sender to pause and resume, see `LineBuffer::flow_state`.
+    pub flow_thresholds: Option<FlowThresholds>,
+    /// Called from the push methods with each line they complete, see `LineHandler`.
+    pub line_handler: Option<LineHandler>,
+    /// The terminator `drain_to` writes after each line, or `None` to write the line's own.
+    pub drain_terminator: Option<Terminator>,
+}
+
+impl LineBufferConfig {
+    /// The default configuration, usable in `const` contexts.
+    pub const DEFAULT: Self = Self {
+        terminator: Terminator::Newline,
+        escape: None,
+        batch_separator: BatchSeparator::Newline,
+        paragraph_line_end: ParagraphLineEnd::Keep,
+        overflow: OverflowPolicy::Reject,
+        max_line_length: None,
+        line_too_long: LineTooLongPolicy::Error,
+        truncate_at_char_boundary: false,
+        strip_trailing_cr: false,
+        require_utf8: false,
+        invalid_utf8: InvalidUtf8Policy::Error,
+        skip_empty_lines: false,
+        skip_comment_prefixes: None,
+        flow_thresholds: None,
+        line_handler: None,
+        drain_terminator: None,
+    };
+}
+
+impl LineBufferConfig {
+    /// Start building a configuration from the defaults, checking it for mistakes at the end.
     /// ```rust
     /// use strlinebuf::{LineBuffer, LineBufferConfig, Terminator};
     ///
-    /// let line_buffer = LineBuffer::<10>::new_with_config(LineBufferConfig {
-    ///    terminator: Terminator::CarriageReturn,
-    /// });
+    /// static CONFIG: LineBufferConfig = match LineBufferConfig::builder()
+    ///     .terminator(Terminator::CarriageReturnNewline)
+    ///     .escape(Some(b'\\'))
+    ///     .build()
+    /// {
+    ///     Ok(config) => config,
+    ///     Err(_) => panic!("invalid line buffer config"),
+    /// };
+    /// let line_buffer = LineBuffer::<64>::new_with_config(&CONFIG);
     /// ```
-    /// The above example creates a new LineBuffer with a capacity of 10 and a CR terminator.
-    pub fn new_with_config(config: LineBufferConfig) -> Self {
-        Self {
-            buffer: [0u8; CAPACITY],
-            config,
-            start: 0,
-            end: 0,
-            empty: true,
+    pub const fn builder() -> ConfigBuilder {
+        ConfigBuilder {
+            config: Self::DEFAULT,
         }
     }
+}
 
-    /// Check if the buffer is empty.
-    /// The buffer is empty if the start and end pointers are equal.
-    pub fn is_empty(&self) -> bool {
-        self.empty
+impl Default for LineBufferConfig {
+    fn default() -> Self {
+        Self::DEFAULT
     }
+}
 
-    /// Check if the buffer is full.
-    pub fn is_full(&self) -> bool {
-        !self.empty && self.start == self.end
+/// Builder for a `LineBufferConfig`, created by `LineBufferConfig::builder`.
+pub struct ConfigBuilder {
+    config: LineBufferConfig,
+}
+
+impl ConfigBuilder {
+    /// Set the terminator that ends each line.
+    pub const fn terminator(mut self, terminator: Terminator) -> Self {
+        self.config.terminator = terminator;
+        self
     }
 
-    /// Write a byte to the buffer.
-    /// If the buffer is full and allow_overwrites is false, an error will be returned.
-    pub fn push_byte(&mut self, byte: u8) -> Result<(), LineBufferTxError> {
-        // println!("start: {}, end: {}, byte: {}", self.start, self.end, byte);
-        if self.is_full() {
-            return Err(LineBufferTxError::BufferFull);
-        }
+    /// Set the escape byte, or `None` for no escaping.
+    pub const fn escape(mut self, escape: Option<u8>) -> Self {
+        self.config.escape = escape;
+        self
+    }
 
-        if self.empty {
-            self.empty = false;
-        }
-        self.buffer[self.end] = byte;
-        self.end = (self.end + 1) % CAPACITY;
+    /// Set the separator placed after each line copied out by `read_lines`.
+    pub const fn batch_separator(mut self, batch_separator: BatchSeparator) -> Self {
+        self.config.batch_separator = batch_separator;
+        self
+    }
 
-        Ok(())
+    /// Set what ends each line copied out by `read_paragraph`.
+    pub const fn paragraph_line_end(mut self, paragraph_line_end: ParagraphLineEnd) -> Self {
+        self.config.paragraph_line_end = paragraph_line_end;
+        self
     }
 
-    /// Write a slice of bytes to the buffer.
-    /// This can be a &[u8] or a &str.ß
-    pub fn push_bytes(&mut self, bytes: &[u8]) -> Result<(), LineBufferTxError> {
-        for byte in bytes {
-            self.push_byte(*byte)?;
-        }
+    /// Set what the push methods do when the buffer is full.
+    pub const fn overflow(mut self, overflow: OverflowPolicy) -> Self {
+        self.config.overflow = overflow;
+        self
+    }
 
-        Ok(())
+    /// Set the longest line reads return as it is, or `None` for no limit.
+    pub const fn max_line_length(mut self, max_line_length: Option<usize>) -> Self {
+        self.config.max_line_length = max_line_length;
+        self
     }
 
-    /// Clear the buffer.
-    /// This will reset the start and end pointers to 0 and set the buffer to empty.
-    ///
-    /// Warning: This will not clear the buffer contents, only the pointers.
-    pub fn clear(&mut self) {
-        self.start = 0;
-        self.end = 0;
-        self.empty = true;
+    /// Set what reads do with a line longer than the maximum line length.
+    pub const fn line_too_long(mut self, line_too_long: LineTooLongPolicy) -> Self {
+        self.config.line_too_long = line_too_long;
+        self
     }
 
-    /// Read a line from the buffer into a provided auxiliary buffer of at least the same capacity.
-    /// Returns the number of bytes read.
-    ///
-    /// Note: The transferred contents will not include the terminator character(s).
-    pub fn read_line_bytes(
-        &mut self,
-        aux_buffer: &mut [u8; CAPACITY],
-    ) -> Result<usize, LineBufferRxError> {
-        if self.is_empty() {
-            return Err(LineBufferRxError::BufferEmpty);
-        }
-        let initial_start = self.start;
+    /// Set whether truncated lines are cut before a UTF-8 character rather than through it.
+    pub const fn truncate_at_char_boundary(mut self, truncate_at_char_boundary: bool) -> Self {
+        self.config.truncate_at_char_boundary = truncate_at_char_boundary;
+        self
+    }
 
-        let mut bytes_read = 0;
-        loop {
-            let next_byte = self.buffer[self.start];
-            match self.config.terminator {
-                Terminator::None => {}
-                Terminator::Newline => {
-                    if next_byte == b'\n' {
-                        self.start = (self.start + 1) % CAPACITY;
-                        if self.start == self.end {
-                            self.empty = true;
-                        }
-                        break;
-                    }
-                }
-                Terminator::CarriageReturn => {
-                    if next_byte == b'\r' {
-                        self.start = (self.start + 1) % CAPACITY;
-                        if self.start == self.end {
-                            self.empty = true;
-                        }
-                        break;
-                    }
-                }
-                Terminator::NULL => {
-                    if next_byte == b'\0' {
-                        self.start = (self.start + 1) % CAPACITY;
-                        if self.start == self.end {
-                            self.empty = true;
-                        }
-                        break;
-                    }
-                }
-                Terminator::CarriageReturnNewline => {
-                    if next_byte == b'\r'
-                        && self.start != self.end
-                        && self.buffer[(self.start + 1) % CAPACITY] == b'\n'
-                    {
-                        self.start = (self.start + 2) % CAPACITY;
-                        if self.start == self.end {
-                            self.empty = true;
-                        }
-                        break;
-                    }
-                }
-                Terminator::NewlineCarriageReturn => {
-                    if next_byte == b'\n'
-                        && self.start != self.end
-                        && self.buffer[(self.start + 1) % CAPACITY] == b'\r'
-                    {
-                        self.start = (self.start + 2) % CAPACITY;
-                        if self.start == self.end {
-                            self.empty = true;
-                        }
-                        break;
-                    }
-                }
-            }
+    /// Set the handler called with each line the push methods complete, or `None` for none.
+    pub const fn line_handler(mut self, line_handler: Option<LineHandler>) -> Self {
+        self.config.line_handler = line_handler;
+        self
+    }
 
-            aux_buffer[bytes_read] = next_byte;
-            self.start = (self.start + 1) % CAPACITY;
-            bytes_read += 1;
+    /// Set the terminator `drain_to` writes after each line, or `None` to keep the line's own.
+    pub const fn drain_terminator(mut self, drain_terminator: Option<Terminator>) -> Self {
+        self.config.drain_terminator = drain_terminator;
+        self
+    }
 
-            if self.start == self.end {
-                self.empty = true;
-                if let Terminator::None = self.config.terminator {
-                    break;
-                } else {
-                    self.start = initial_start;
-                    return Err(LineBufferRxError::NoLines);
+    /// Set whether reads drop a `\r` right before a `\n` terminator.
+    pub const fn strip_trailing_cr(mut self, strip_trailing_cr: bool) -> Self {
+        self.config.strip_trailing_cr = strip_trailing_cr;
+        self
+    }
+
+    /// Set whether reads check that lines are valid UTF-8.
+    pub const fn require_utf8(mut self, require_utf8: bool) -> Self {
+        self.config.require_utf8 = require_utf8;
+        self
+    }
+
+    /// Set what reads do with a line that isn't valid UTF-8.
+    pub const fn invalid_utf8(mut self, invalid_utf8: InvalidUtf8Policy) -> Self {
+        self.config.invalid_utf8 = invalid_utf8;
+        self
+    }
+
+    /// Set whether reads drop empty lines.
+    pub const fn skip_empty_lines(mut self, skip_empty_lines: bool) -> Self {
+        self.config.skip_empty_lines = skip_empty_lines;
+        self
+    }
+
+    /// Set the bytes that start a comment line for reads to drop, or `None` to keep every line.
+    pub const fn skip_comment_prefixes(
+        mut self,
+        skip_comment_prefixes: Option<&'static [u8]>,
+    ) -> Self {
+        self.config.skip_comment_prefixes = skip_comment_prefixes;
+        self
+    }
+
+    /// Set the fill levels at which to pause and resume the sender, or `None` to not track them.
+    pub const fn flow_thresholds(mut self, flow_thresholds: Option<FlowThresholds>) -> Self {
+        self.config.flow_thresholds = flow_thresholds;
+        self
+    }
+
+    /// Check the settings against each other and return the configuration.
+    pub const fn build(self) -> Result<LineBufferConfig, ConfigError> {
+        if let Some(thresholds) = self.config.flow_thresholds {
+            if thresholds.low >= thresholds.high {
+                return Err(ConfigError::FlowThresholdsOverlap);
+            }
+        }
+        let terminator = self.config.terminator.bytes();
+        if terminator.is_empty() && !matches!(self.config.terminator, Terminator::None) {
+            return Err(ConfigError::EmptySequence);
+        }
+        if let Some(escape) = self.config.escape {
+            let mut i = 0;
+            while i < terminator.len() {
+                let ignore_case = self.config.terminator.ignores_case();
+                if terminator[i] == escape
+                    || (ignore_case && terminator[i].eq_ignore_ascii_case(&escape))
+                {
+                    return Err(ConfigError::EscapeInTerminator);
                 }
+                i += 1;
             }
         }
+        Ok(self.config)
+    }
+}
 
-        Ok(bytes_read)
+/// A mistake found by `ConfigBuilder::build`.
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    /// `Terminator::Sequence` was given an empty sequence. Use `Terminator::None` for no terminator.
+    EmptySequence,
+    /// The escape byte also appears in the terminator, so it's unclear which role it plays.
+    EscapeInTerminator,
+    /// The low flow threshold is not below the high one, which leaves no room for hysteresis.
+    FlowThresholdsOverlap,
+}
+
+impl core::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ConfigError::EmptySequence => f.write_str("terminator sequence is empty"),
+            ConfigError::EscapeInTerminator => f.write_str("escape byte is part of the terminator"),
+            ConfigError::FlowThresholdsOverlap => {
+                f.write_str("low flow threshold is not below the high threshold")
+            }
+        }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl core::error::Error for ConfigError {}
 
-    /// Simple test to check if the library is working.
-    #[test]
-    fn test_new() {
-        let line_buffer = LineBuffer::<10>::new();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineBufferTxError {
+    /// The buffer had no room for the `requested` bytes.
+    /// `available` bytes were free when the push was attempted, and `written` of the requested
+    /// bytes were stored before giving up.
+    BufferFull {
+        requested: usize,
+        available: usize,
+        written: usize,
+    },
+    /// The buffer filled up before the input ran out.
+    /// `accepted` bytes were stored and `rejected` is the first byte that did not fit.
+    Overflow { accepted: usize, rejected: u8 },
+}
 
-        assert_eq!(line_buffer.buffer, [0; 10]);
-        assert_eq!(line_buffer.start, 0);
-        assert_eq!(line_buffer.end, 0);
-        assert_eq!(line_buffer.config.terminator, Terminator::Newline);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineBufferRxError {
+    BufferEmpty,
+    /// No complete line is buffered yet. `pending` bytes are waiting for a terminator.
+    NoLines {
+        pending: usize,
+    },
+    /// The provided buffer cannot hold the next line.
+    AuxBufferTooSmall,
+    /// The next line was `found` bytes long rather than the `expected` length.
+    UnexpectedLineLength {
+        found: usize,
+        expected: usize,
+    },
+    /// The next line was `len` bytes long, over `config.max_line_length`, and has been dropped.
+    LineTooLong {
+        len: usize,
+    },
+    /// The next line is not valid UTF-8. The first `valid_up_to` bytes of it are.
+    InvalidUtf8 {
+        valid_up_to: usize,
+    },
+    /// The line at the front is part way through being read by `read_line_chunk`, which has to
+    /// finish it first.
+    MidLine,
+    /// Fewer bytes are buffered than were asked for. `available` are.
+    NotEnoughData {
+        available: usize,
+    },
+    /// The line split into `found` fields, more than the output has room for. The line has
+    /// been consumed.
+    TooManyFields {
+        found: usize,
+    },
+}
+
+impl core::fmt::Display for LineBufferTxError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            LineBufferTxError::BufferFull {
+                requested,
+                available,
+                written,
+            } => write!(
+                f,
+                "line buffer is full: {written} of {requested} bytes written, {available} were free"
+            ),
+            LineBufferTxError::Overflow { accepted, .. } => {
+                write!(f, "line buffer filled up after accepting {accepted} bytes")
+            }
+        }
     }
+}
 
-    /// Test pushing a byte at a time, up until and including when the buffer is full.
-    #[test]
-    fn test_push_byte() {
-        let mut line_buffer = LineBuffer::<10>::new();
+impl core::error::Error for LineBufferTxError {}
 
-        line_buffer.push_byte(65).unwrap();
+impl core::fmt::Display for LineBufferRxError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            LineBufferRxError::BufferEmpty => f.write_str("line buffer is empty"),
+            LineBufferRxError::NoLines { pending } => {
+                write!(f, "no complete line is buffered, {pending} bytes pending")
+            }
+            LineBufferRxError::AuxBufferTooSmall => {
+                f.write_str("output buffer is too small for the line")
+            }
+            LineBufferRxError::UnexpectedLineLength { found, expected } => {
+                write!(f, "line is {found} bytes long, expected {expected}")
+            }
+            LineBufferRxError::LineTooLong { len } => {
+                write!(f, "line is {len} bytes long, over the maximum line length")
+            }
+            LineBufferRxError::InvalidUtf8 { valid_up_to } => {
+                write!(f, "line is not valid UTF-8 after {valid_up_to} bytes")
+            }
+            LineBufferRxError::MidLine => {
+                f.write_str("line is part way through being read in chunks")
+            }
+            LineBufferRxError::NotEnoughData { available } => {
+                write!(f, "not enough data buffered, only {available} bytes")
+            }
+            LineBufferRxError::TooManyFields { found } => {
+                write!(f, "line has {found} fields, more than there is room for")
+            }
+        }
+    }
+}
 
-        assert_eq!(line_buffer.buffer, [65, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
-        assert_eq!(line_buffer.start, 0);
-        assert_eq!(line_buffer.end, 1);
-        assert_eq!(line_buffer.empty, false);
+impl core::error::Error for LineBufferRxError {}
 
-        line_buffer.push_byte(66).unwrap();
+/// Separator placed after each line copied out by `LineBuffer::read_lines`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BatchSeparator {
+    /// `\n` (Newline) after each line.
+    Newline,
+    /// `\0` (NULL) after each line.
+    NULL,
+}
 
-        assert_eq!(line_buffer.buffer, [65, 66, 0, 0, 0, 0, 0, 0, 0, 0]);
-        assert_eq!(line_buffer.start, 0);
-        assert_eq!(line_buffer.end, 2);
-        assert_eq!(line_buffer.empty, false);
+/// What ends each line copied out by `LineBuffer::read_paragraph`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParagraphLineEnd {
+    /// The line's own terminator, as it arrived.
+    Keep,
+    /// `\n`, whatever the terminator. A `\r` that `strip_trailing_cr` drops is left out too.
+    Newline,
+}
 
-        line_buffer.push_byte(67).unwrap();
-        line_buffer.push_byte(68).unwrap();
-        line_buffer.push_byte(69).unwrap();
-        line_buffer.push_byte(70).unwrap();
-        line_buffer.push_byte(71).unwrap();
-        line_buffer.push_byte(72).unwrap();
-        line_buffer.push_byte(73).unwrap();
-        line_buffer.push_byte(74).unwrap();
+/// Error returned by `LineBuffer::rewind`.
+#[derive(Debug)]
+pub enum RewindError {
+    /// Some of the bytes read since the last `clear` have since been overwritten.
+    Overwritten,
+}
 
-        assert_eq!(line_buffer.buffer, [65, 66, 67, 68, 69, 70, 71, 72, 73, 74]);
-        assert_eq!(line_buffer.start, 0);
-        assert_eq!(line_buffer.end, 0);
-        assert_eq!(line_buffer.empty, false);
+impl core::fmt::Display for RewindError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RewindError::Overwritten => f.write_str("bytes to rewind over have been overwritten"),
+        }
+    }
+}
+
+impl core::error::Error for RewindError {}
+
+/// Running totals kept by a line buffer with the `stats` feature, see `LineBuffer::stats`.
+///
+/// Bytes put back with `unread` or `rewind` are not counted as pushed, so apart from those,
+/// `bytes_pushed == bytes_read + bytes_dropped + bytes_skipped + len()`.
+#[cfg(feature = "stats")]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LineBufferStats {
+    /// Bytes accepted by the push methods.
+    pub bytes_pushed: u64,
+    /// Bytes consumed by the read methods, including terminators and escape bytes.
+    pub bytes_read: u64,
+    /// Lines consumed by the read methods.
+    pub lines_read: u32,
+    /// Bytes thrown away without being read, to make room for new ones or by `clear`.
+    pub bytes_dropped: u32,
+    /// Bytes the push methods refused because the buffer was full.
+    pub bytes_rejected: u32,
+    /// Whole lines evicted to make room for new bytes, see `OverflowPolicy::DropOldestLine`.
+    pub lines_dropped: u32,
+    /// Bytes of the lines `skip_empty_lines` and `skip_comment_prefixes` had reads pass over.
+    pub bytes_skipped: u32,
+    /// Lines `skip_empty_lines` and `skip_comment_prefixes` had reads pass over.
+    pub lines_skipped: u32,
+    /// Length of the longest line read, in buffered bytes without the terminator.
+    pub max_line_len: usize,
+    /// Most bytes buffered at once.
+    pub max_fill: usize,
+}
+
+#[cfg(feature = "stats")]
+impl LineBufferStats {
+    const ZERO: Self = Self {
+        bytes_pushed: 0,
+        bytes_read: 0,
+        lines_read: 0,
+        bytes_dropped: 0,
+        bytes_rejected: 0,
+        lines_dropped: 0,
+        bytes_skipped: 0,
+        lines_skipped: 0,
+        max_line_len: 0,
+        max_fill: 0,
+    };
+}
+
+/// A function the push methods call with each line they complete, as soon as its terminator
+/// lands, instead of the lines being polled for.
+///
+/// The line is passed as the two slices `with_line` would pass, without its terminator but with
+/// any escape bytes, and the read policies such as `max_line_length` don't apply to it.
+/// The handler is a plain `fn`, and must not call back into the buffer it's handling, which
+/// debug builds check for.
+/// ```rust
+/// use core::sync::atomic::{AtomicUsize, Ordering};
+/// use strlinebuf::{LineBuffer, LineBufferConfig, LineHandler};
+///
+/// static LINES: AtomicUsize = AtomicUsize::new(0);
+///
+/// fn on_line(_first: &[u8], _second: &[u8]) {
+///     LINES.fetch_add(1, Ordering::Relaxed);
+/// }
+///
+/// let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+///     line_handler: Some(LineHandler { on_line, consume: true }),
+///     ..LineBufferConfig::DEFAULT
+/// });
+/// line_buffer.push_bytes(b"OK\nERR").unwrap();
+/// assert_eq!(LINES.load(Ordering::Relaxed), 1);
+/// assert_eq!(line_buffer.len(), 3);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct LineHandler {
+    /// Called with each completed line.
+    pub on_line: fn(&[u8], &[u8]),
+    /// Consume each line once it's handled, so only the partial line stays buffered, instead of
+    /// leaving it for the read methods. Lines already buffered when the handler is set are
+    /// handed over and consumed along with the next one to complete.
+    pub consume: bool,
+}
+
+/// Fill levels, in bytes, for flow control with hysteresis.
+///
+/// The buffer asks the sender to pause once it holds `high` bytes or more,
+/// and to resume once it has drained to `low` bytes or fewer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlowThresholds {
+    pub high: usize,
+    pub low: usize,
+}
+
+impl FlowThresholds {
+    /// Thresholds given as percentages of `capacity`.
+    pub const fn percent(capacity: usize, high: usize, low: usize) -> Self {
+        Self {
+            high: capacity * high / 100,
+            low: capacity * low / 100,
+        }
+    }
+}
+
+/// Whether the sender should currently be sending, see `LineBuffer::flow_state`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlowState {
+    /// Below the high threshold, or back down to the low one since.
+    Flowing,
+    /// Reached the high threshold and not yet drained to the low one.
+    Paused,
+}
+
+/// A change of `FlowState`, reported once by `LineBuffer::poll_flow_change`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlowChange {
+    /// Deassert RTS, or send XOFF.
+    Pause,
+    /// Reassert RTS, or send XON.
+    Resume,
+}
+
+/// Idle-flush state, see `LineBuffer::set_idle_flush`.
+#[derive(Clone, Copy)]
+struct IdleFlush {
+    /// Ticks of silence after which a partial line is ended.
+    ticks: u32,
+    /// The tick at which a push was last seen.
+    last_push: u32,
+    /// Whether anything was pushed since the last `tick`.
+    pushed: bool,
+}
+
+/// What `push_byte`, `push_bytes`, `push_from_iter` and `push_byte_overwrite` do when the buffer
+/// is full.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverflowPolicy {
+    /// Refuse the bytes that don't fit with `LineBufferTxError::BufferFull` or `Overflow`.
+    Reject,
+    /// Drop the oldest bytes to make room, so the newest data always wins.
+    /// The oldest line may be left partial.
+    OverwriteOldest,
+    /// Drop whole oldest lines, terminators included, until the new bytes fit.
+    ///
+    /// A push that wouldn't fit even after dropping every complete line is rejected and drops
+    /// nothing. In particular a partial line at the front is never cut short, so the first
+    /// buffered line is always one that arrived whole. `push_byte_overwrite` refuses its byte
+    /// the same way.
+    DropOldestLine,
+    /// Refuse the whole push with `LineBufferTxError::BufferFull` unless all of it fits, so
+    /// nothing is written. `push_from_iter`, which can't know how many bytes are coming, refuses
+    /// them like `Reject`.
+    RejectAll,
+}
+
+/// What reads do with a line longer than `LineBufferConfig::max_line_length`.
+///
+/// The policy applies to complete lines as they are read, wherever they sit in the storage.
+/// Only lines ended by a terminator are affected, so `Terminator::None` reads are never cut.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineTooLongPolicy {
+    /// Return only the first `max_line_length` bytes of the line and drop the rest.
+    /// `LineBuffer::read_line_info` reports the line as truncated.
+    Truncate,
+    /// Drop the line, terminator included, and read the next one instead.
+    Discard,
+    /// Drop the line, terminator included, and return `LineBufferRxError::LineTooLong`.
+    Error,
+}
+
+/// What reads do with a line that isn't valid UTF-8 when `LineBufferConfig::require_utf8` is set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InvalidUtf8Policy {
+    /// Drop the line, terminator included, and read the next one instead.
+    Discard,
+    /// Return `LineBufferRxError::InvalidUtf8` and leave the line in the buffer.
+    Error,
+}
+
+/// Summary of a `LineBuffer::read_line_info` call.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineInfo {
+    /// Number of bytes written to the output buffer. For a truncated line, this is what was
+    /// kept of it.
+    pub len: usize,
+    /// Whether the line was cut short by `LineTooLongPolicy::Truncate`.
+    pub truncated: bool,
+}
+
+/// Summary of a `LineBuffer::read_lines` call.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BatchInfo {
+    /// Number of lines transferred.
+    pub lines: usize,
+    /// Number of bytes written to the output buffer, including separators.
+    pub bytes: usize,
+}
+
+/// A piece of a line read by `LineBuffer::read_line_chunk`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineChunk {
+    /// Number of bytes copied out.
+    pub len: usize,
+    /// Whether this was the last piece of the line, and its terminator has been consumed.
+    pub finished: bool,
+}
+
+/// Error returned by `LineBuffer::visit_lines` when the visitor fails.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VisitError<E> {
+    /// The visitor's error. The line it failed on is still buffered.
+    pub error: E,
+    /// Number of lines handled, and consumed, before the failure.
+    pub handled: usize,
+}
+
+impl<E: core::fmt::Debug> core::fmt::Display for VisitError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "visitor failed after {} lines: {:?}",
+            self.handled, self.error
+        )
+    }
+}
+
+impl<E: core::fmt::Debug> core::error::Error for VisitError<E> {}
+
+/// Summary of a `LineBuffer::drain_to` call.
+#[cfg(feature = "embedded-io")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrainReport {
+    /// Number of lines forwarded.
+    pub lines: usize,
+    /// Number of bytes forwarded, including terminators.
+    pub bytes: usize,
+}
+
+/// Error returned by `LineBuffer::drain_to` when the writer fails part way.
+#[cfg(feature = "embedded-io")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrainError<E> {
+    /// The writer's error, or `None` if it stopped taking bytes by returning `Ok(0)`.
+    pub error: Option<E>,
+    /// What was forwarded before the failure.
+    pub report: DrainReport,
+    /// Bytes of the failed line, terminator included, that the writer took before failing.
+    /// The line stays buffered, so the next call sends it again from the start.
+    pub written: usize,
+}
+
+#[cfg(feature = "embedded-io")]
+impl<E: core::fmt::Debug> core::fmt::Display for DrainError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match &self.error {
+            Some(error) => write!(
+                f,
+                "writer failed after {} lines: {error:?}",
+                self.report.lines
+            ),
+            None => write!(
+                f,
+                "writer stopped taking bytes after {} lines",
+                self.report.lines
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<E: core::fmt::Debug> core::error::Error for DrainError<E> {}
+
+/// Error returned by `LineBuffer::fill_from` when the reader fails part way.
+#[cfg(feature = "embedded-io")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FillError<E> {
+    /// The reader's error.
+    pub error: E,
+    /// Bytes read into the buffer before the failure. They stay buffered.
+    pub filled: usize,
+}
+
+#[cfg(feature = "embedded-io")]
+impl<E: core::fmt::Debug> core::fmt::Display for FillError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "reader failed after {} bytes: {:?}",
+            self.filled, self.error
+        )
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<E: core::fmt::Debug> core::error::Error for FillError<E> {}
+
+/// Error returned by `LineBuffer::save_state`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveError {
+    /// The output buffer is too small. The saved state needs `needed` bytes.
+    OutputTooSmall { needed: usize },
+    /// The configuration uses a `Terminator::Sequence` or `SequenceCI`, which can't be restored
+    /// since its bytes would have to be `'static`.
+    UnsupportedTerminator,
+    /// The configuration has `skip_comment_prefixes`, which can't be restored since they would
+    /// have to be `'static`.
+    UnsupportedCommentPrefixes,
+}
+
+impl core::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SaveError::OutputTooSmall { needed } => {
+                write!(f, "output buffer is too small, {needed} bytes needed")
+            }
+            SaveError::UnsupportedTerminator => f.write_str("terminator sequences can't be saved"),
+            SaveError::UnsupportedCommentPrefixes => f.write_str("comment prefixes can't be saved"),
+        }
+    }
+}
+
+impl core::error::Error for SaveError {}
+
+/// Error returned by `LineBuffer::restore_state`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestoreError {
+    /// The data was saved in format version `found`, which this version of the crate can't read.
+    VersionMismatch { found: u8 },
+    /// The data holds `len` pending bytes, more than the buffer's `capacity`.
+    TooLarge { len: usize, capacity: usize },
+    /// The data is cut short or isn't a saved state.
+    Corrupt,
+    /// The configuration passed in isn't the one that was saved.
+    ConfigMismatch,
+}
+
+impl core::fmt::Display for RestoreError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RestoreError::VersionMismatch { found } => {
+                write!(
+                    f,
+                    "saved state is version {found}, expected {STATE_VERSION}"
+                )
+            }
+            RestoreError::TooLarge { len, capacity } => {
+                write!(
+                    f,
+                    "saved state holds {len} bytes, over the capacity of {capacity}"
+                )
+            }
+            RestoreError::Corrupt => f.write_str("saved state is corrupt"),
+            RestoreError::ConfigMismatch => {
+                f.write_str("saved state has a different configuration")
+            }
+        }
+    }
+}
+
+impl core::error::Error for RestoreError {}
+
+/// Format version written by `save_state`, bumped whenever the encoding changes.
+const STATE_VERSION: u8 = 3;
+
+/// Length of the fixed-size part of a saved state, before the pending bytes.
+///
+/// Byte 0 is the version, bytes 1-2 the little-endian flags (`STATE_*` below), bytes 3-9 the
+/// terminator, drain terminator, escape byte, batch separator, overflow, line-too-long and
+/// invalid-UTF-8 policies, one byte each, and then come little-endian `u64`s
+/// for `max_line_length`, the high and low flow thresholds, and the number of pending bytes.
+/// Settings that are `None` are written as zero.
+const STATE_HEADER_LEN: usize = STATE_CONFIG_LEN + 8;
+
+/// Length of the part of the header that encodes the configuration.
+const STATE_CONFIG_LEN: usize = 34;
+
+const STATE_TRUNCATE_AT_CHAR_BOUNDARY: u16 = 1 << 0;
+const STATE_STRIP_TRAILING_CR: u16 = 1 << 1;
+const STATE_REQUIRE_UTF8: u16 = 1 << 2;
+const STATE_ESCAPE: u16 = 1 << 3;
+const STATE_MAX_LINE_LENGTH: u16 = 1 << 4;
+const STATE_FLOW_THRESHOLDS: u16 = 1 << 5;
+const STATE_DRAIN_TERMINATOR: u16 = 1 << 6;
+const STATE_PARAGRAPH_NEWLINE: u16 = 1 << 7;
+const STATE_SKIP_EMPTY_LINES: u16 = 1 << 8;
+
+/// The byte a built-in terminator is saved as, or `None` for a sequence.
+const fn terminator_to_state(terminator: Terminator) -> Option<u8> {
+    match terminator {
+        Terminator::None => Some(0),
+        Terminator::CarriageReturn => Some(1),
+        Terminator::Newline => Some(2),
+        Terminator::NULL => Some(3),
+        Terminator::CarriageReturnNewline => Some(4),
+        Terminator::NewlineCarriageReturn => Some(5),
+        Terminator::Sequence(_) | Terminator::SequenceCI(_) => None,
+    }
+}
+
+const fn terminator_from_state(byte: u8) -> Option<Terminator> {
+    match byte {
+        0 => Some(Terminator::None),
+        1 => Some(Terminator::CarriageReturn),
+        2 => Some(Terminator::Newline),
+        3 => Some(Terminator::NULL),
+        4 => Some(Terminator::CarriageReturnNewline),
+        5 => Some(Terminator::NewlineCarriageReturn),
+        _ => None,
+    }
+}
+
+impl LineBufferConfig {
+    /// The configuration saved by `save_state` in `data`, for a `static` or leaked copy to pass
+    /// to `LineBuffer::restore_state`. The `line_handler` can't be saved and comes back as `None`.
+    /// ```rust
+    /// use strlinebuf::{LineBuffer, LineBufferConfig};
+    ///
+    /// let line_buffer = LineBuffer::<16>::new();
+    /// let mut saved = [0u8; 64];
+    /// line_buffer.save_state(&mut saved).unwrap();
+    ///
+    /// let config = LineBufferConfig::from_state(&saved).unwrap();
+    /// assert_eq!(config.overflow, LineBufferConfig::DEFAULT.overflow);
+    /// ```
+    pub fn from_state(data: &[u8]) -> Result<Self, RestoreError> {
+        match data.first() {
+            Some(&STATE_VERSION) => {}
+            Some(&found) => return Err(RestoreError::VersionMismatch { found }),
+            None => return Err(RestoreError::Corrupt),
+        }
+        let header = data.get(..STATE_HEADER_LEN).ok_or(RestoreError::Corrupt)?;
+        let flags = u16::from_le_bytes([header[1], header[2]]);
+        if flags >= STATE_SKIP_EMPTY_LINES << 1 {
+            return Err(RestoreError::Corrupt);
+        }
+        let set = |flag: u16| flags & flag != 0;
+        Ok(LineBufferConfig {
+            terminator: terminator_from_state(header[3]).ok_or(RestoreError::Corrupt)?,
+            drain_terminator: match set(STATE_DRAIN_TERMINATOR) {
+                true => Some(terminator_from_state(header[4]).ok_or(RestoreError::Corrupt)?),
+                false => None,
+            },
+            escape: set(STATE_ESCAPE).then_some(header[5]),
+            batch_separator: match header[6] {
+                0 => BatchSeparator::Newline,
+                1 => BatchSeparator::NULL,
+                _ => return Err(RestoreError::Corrupt),
+            },
+            paragraph_line_end: match set(STATE_PARAGRAPH_NEWLINE) {
+                true => ParagraphLineEnd::Newline,
+                false => ParagraphLineEnd::Keep,
+            },
+            overflow: match header[7] {
+                0 => OverflowPolicy::Reject,
+                1 => OverflowPolicy::OverwriteOldest,
+                2 => OverflowPolicy::DropOldestLine,
+                3 => OverflowPolicy::RejectAll,
+                _ => return Err(RestoreError::Corrupt),
+            },
+            line_too_long: match header[8] {
+                0 => LineTooLongPolicy::Truncate,
+                1 => LineTooLongPolicy::Discard,
+                2 => LineTooLongPolicy::Error,
+                _ => return Err(RestoreError::Corrupt),
+            },
+            invalid_utf8: match header[9] {
+                0 => InvalidUtf8Policy::Discard,
+                1 => InvalidUtf8Policy::Error,
+                _ => return Err(RestoreError::Corrupt),
+            },
+            max_line_length: match set(STATE_MAX_LINE_LENGTH) {
+                true => Some(state_usize(header, 10)?),
+                false => None,
+            },
+            flow_thresholds: match set(STATE_FLOW_THRESHOLDS) {
+                true => Some(FlowThresholds {
+                    high: state_usize(header, 18)?,
+                    low: state_usize(header, 26)?,
+                }),
+                false => None,
+            },
+            truncate_at_char_boundary: set(STATE_TRUNCATE_AT_CHAR_BOUNDARY),
+            strip_trailing_cr: set(STATE_STRIP_TRAILING_CR),
+            require_utf8: set(STATE_REQUIRE_UTF8),
+            skip_empty_lines: set(STATE_SKIP_EMPTY_LINES),
+            skip_comment_prefixes: None,
+            line_handler: None,
+        })
+    }
+
+    /// The configuration part of a saved state header.
+    fn to_state(&self) -> Result<[u8; STATE_CONFIG_LEN], SaveError> {
+        let terminator =
+            terminator_to_state(self.terminator).ok_or(SaveError::UnsupportedTerminator)?;
+        let drain_terminator = match self.drain_terminator {
+            Some(drain_terminator) => {
+                terminator_to_state(drain_terminator).ok_or(SaveError::UnsupportedTerminator)?
+            }
+            None => 0,
+        };
+        if self.skip_comment_prefixes.is_some() {
+            return Err(SaveError::UnsupportedCommentPrefixes);
+        }
+
+        let mut flags = 0;
+        for (set, flag) in [
+            (
+                self.truncate_at_char_boundary,
+                STATE_TRUNCATE_AT_CHAR_BOUNDARY,
+            ),
+            (self.strip_trailing_cr, STATE_STRIP_TRAILING_CR),
+            (self.require_utf8, STATE_REQUIRE_UTF8),
+            (self.escape.is_some(), STATE_ESCAPE),
+            (self.max_line_length.is_some(), STATE_MAX_LINE_LENGTH),
+            (self.flow_thresholds.is_some(), STATE_FLOW_THRESHOLDS),
+            (self.drain_terminator.is_some(), STATE_DRAIN_TERMINATOR),
+            (
+                self.paragraph_line_end == ParagraphLineEnd::Newline,
+                STATE_PARAGRAPH_NEWLINE,
+            ),
+            (self.skip_empty_lines, STATE_SKIP_EMPTY_LINES),
+        ] {
+            if set {
+                flags |= flag;
+            }
+        }
+        let thresholds = self
+            .flow_thresholds
+            .unwrap_or(FlowThresholds { high: 0, low: 0 });
+
+        let mut state = [0u8; STATE_CONFIG_LEN];
+        let [flags_low, flags_high] = flags.to_le_bytes();
+        state[..10].copy_from_slice(&[
+            STATE_VERSION,
+            flags_low,
+            flags_high,
+            terminator,
+            drain_terminator,
+            self.escape.unwrap_or(0),
+            self.batch_separator as u8,
+            self.overflow as u8,
+            self.line_too_long as u8,
+            self.invalid_utf8 as u8,
+        ]);
+        for (i, value) in [
+            self.max_line_length.unwrap_or(0),
+            thresholds.high,
+            thresholds.low,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            state[10 + i * 8..18 + i * 8].copy_from_slice(&(value as u64).to_le_bytes());
+        }
+        Ok(state)
+    }
+}
+
+/// Read the little-endian `u64` at `offset` of a saved state header, as a `usize`.
+fn state_usize(header: &[u8], offset: usize) -> Result<usize, RestoreError> {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&header[offset..offset + 8]);
+    usize::try_from(u64::from_le_bytes(bytes)).map_err(|_| RestoreError::Corrupt)
+}
+
+/// Why a `Utf8Stream` stopped.
+enum Utf8Stop {
+    /// The bytes aren't valid UTF-8 after the first `valid_up_to`.
+    Invalid { valid_up_to: usize },
+    /// The function handed the text failed.
+    Sink,
+}
+
+/// Decodes UTF-8 that arrives in pieces, handing on each run of whole characters as a `str`,
+/// with characters split between pieces put back together.
+struct Utf8Stream {
+    carry: [u8; 4],
+    carry_len: usize,
+    /// Number of bytes handed on so far.
+    valid: usize,
+}
+
+impl Utf8Stream {
+    const EMPTY: Self = Self {
+        carry: [0; 4],
+        carry_len: 0,
+        valid: 0,
+    };
+
+    fn invalid(&self, valid_up_to: usize) -> Utf8Stop {
+        Utf8Stop::Invalid {
+            valid_up_to: self.valid + valid_up_to,
+        }
+    }
+
+    fn feed(
+        &mut self,
+        mut bytes: &[u8],
+        emit: &mut impl FnMut(&str) -> core::fmt::Result,
+    ) -> Result<(), Utf8Stop> {
+        // Finish the character the previous piece ended part way into.
+        while self.carry_len > 0 && !bytes.is_empty() {
+            self.carry[self.carry_len] = bytes[0];
+            self.carry_len += 1;
+            bytes = &bytes[1..];
+            match core::str::from_utf8(&self.carry[..self.carry_len]) {
+                Ok(text) => {
+                    emit(text).map_err(|_| Utf8Stop::Sink)?;
+                    self.valid += text.len();
+                    self.carry_len = 0;
+                }
+                Err(error) if error.error_len().is_none() => {}
+                Err(_) => return Err(self.invalid(0)),
+            }
+        }
+
+        let valid = match core::str::from_utf8(bytes) {
+            Ok(_) => bytes.len(),
+            Err(error) if error.error_len().is_some() => {
+                return Err(self.invalid(error.valid_up_to()));
+            }
+            Err(error) => error.valid_up_to(),
+        };
+        let (text, rest) = bytes.split_at(valid);
+        let text = core::str::from_utf8(text).map_err(|_| self.invalid(0))?;
+        if !text.is_empty() {
+            emit(text).map_err(|_| Utf8Stop::Sink)?;
+            self.valid += text.len();
+        }
+        self.carry[..rest.len()].copy_from_slice(rest);
+        self.carry_len = rest.len();
+        Ok(())
+    }
+
+    /// Check nothing is left waiting for the rest of a character.
+    fn finish(&self) -> Result<(), Utf8Stop> {
+        match self.carry_len {
+            0 => Ok(()),
+            _ => Err(self.invalid(0)),
+        }
+    }
+}
+
+/// Copy bytes from an iterator into `out`.
+/// Returns the number of bytes copied, or `None` if they don't fit.
+fn copy_into(bytes: impl Iterator<Item = u8>, out: &mut [u8]) -> Option<usize> {
+    let mut copied = 0;
+    for byte in bytes {
+        *out.get_mut(copied)? = byte;
+        copied += 1;
+    }
+    Some(copied)
+}
+
+/// Copy bytes from an iterator into `out` as UTF-8, replacing each invalid sequence with U+FFFD
+/// the way `String::from_utf8_lossy` does.
+/// Returns the number of bytes written, or `None` if they don't fit.
+fn copy_lossy(bytes: impl Iterator<Item = u8>, out: &mut [u8]) -> Option<usize> {
+    fn put(out: &mut [u8], len: &mut usize, bytes: &[u8]) -> Option<()> {
+        out.get_mut(*len..*len + bytes.len())?
+            .copy_from_slice(bytes);
+        *len += bytes.len();
+        Some(())
+    }
+    const REPLACEMENT: &[u8] = "\u{FFFD}".as_bytes();
+
+    let mut len = 0;
+    // The character being decoded, how long it will be, and the range its next byte must be in.
+    let mut char_bytes = [0u8; 4];
+    let mut char_len = 0;
+    let mut expected = 0;
+    let mut next_range = 0x80..=0xBF;
+    for byte in bytes {
+        if char_len > 0 {
+            if next_range.contains(&byte) {
+                char_bytes[char_len] = byte;
+                char_len += 1;
+                next_range = 0x80..=0xBF;
+                if char_len == expected {
+                    put(out, &mut len, &char_bytes[..expected])?;
+                    char_len = 0;
+                }
+                continue;
+            }
+            // The character was cut short, and `byte` may start the next one.
+            put(out, &mut len, REPLACEMENT)?;
+            char_len = 0;
+        }
+        (expected, next_range) = match byte {
+            0x00..=0x7F => (1, 0x80..=0xBF),
+            0xC2..=0xDF => (2, 0x80..=0xBF),
+            0xE0 => (3, 0xA0..=0xBF),
+            0xE1..=0xEC | 0xEE | 0xEF => (3, 0x80..=0xBF),
+            0xED => (3, 0x80..=0x9F),
+            0xF0 => (4, 0x90..=0xBF),
+            0xF1..=0xF3 => (4, 0x80..=0xBF),
+            0xF4 => (4, 0x80..=0x8F),
+            _ => (0, 0x80..=0xBF),
+        };
+        match expected {
+            0 => put(out, &mut len, REPLACEMENT)?,
+            1 => put(out, &mut len, &[byte])?,
+            _ => {
+                char_bytes[0] = byte;
+                char_len = 1;
+            }
+        }
+    }
+    if char_len > 0 {
+        put(out, &mut len, REPLACEMENT)?;
+    }
+    Some(len)
+}
+
+/// `LineBuffer` is a simple ring buffer that can be used to store bytes until a line terminator is reached.
+/// ```rust
+/// use strlinebuf::LineBuffer;
+///
+/// let mut line_buffer = LineBuffer::<10>::new();
+/// line_buffer.push_bytes(b"Hello\n").unwrap();
+///
+/// let mut aux_buffer = [0u8; 10];
+/// let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+/// let line = core::str::from_utf8(&aux_buffer[..bytes_read]).unwrap();
+/// // line == "Hello"
+/// ```
+pub type LineBuffer<const CAPACITY: usize, I = usize, T = ConfigTerminator> =
+    RawLineBuffer<[u8; CAPACITY], I, T>;
+
+/// A `LineBuffer` whose array is left uninitialized instead of zeroed, so creating a large one
+/// on the stack costs nothing up front. Each byte is written before it's ever read, so it
+/// behaves exactly like `LineBuffer`.
+/// ```rust
+/// use strlinebuf::LineBufferUninit;
+///
+/// let mut line_buffer = LineBufferUninit::<4096>::new();
+/// line_buffer.push_bytes(b"Hello\n").unwrap();
+///
+/// let mut aux_buffer = [0u8; 4096];
+/// let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+/// assert_eq!(&aux_buffer[..bytes_read], b"Hello");
+/// ```
+pub type LineBufferUninit<const CAPACITY: usize, I = usize, T = ConfigTerminator> =
+    RawLineBuffer<UninitStorage<CAPACITY>, I, T>;
+
+/// A line buffer over a caller-provided slice, for when the capacity is only known at runtime.
+/// It behaves exactly like `LineBuffer`, with the capacity taken from the slice length.
+/// ```rust
+/// use strlinebuf::LineBufferSlice;
+///
+/// let mut storage = [0u8; 10];
+/// let mut line_buffer = LineBufferSlice::new(&mut storage);
+/// line_buffer.push_bytes(b"Hello\n").unwrap();
+///
+/// let mut aux_buffer = [0u8; 10];
+/// let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+/// assert_eq!(&aux_buffer[..bytes_read], b"Hello");
+/// ```
+pub type LineBufferSlice<'a, I = usize, T = ConfigTerminator> = RawLineBuffer<&'a mut [u8], I, T>;
+
+/// A heap-backed line buffer that grows instead of filling up, for targets with an allocator.
+/// It behaves exactly like `LineBuffer`, except that pushes only fail with
+/// `LineBufferTxError::BufferFull` once the buffer has grown to its maximum capacity.
+/// ```rust
+/// use strlinebuf::LineBufferVec;
+///
+/// let mut line_buffer = LineBufferVec::with_max_capacity(4, 64);
+/// line_buffer.push_bytes(b"longer than four\n").unwrap();
+/// assert!(line_buffer.capacity() >= 17);
+///
+/// let mut aux_buffer = [0u8; 64];
+/// let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+/// assert_eq!(&aux_buffer[..bytes_read], b"longer than four");
+/// ```
+#[cfg(feature = "alloc")]
+pub type LineBufferVec<I = usize, T = ConfigTerminator> = RawLineBuffer<VecStorage, I, T>;
+
+/// The ring buffer and line logic shared by `LineBuffer`, `LineBufferUninit`, `LineBufferSlice`
+/// and `LineBufferVec`, generic over the [`Storage`], the index type and the [`TerminatorSpec`].
+pub struct RawLineBuffer<S: Storage, I: RingIndex = usize, T: TerminatorSpec = ConfigTerminator> {
+    pub buffer: S,
+    /// Shared rather than held by value, so buffers with the same configuration don't each carry
+    /// a copy. Changed through `set_config`, which marks the line count for a recount.
+    config: &'static LineBufferConfig,
+    start: I,
+    end: I,
+    empty: bool,
+    /// Line count, maintained as bytes are pushed and consumed.
+    scan: ScanState<I>,
+    /// Whether `config` was replaced since the lines were last counted.
+    config_changed: bool,
+    /// Number of already-read bytes just before `start` that are still intact, for `rewind`.
+    rewindable: I,
+    /// Whether a write has landed on already-read bytes since the last `clear`.
+    rewind_lost: bool,
+    /// Most bytes buffered at once since creation or `reset_high_watermark`.
+    high_watermark: I,
+    /// Flow control state, updated whenever the length changes.
+    flow: FlowState,
+    /// The flow change not yet returned by `poll_flow_change`.
+    flow_change: Option<FlowChange>,
+    /// Whether the line at the front was cut short by `LineTooLongPolicy::Truncate`.
+    truncated: bool,
+    /// Whether `config.line_handler` is running, to catch it calling back into the buffer.
+    in_line_handler: bool,
+    /// Whether `read_line_chunk` has read part of the line at the front.
+    mid_line: bool,
+    /// Whether the front of the buffer is a terminator left by `read_line_leave_terminator`.
+    terminator_left: bool,
+    /// How far `scan` has searched for the end of the first line, counted from `start`.
+    scan_pos: I,
+    /// The search state at `scan_pos`, with `lines` set once the end of the first line was found.
+    scan_pos_state: ScanState<I>,
+    idle_flush: Option<IdleFlush>,
+    #[cfg(feature = "stats")]
+    stats: LineBufferStats,
+    terminator: PhantomData<T>,
+}
+
+/// Backing storage for a [`RawLineBuffer`]. Unless it says otherwise, the length of the slice is
+/// the buffer's capacity.
+pub trait Storage {
+    /// The storage as a byte slice.
+    /// Storage that starts out uninitialized may leave off bytes that were never written,
+    /// which the line buffer never reads.
+    fn as_slice(&self) -> &[u8];
+    /// The storage as a mutable byte slice.
+    fn as_mut_slice(&mut self) -> &mut [u8];
+    /// The bytes in `range`, for writing.
+    #[inline(always)]
+    fn range_mut(&mut self, range: Range<usize>) -> &mut [u8] {
+        &mut self.as_mut_slice()[range]
+    }
+    /// Number of bytes the storage can hold.
+    #[inline(always)]
+    fn capacity(&self) -> usize {
+        self.as_slice().len()
+    }
+    /// Grow to hold at least `capacity` bytes, or as many as the storage allows, keeping the
+    /// existing bytes where they are. Returns whether it grew at all.
+    /// Fixed-size storage never grows.
+    #[inline(always)]
+    fn try_grow(&mut self, capacity: usize) -> bool {
+        let _ = capacity;
+        false
+    }
+}
+
+impl<const CAPACITY: usize> Storage for [u8; CAPACITY] {
+    #[inline(always)]
+    fn as_slice(&self) -> &[u8] {
+        self
+    }
+
+    #[inline(always)]
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        self
+    }
+
+    #[inline(always)]
+    fn capacity(&self) -> usize {
+        CAPACITY
+    }
+}
+
+impl Storage for &mut [u8] {
+    #[inline(always)]
+    fn as_slice(&self) -> &[u8] {
+        self
+    }
+
+    #[inline(always)]
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        self
+    }
+}
+
+/// Array storage for a `LineBufferUninit`, left uninitialized until it is written, so that
+/// creating a large buffer doesn't have to zero it first.
+pub struct UninitStorage<const CAPACITY: usize> {
+    bytes: [MaybeUninit<u8>; CAPACITY],
+    /// Bytes before this index have been written or zeroed. Writes mostly land right here, so
+    /// it rarely takes more than the write itself to move it along.
+    initialized: usize,
+}
+
+impl<const CAPACITY: usize> Storage for UninitStorage<CAPACITY> {
+    #[inline(always)]
+    fn as_slice(&self) -> &[u8] {
+        let bytes = &self.bytes[..self.initialized];
+        // SAFETY: the first `initialized` bytes are initialized, and `MaybeUninit<u8>` has the
+        // same layout as `u8`.
+        unsafe { &*(bytes as *const [MaybeUninit<u8>] as *const [u8]) }
+    }
+
+    #[inline(always)]
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        self.range_mut(0..CAPACITY)
+    }
+
+    #[inline(always)]
+    fn range_mut(&mut self, range: Range<usize>) -> &mut [u8] {
+        if range.end > self.initialized {
+            for byte in &mut self.bytes[self.initialized..range.end] {
+                byte.write(0);
+            }
+            self.initialized = range.end;
+        }
+        let bytes = &mut self.bytes[range];
+        // SAFETY: `range` ends at or before `initialized`, and `MaybeUninit<u8>` has the same
+        // layout as `u8`.
+        unsafe { &mut *(bytes as *mut [MaybeUninit<u8>] as *mut [u8]) }
+    }
+
+    #[inline(always)]
+    fn capacity(&self) -> usize {
+        CAPACITY
+    }
+}
+
+/// Heap storage for a `LineBufferVec`, which doubles in size whenever it fills, up to a maximum.
+#[cfg(feature = "alloc")]
+pub struct VecStorage {
+    bytes: alloc::vec::Vec<u8>,
+    max_capacity: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl Storage for VecStorage {
+    fn as_slice(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.bytes
+    }
+
+    fn try_grow(&mut self, capacity: usize) -> bool {
+        let current = self.bytes.len();
+        if current >= self.max_capacity {
+            return false;
+        }
+        let grown = capacity.max(current * 2).max(1).min(self.max_capacity);
+        self.bytes.resize(grown, 0);
+        true
+    }
+}
+
+/// Integer type used for a `LineBuffer`'s internal indices and counters.
+///
+/// Defaults to `usize`. When `CAPACITY` fits in a smaller type, using it shrinks the struct,
+/// e.g. `LineBuffer<64, u8>`. Picking a type too small for `CAPACITY` fails to compile.
+/// ```rust
+/// use strlinebuf::LineBuffer;
+///
+/// let line_buffer = LineBuffer::<64, u8>::new();
+/// ```
+/// ```compile_fail
+/// use strlinebuf::LineBuffer;
+///
+/// let line_buffer = LineBuffer::<300, u8>::new();
+/// ```
+pub trait RingIndex: Copy + PartialEq + private::Sealed {
+    /// Zero, for use in `const` constructors.
+    const ZERO: Self;
+    /// The largest value the type can hold.
+    const MAX: usize;
+    /// Convert to `usize`.
+    fn get(self) -> usize;
+    /// Convert from `usize`. Values never exceed `CAPACITY`, which is checked against `MAX`.
+    fn new(value: usize) -> Self;
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+macro_rules! impl_ring_index {
+    ($($t:ty),*) => {
+        $(
+            impl private::Sealed for $t {}
+
+            impl RingIndex for $t {
+                const ZERO: Self = 0;
+                const MAX: usize = <$t>::MAX as usize;
+
+                #[inline(always)]
+                fn get(self) -> usize {
+                    self as usize
+                }
+
+                #[inline(always)]
+                fn new(value: usize) -> Self {
+                    value as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_ring_index!(u8, u16, u32, usize);
+
+/// Line-counting state, advanced one pushed byte at a time.
+///
+/// Terminators are matched with a small KMP-style state machine, so each byte costs O(1)
+/// regardless of how the terminator was split across pushes.
+#[derive(Clone, Copy)]
+struct ScanState<I: RingIndex> {
+    /// Number of complete lines.
+    lines: I,
+    /// Length of the terminator prefix matched by the most recent bytes.
+    matched: I,
+    /// Whether the next byte is escaped.
+    escaped: bool,
+}
+
+impl<I: RingIndex> ScanState<I> {
+    const EMPTY: Self = Self {
+        lines: I::ZERO,
+        matched: I::ZERO,
+        escaped: false,
+    };
+
+    /// Advance over the next byte in logical order.
+    /// Returns `true` if it completed a terminator, which is then counted as a line.
+    fn advance(&mut self, byte: u8, scanner: &LineScanner) -> bool {
+        let terminator = scanner.terminator;
+        if terminator.is_empty() {
+            return false;
+        }
+        if self.escaped {
+            // An escaped byte is literal, so no terminator can include it.
+            self.escaped = false;
+            self.matched = I::ZERO;
+            return false;
+        }
+        self.escaped = Some(byte) == scanner.escape;
+
+        let matched = advance_match(terminator, self.matched.get(), byte, scanner.ignore_case);
+        if matched == terminator.len() {
+            self.lines = I::new(self.lines.get() + 1);
+            self.matched = I::ZERO;
+            true
+        } else {
+            self.matched = I::new(matched);
+            false
+        }
+    }
+
+    /// Advance over several bytes in logical order.
+    fn advance_slice(&mut self, bytes: &[u8], scanner: &LineScanner) {
+        if let Some(byte) = scanner.single_byte() {
+            // No partial matches or escapes to track, so only the count changes.
+            self.lines = I::new(self.lines.get() + count_byte(bytes, byte));
+            return;
+        }
+        for byte in bytes {
+            self.advance(*byte, scanner);
+        }
+    }
+}
+
+/// Splits lines out of contiguous bytes, such as a DMA buffer, with the same terminator and escape
+/// handling as the line buffers but without their ring storage. The line buffers find lines with it too.
+/// ```rust
+/// use strlinebuf::{LineScanner, Terminator};
+///
+/// let scanner = LineScanner::new(Terminator::CarriageReturnNewline);
+/// let data = b"AT\r\nOK\r";
+///
+/// let (line, consumed) = scanner.next_line(data).unwrap();
+/// assert_eq!(line, b"AT");
+/// // The rest ends part way into a terminator, so more data is needed.
+/// assert_eq!(scanner.next_line(&data[consumed..]), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineScanner {
+    terminator: &'static [u8],
+    escape: Option<u8>,
+    ignore_case: bool,
+}
+
+impl LineScanner {
+    /// Create a scanner for `terminator`, without an escape byte.
+    pub const fn new(terminator: Terminator) -> Self {
+        Self {
+            terminator: terminator.bytes(),
+            escape: None,
+            ignore_case: terminator.ignores_case(),
+        }
+    }
+
+    /// Create a scanner with the terminator and escape byte of `config`.
+    pub const fn from_config(config: &LineBufferConfig) -> Self {
+        Self {
+            terminator: config.terminator.bytes(),
+            escape: config.escape,
+            ignore_case: config.terminator.ignores_case(),
+        }
+    }
+
+    /// The terminator byte, if the terminator is a single byte matched exactly with no escape
+    /// byte to watch for, so that lines can be found by searching for the byte alone.
+    #[inline(always)]
+    fn single_byte(&self) -> Option<u8> {
+        match (self.terminator, self.escape, self.ignore_case) {
+            ([byte], None, false) => Some(*byte),
+            _ => None,
+        }
+    }
+
+    /// The first complete line in `data`, without its terminator, along with the number of bytes
+    /// it takes up including the terminator, so the next line starts at `data[consumed..]`.
+    /// Escape bytes are left in the line.
+    ///
+    /// Returns `None` if `data` holds no complete line yet, including when it ends part way into
+    /// a terminator: call again with more data appended. With `Terminator::None`, all of `data`
+    /// is one line.
+    pub fn next_line<'a>(&self, data: &'a [u8]) -> Option<(&'a [u8], usize)> {
+        if self.terminator.is_empty() {
+            return (!data.is_empty()).then_some((data, data.len()));
+        }
+        let mut state = ScanState::<usize>::EMPTY;
+        let consumed = self.scan(&mut state, data)? + 1;
+        Some((&data[..consumed - self.terminator.len()], consumed))
+    }
+
+    /// Continue `state` over `bytes`, returning the index of the byte that completes the next
+    /// terminator. Split input is scanned by passing each piece in turn with the same `state`.
+    #[inline(always)]
+    fn scan<I: RingIndex>(&self, state: &mut ScanState<I>, bytes: &[u8]) -> Option<usize> {
+        if let Some(byte) = self.single_byte() {
+            // No partial matches or escapes to carry between pieces.
+            return find_byte(bytes, byte);
+        }
+        bytes.iter().position(|byte| state.advance(*byte, self))
+    }
+}
+
+/// Split `data` into lines exactly as a line buffer with `terminator` would, without copying it.
+/// The trailing bytes not ended by a terminator are left in `SplitLines::remainder`.
+/// ```rust
+/// use strlinebuf::{split_lines, Terminator};
+///
+/// let mut lines = split_lines(b"one\r\ntwo\r\nthr", Terminator::CarriageReturnNewline);
+/// assert_eq!(lines.next(), Some(&b"one"[..]));
+/// assert_eq!(lines.next(), Some(&b"two"[..]));
+/// assert_eq!(lines.next(), None);
+/// assert_eq!(lines.remainder(), b"thr");
+/// ```
+pub fn split_lines(data: &[u8], terminator: Terminator) -> SplitLines<'_> {
+    SplitLines {
+        rest: data,
+        scanner: LineScanner::new(terminator),
+    }
+}
+
+/// Iterator over the lines of a slice, created by [`split_lines`].
+pub struct SplitLines<'a> {
+    rest: &'a [u8],
+    scanner: LineScanner,
+}
+
+impl<'a> SplitLines<'a> {
+    /// The bytes not yet returned as part of a line. Once the iterator is exhausted, this is the
+    /// trailing partial line.
+    pub fn remainder(&self) -> &'a [u8] {
+        self.rest
+    }
+}
+
+impl<'a> Iterator for SplitLines<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (line, consumed) = self.scanner.next_line(self.rest)?;
+        self.rest = &self.rest[consumed..];
+        Some(line)
+    }
+}
+
+/// Iterator over the logical offset and length of each complete line in a line buffer, created by
+/// `RawLineBuffer::line_positions`.
+pub struct LinePositions<'a> {
+    first: &'a [u8],
+    second: &'a [u8],
+    scanner: LineScanner,
+    /// Logical offset of the next line.
+    offset: usize,
+}
+
+impl Iterator for LinePositions<'_> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let len = self.first.len() + self.second.len();
+        if self.offset >= len {
+            return None;
+        }
+        let terminator_len = self.scanner.terminator.len();
+        if terminator_len == 0 {
+            let line = (self.offset, len - self.offset);
+            self.offset = len;
+            return Some(line);
+        }
+
+        let first = self.first.get(self.offset..).unwrap_or(&[]);
+        let second = &self.second[self.offset.saturating_sub(self.first.len())..];
+        let mut state = ScanState::<usize>::EMPTY;
+        let end = match self.scanner.scan(&mut state, first) {
+            Some(i) => i,
+            None => match self.scanner.scan(&mut state, second) {
+                Some(i) => first.len() + i,
+                None => {
+                    // Only a partial line is left.
+                    self.offset = len;
+                    return None;
+                }
+            },
+        };
+        let line = (self.offset, end + 1 - terminator_len);
+        self.offset += end + 1;
+        Some(line)
+    }
+}
+
+/// Iterator over the buffered bytes of a line buffer in chunks of up to `chunk_size` bytes, in
+/// logical order, created by `RawLineBuffer::chunks`. Each chunk is two slices, the second only
+/// non-empty for the chunk that wraps around the end of the storage.
+pub struct Chunks<'a> {
+    first: &'a [u8],
+    second: &'a [u8],
+    chunk_size: usize,
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = (&'a [u8], &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.first.is_empty() {
+            return None;
+        }
+        let (head, first) = self.first.split_at(self.first.len().min(self.chunk_size));
+        let (tail, second) = self
+            .second
+            .split_at((self.chunk_size - head.len()).min(self.second.len()));
+        (self.first, self.second) = if first.is_empty() {
+            (second, &[][..])
+        } else {
+            (first, second)
+        };
+        Some((head, tail))
+    }
+}
+
+/// Hands out the buffered bytes of a line buffer a chunk at a time, dropping them only as they
+/// are acknowledged, created by `RawLineBuffer::drain_chunks`.
+pub struct DrainChunks<'a, S: Storage, I: RingIndex, T: TerminatorSpec> {
+    line_buffer: &'a mut RawLineBuffer<S, I, T>,
+    chunk_size: usize,
+}
+
+impl<S: Storage, I: RingIndex, T: TerminatorSpec> DrainChunks<'_, S, I, T> {
+    /// The next chunk of up to `chunk_size` bytes, as one or two slices, or `None` once the
+    /// buffer is empty. It stays buffered until `consume` is called.
+    pub fn next_chunk(&self) -> Option<(&[u8], &[u8])> {
+        self.line_buffer.chunks(self.chunk_size).next()
+    }
+
+    /// Drop the first `count` bytes, such as the part of a chunk that was sent and acknowledged.
+    pub fn consume(&mut self, count: usize) {
+        let count = count.min(self.line_buffer.len());
+        #[cfg(feature = "stats")]
+        {
+            self.line_buffer.stats.bytes_read += count as u64;
+        }
+        self.line_buffer.consume(count);
+    }
+}
+
+/// The next line of a line buffer, borrowed in place as a `&str`, created by
+/// `RawLineBuffer::next_line_str`. The line stays buffered until `consume` is called.
+pub struct LineStr<'a, S: Storage, I: RingIndex, T: TerminatorSpec> {
+    line_buffer: &'a mut RawLineBuffer<S, I, T>,
+    line_len: usize,
+    terminator_len: usize,
+}
+
+impl<S: Storage, I: RingIndex, T: TerminatorSpec> LineStr<'_, S, I, T> {
+    /// The line, without its terminator.
+    pub fn as_str(&self) -> &str {
+        let (line, _) = self.line_buffer.slices(0, self.line_len);
+        // SAFETY: `next_line_str` made the line contiguous and checked that it is valid UTF-8,
+        // and the buffer can't change while it is borrowed here.
+        unsafe { core::str::from_utf8_unchecked(line) }
+    }
+
+    /// Drop the line, along with its terminator, from the buffer.
+    pub fn consume(self) {
+        self.line_buffer
+            .finish_read(self.line_len, self.terminator_len);
+    }
+}
+
+impl<S: Storage, I: RingIndex, T: TerminatorSpec> core::ops::Deref for LineStr<'_, S, I, T> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+/// Extend a partial match of the first `matched` bytes of `pattern` by `byte`.
+/// Returns the length of the longest prefix of `pattern` that the bytes seen so far end with.
+///
+/// With `ignore_case`, ASCII letters match either case.
+///
+/// Requires `matched < pattern.len()`.
+fn advance_match(pattern: &[u8], mut matched: usize, byte: u8, ignore_case: bool) -> usize {
+    let eq = |a: &[u8], b: &[u8]| match ignore_case {
+        true => a.eq_ignore_ascii_case(b),
+        false => a == b,
+    };
+    loop {
+        if eq(&pattern[matched..matched + 1], &[byte]) {
+            return matched + 1;
+        }
+        if matched == 0 {
+            return 0;
+        }
+        // Fall back to the longest proper prefix of the match that is also a suffix of it.
+        matched = (1..matched)
+            .rev()
+            .find(|len| eq(&pattern[..*len], &pattern[matched - len..matched]))
+            .unwrap_or(0);
+    }
+}
+
+/// Position of the first `needle` in `haystack`.
+#[cfg(not(feature = "fast-scan"))]
+fn find_byte(haystack: &[u8], needle: u8) -> Option<usize> {
+    haystack.iter().position(|byte| *byte == needle)
+}
+
+/// Number of times `needle` occurs in `haystack`.
+#[cfg(not(feature = "fast-scan"))]
+fn count_byte(haystack: &[u8], needle: u8) -> usize {
+    haystack.iter().filter(|byte| **byte == needle).count()
+}
+
+/// Size of the words compared at once by the `fast-scan` search.
+#[cfg(feature = "fast-scan")]
+const WORD: usize = core::mem::size_of::<usize>();
+
+/// Split `haystack` into a head before the first word-aligned address, the aligned words, and a tail.
+#[cfg(feature = "fast-scan")]
+fn split_aligned(haystack: &[u8]) -> (&[u8], &[u8], &[u8]) {
+    let head = haystack.as_ptr().align_offset(WORD).min(haystack.len());
+    let (head, rest) = haystack.split_at(head);
+    let (words, tail) = rest.split_at(rest.len() - rest.len() % WORD);
+    (head, words, tail)
+}
+
+/// Mark each byte of `word` equal to `needle` by setting its high bit, and clear all other bits.
+///
+/// Unlike the usual `(x - 0x01..) & !x & 0x80..` test, this never carries into the next byte,
+/// so every marked byte is an exact match and the marks can be counted.
+#[cfg(feature = "fast-scan")]
+fn match_mask(word: [u8; WORD], needle: u8) -> usize {
+    const LOW: usize = usize::MAX / 0xff * 0x7f;
+    let x = usize::from_ne_bytes(word) ^ (usize::MAX / 0xff * needle as usize);
+    !(((x & LOW) + LOW) | x | LOW)
+}
+
+/// Position of the first `needle` in `haystack`, comparing a word at a time once aligned.
+#[cfg(feature = "fast-scan")]
+fn find_byte(haystack: &[u8], needle: u8) -> Option<usize> {
+    let (head, words, tail) = split_aligned(haystack);
+    if let Some(i) = head.iter().position(|byte| *byte == needle) {
+        return Some(i);
+    }
+    for (i, word) in words.chunks_exact(WORD).enumerate() {
+        let mask = match_mask(word.try_into().unwrap(), needle);
+        if mask != 0 {
+            let byte = if cfg!(target_endian = "little") {
+                mask.trailing_zeros()
+            } else {
+                mask.leading_zeros()
+            } as usize
+                / 8;
+            return Some(head.len() + i * WORD + byte);
+        }
+    }
+    tail.iter()
+        .position(|byte| *byte == needle)
+        .map(|i| haystack.len() - tail.len() + i)
+}
+
+/// Number of times `needle` occurs in `haystack`, comparing a word at a time once aligned.
+#[cfg(feature = "fast-scan")]
+fn count_byte(haystack: &[u8], needle: u8) -> usize {
+    let (head, words, tail) = split_aligned(haystack);
+    let ends = head.iter().chain(tail).filter(|byte| **byte == needle);
+    let middle = words
+        .chunks_exact(WORD)
+        .map(|word| match_mask(word.try_into().unwrap(), needle).count_ones() as usize);
+    ends.count() + middle.sum::<usize>()
+}
+
+impl<const CAPACITY: usize, I: RingIndex, T: TerminatorSpec> Default
+    for LineBuffer<CAPACITY, I, T>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const CAPACITY: usize, I: RingIndex, T: TerminatorSpec> LineBuffer<CAPACITY, I, T> {
+    /// Create a new LineBuffer with the specified capacity and terminator.
+    /// Example:
+    /// ```rust
+    /// use strlinebuf::{LineBuffer, LineBufferConfig, Terminator};
+    ///
+    /// let line_buffer = LineBuffer::<10>::new();
+    /// ```
+    /// The above example creates a new LineBuffer with a capacity of 10 and a newline terminator.
+    ///
+    /// Being a `const fn`, it can also initialize a `static`:
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// static mut RX_BUFFER: LineBuffer<256> = LineBuffer::new();
+    /// ```
+    ///
+    /// A zero capacity is rejected at compile time:
+    /// ```compile_fail
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let line_buffer = LineBuffer::<0>::new();
+    /// ```
+    pub const fn new() -> Self {
+        Self::new_with_config(&LineBufferConfig::DEFAULT)
+    }
+
+    /// Create a new LineBuffer that overwrites its oldest bytes when full instead of rejecting
+    /// new ones, with the default newline terminator. Handy for keeping the latest log output.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<8>::new_overwriting();
+    /// line_buffer.push_bytes(b"old\nnew\nlast").unwrap();
+    /// assert_eq!(line_buffer.len(), 8);
+    /// ```
+    pub const fn new_overwriting() -> Self {
+        Self::new_with_config(&LineBufferConfig {
+            overflow: OverflowPolicy::OverwriteOldest,
+            ..LineBufferConfig::DEFAULT
+        })
+    }
+
+    /// Create a new LineBuffer with the specified capacity and terminator.
+    /// Example:
+    /// ```rust
+    /// use strlinebuf::{LineBuffer, LineBufferConfig, Terminator};
+    ///
+    /// let line_buffer = LineBuffer::<10>::new_with_config(&LineBufferConfig {
+    ///    terminator: Terminator::CarriageReturn,
+    ///    ..LineBufferConfig::DEFAULT
+    /// });
+    /// ```
+    /// The above example creates a new LineBuffer with a capacity of 10 and a CR terminator.
+    pub const fn new_with_config(config: &'static LineBufferConfig) -> Self {
+        const { assert!(CAPACITY > 0, "LineBuffer capacity must be > 0") }
+        const {
+            assert!(
+                CAPACITY <= I::MAX,
+                "CAPACITY does not fit in the index type"
+            )
+        }
+        Self::from_storage([0u8; CAPACITY], config)
+    }
+
+    /// Read a line from the buffer into a provided auxiliary buffer of at least the same capacity.
+    /// Returns the number of bytes read.
+    ///
+    /// Note: The transferred contents will not include the terminator character(s), nor any escape bytes.
+    /// With `Terminator::None`, everything buffered is read.
+    pub fn read_line_bytes(
+        &mut self,
+        aux_buffer: &mut [u8; CAPACITY],
+    ) -> Result<usize, LineBufferRxError> {
+        self.read_line_into(aux_buffer)
+    }
+
+    /// Create a LineBuffer from the state written by `save_state`, with the same configuration
+    /// and buffered bytes, so it reads the same lines. Any bytes after the saved state in `data`
+    /// are ignored.
+    ///
+    /// The buffer uses `config`, which must match the saved configuration, see
+    /// `LineBufferConfig::from_state`, or `RestoreError::ConfigMismatch` is returned. Its
+    /// `line_handler` isn't called for the restored lines.
+    /// Fails with `RestoreError::TooLarge` if more bytes were saved than `CAPACITY` holds.
+    /// ```rust
+    /// use strlinebuf::{LineBuffer, LineBufferConfig, RestoreError, Terminator};
+    ///
+    /// let mut line_buffer = LineBuffer::<16>::new();
+    /// line_buffer.push_bytes(b"OK\nERROR\n").unwrap();
+    ///
+    /// let mut saved = [0u8; 64];
+    /// line_buffer.save_state(&mut saved).unwrap();
+    ///
+    /// let mut restored = LineBuffer::<16>::restore_state(&saved, &LineBufferConfig::DEFAULT).unwrap();
+    /// let mut aux_buffer = [0u8; 16];
+    /// let len = restored.read_line_bytes(&mut aux_buffer).unwrap();
+    /// assert_eq!(&aux_buffer[..len], b"OK");
+    ///
+    /// static CR: LineBufferConfig = LineBufferConfig {
+    ///     terminator: Terminator::CarriageReturn,
+    ///     ..LineBufferConfig::DEFAULT
+    /// };
+    /// assert_eq!(
+    ///     LineBuffer::<16>::restore_state(&saved, &CR).err(),
+    ///     Some(RestoreError::ConfigMismatch)
+    /// );
+    /// assert_eq!(
+    ///     LineBuffer::<8>::restore_state(&saved, &LineBufferConfig::DEFAULT).err(),
+    ///     Some(RestoreError::TooLarge { len: 9, capacity: 8 })
+    /// );
+    /// ```
+    pub fn restore_state(
+        data: &[u8],
+        config: &'static LineBufferConfig,
+    ) -> Result<Self, RestoreError> {
+        LineBufferConfig::from_state(data)?;
+        let header = &data[..STATE_HEADER_LEN];
+        match config.to_state() {
+            Ok(state) if state == header[..STATE_CONFIG_LEN] => {}
+            _ => return Err(RestoreError::ConfigMismatch),
+        }
+        let len = state_usize(header, STATE_CONFIG_LEN)?;
+        if len > CAPACITY {
+            return Err(RestoreError::TooLarge {
+                len,
+                capacity: CAPACITY,
+            });
+        }
+        let bytes = data
+            .get(STATE_HEADER_LEN..STATE_HEADER_LEN + len)
+            .ok_or(RestoreError::Corrupt)?;
+
+        // Bytes that were saved are taken as they are, without the line handler seeing them.
+        let mut line_buffer = Self::new_with_config(&LineBufferConfig::DEFAULT);
+        let pushed = line_buffer.push_bytes(bytes);
+        debug_assert!(pushed.is_ok());
+        line_buffer.set_config(config);
+        line_buffer.update_flow();
+        Ok(line_buffer)
+    }
+
+    /// Move the buffered bytes and the configuration to a new LineBuffer with a capacity of `M`,
+    /// which may be larger or smaller.
+    ///
+    /// If the bytes don't fit in `M`, this buffer is handed back untouched along with
+    /// `LineBufferTxError::BufferFull`. The `line_handler` isn't called again for lines it has
+    /// already seen, and `stats` and `rewind` history start afresh.
+    /// ```rust
+    /// use strlinebuf::{LineBuffer, LineBufferTxError};
+    ///
+    /// let mut line_buffer = LineBuffer::<8>::new();
+    /// line_buffer.push_bytes(b"OK\n+CSQ").unwrap();
+    ///
+    /// let Err((line_buffer, error)) = line_buffer.resize::<4>() else {
+    ///     panic!("7 bytes don't fit in 4");
+    /// };
+    /// assert!(matches!(error, LineBufferTxError::BufferFull { .. }));
+    ///
+    /// let Ok(mut line_buffer) = line_buffer.resize::<64>() else {
+    ///     panic!("7 bytes fit in 64");
+    /// };
+    /// line_buffer.push_bytes(b": 21,99\n").unwrap();
+    /// assert_eq!(line_buffer.line_count(), 2);
+    /// ```
+    pub fn resize<const M: usize>(self) -> Result<LineBuffer<M, I, T>, (Self, LineBufferTxError)> {
+        if self.len() > M {
+            let error = LineBufferTxError::BufferFull {
+                requested: self.len(),
+                available: M,
+                written: 0,
+            };
+            return Err((self, error));
+        }
+        // Bytes are moved as they are, without the line handler seeing them again.
+        let mut resized = LineBuffer::<M, I, T>::new_with_config(&LineBufferConfig::DEFAULT);
+        let cloned = self.clone_contents_into(&mut resized);
+        debug_assert!(cloned.is_ok());
+        resized.set_config(self.config);
+        resized.update_flow();
+        resized.mid_line = self.mid_line;
+        resized.terminator_left = self.terminator_left;
+        resized.idle_flush = self.idle_flush;
+
+        Ok(resized)
+    }
+}
+
+impl<const CAPACITY: usize, I: RingIndex, T: TerminatorSpec> Default
+    for LineBufferUninit<CAPACITY, I, T>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const CAPACITY: usize, I: RingIndex, T: TerminatorSpec> LineBufferUninit<CAPACITY, I, T> {
+    /// Create a new LineBufferUninit with the specified capacity and the default configuration.
+    ///
+    /// Like `LineBuffer::new`, it can initialize a `static`, and rejects a zero capacity at
+    /// compile time.
+    pub const fn new() -> Self {
+        Self::new_with_config(&LineBufferConfig::DEFAULT)
+    }
+
+    /// Create a new LineBufferUninit with the specified capacity and configuration.
+    pub const fn new_with_config(config: &'static LineBufferConfig) -> Self {
+        const { assert!(CAPACITY > 0, "LineBuffer capacity must be > 0") }
+        const {
+            assert!(
+                CAPACITY <= I::MAX,
+                "CAPACITY does not fit in the index type"
+            )
+        }
+        let storage = UninitStorage {
+            bytes: [const { MaybeUninit::uninit() }; CAPACITY],
+            initialized: 0,
+        };
+        Self::from_storage(storage, config)
+    }
+
+    /// Read a line from the buffer into a provided auxiliary buffer of at least the same capacity.
+    /// Returns the number of bytes read.
+    ///
+    /// Note: The transferred contents will not include the terminator character(s), nor any escape bytes.
+    /// With `Terminator::None`, everything buffered is read.
+    pub fn read_line_bytes(
+        &mut self,
+        aux_buffer: &mut [u8; CAPACITY],
+    ) -> Result<usize, LineBufferRxError> {
+        self.read_line_into(aux_buffer)
+    }
+}
+
+/// A `LineBuffer` for a `static`, handed out as `&'static mut` exactly once, so it can be given to
+/// an interrupt handler at init without `static mut` or any unsafe code.
+/// ```rust
+/// use strlinebuf::{LineBuffer, StaticLineBuffer};
+///
+/// static RX: StaticLineBuffer<512> = StaticLineBuffer::new();
+///
+/// /// State owned by the UART interrupt handler.
+/// struct UartIsr {
+///     rx: &'static mut LineBuffer<512>,
+/// }
+///
+/// impl UartIsr {
+///     fn on_byte(&mut self, byte: u8) {
+///         let _ = self.rx.push_byte(byte);
+///     }
+/// }
+///
+/// // At init, main takes the buffer and moves it into the handler's state.
+/// let mut isr = UartIsr { rx: RX.take().unwrap() };
+/// assert!(RX.take().is_none());
+///
+/// for byte in b"AT\r\n" {
+///     isr.on_byte(*byte);
+/// }
+/// assert!(isr.rx.has_complete_line());
+/// ```
+#[cfg(target_has_atomic = "8")]
+pub struct StaticLineBuffer<
+    const CAPACITY: usize,
+    I: RingIndex = usize,
+    T: TerminatorSpec = ConfigTerminator,
+> {
+    line_buffer: UnsafeCell<LineBuffer<CAPACITY, I, T>>,
+    taken: AtomicBool,
+}
+
+// SAFETY: the buffer is only reachable through the one `&mut` that `take` hands out, so sharing
+// the wrapper between threads or interrupts never shares the buffer.
+#[cfg(target_has_atomic = "8")]
+unsafe impl<const CAPACITY: usize, I: RingIndex, T: TerminatorSpec> Sync
+    for StaticLineBuffer<CAPACITY, I, T>
+where
+    LineBuffer<CAPACITY, I, T>: Send,
+{
+}
+
+#[cfg(target_has_atomic = "8")]
+impl<const CAPACITY: usize, I: RingIndex, T: TerminatorSpec> Default
+    for StaticLineBuffer<CAPACITY, I, T>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(target_has_atomic = "8")]
+impl<const CAPACITY: usize, I: RingIndex, T: TerminatorSpec> StaticLineBuffer<CAPACITY, I, T> {
+    /// Create a new StaticLineBuffer with the default configuration.
+    pub const fn new() -> Self {
+        Self::new_with_config(&LineBufferConfig::DEFAULT)
+    }
+
+    /// Create a new StaticLineBuffer with the specified configuration.
+    pub const fn new_with_config(config: &'static LineBufferConfig) -> Self {
+        Self {
+            line_buffer: UnsafeCell::new(LineBuffer::new_with_config(config)),
+            taken: AtomicBool::new(false),
+        }
+    }
+
+    /// Take the buffer. Only the first call returns it; every later call returns `None`.
+    #[allow(clippy::mut_from_ref)] // The `taken` flag makes the `&mut` unique.
+    pub fn take(&'static self) -> Option<&'static mut LineBuffer<CAPACITY, I, T>> {
+        if self.taken.swap(true, Ordering::AcqRel) {
+            return None;
+        }
+        // SAFETY: `taken` was false, so this is the only reference ever handed out.
+        Some(unsafe { &mut *self.line_buffer.get() })
+    }
+}
+
+/// A `LineBuffer` that can be pushed to and read from through shared references, for single-core
+/// code where nothing touches it from an interrupt, so no critical section is needed.
+///
+/// It isn't `Sync`, so it can't be put in a `static` or shared with an interrupt handler. Using it
+/// again from inside one of its own closures, such as reading inside `with_line`, panics.
+/// ```rust
+/// use strlinebuf::LocalLineBuffer;
+///
+/// let rx = LocalLineBuffer::<64>::new();
+/// let (uart, parser) = (&rx, &rx);
+///
+/// uart.push_bytes(b"AT\r\n").unwrap();
+/// let mut aux_buffer = [0u8; 64];
+/// let bytes_read = parser.read_line_bytes(&mut aux_buffer).unwrap();
+/// assert_eq!(&aux_buffer[..bytes_read], b"AT\r");
+/// ```
+///
+/// ```compile_fail
+/// use strlinebuf::LocalLineBuffer;
+///
+/// static RX: LocalLineBuffer<64> = LocalLineBuffer::new();
+/// ```
+pub struct LocalLineBuffer<
+    const CAPACITY: usize,
+    I: RingIndex = usize,
+    T: TerminatorSpec = ConfigTerminator,
+> {
+    line_buffer: RefCell<LineBuffer<CAPACITY, I, T>>,
+}
+
+impl<const CAPACITY: usize, I: RingIndex, T: TerminatorSpec> Default
+    for LocalLineBuffer<CAPACITY, I, T>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const CAPACITY: usize, I: RingIndex, T: TerminatorSpec> LocalLineBuffer<CAPACITY, I, T> {
+    /// Create a new LocalLineBuffer with the default configuration.
+    pub const fn new() -> Self {
+        Self::new_with_config(&LineBufferConfig::DEFAULT)
+    }
+
+    /// Create a new LocalLineBuffer with the specified configuration.
+    pub const fn new_with_config(config: &'static LineBufferConfig) -> Self {
+        Self {
+            line_buffer: RefCell::new(LineBuffer::new_with_config(config)),
+        }
+    }
+
+    /// Run `f` with the underlying `LineBuffer`, for anything not forwarded here.
+    ///
+    /// Panics if called from inside another closure of this buffer.
+    pub fn with<R>(&self, f: impl FnOnce(&mut LineBuffer<CAPACITY, I, T>) -> R) -> R {
+        let mut line_buffer = self
+            .line_buffer
+            .try_borrow_mut()
+            .expect("LocalLineBuffer used again from inside one of its own closures");
+        f(&mut line_buffer)
+    }
+
+    /// Push a byte, see `LineBuffer::push_byte`.
+    pub fn push_byte(&self, byte: u8) -> Result<(), LineBufferTxError> {
+        self.with(|line_buffer| line_buffer.push_byte(byte))
+    }
+
+    /// Push bytes, see `LineBuffer::push_bytes`.
+    pub fn push_bytes(&self, bytes: &[u8]) -> Result<(), LineBufferTxError> {
+        self.with(|line_buffer| line_buffer.push_bytes(bytes))
+    }
+
+    /// Read a line, see `LineBuffer::read_line_bytes`.
+    pub fn read_line_bytes(
+        &self,
+        aux_buffer: &mut [u8; CAPACITY],
+    ) -> Result<usize, LineBufferRxError> {
+        self.with(|line_buffer| line_buffer.read_line_bytes(aux_buffer))
+    }
+
+    /// Run `f` on the next line in place, see `LineBuffer::with_line`.
+    ///
+    /// Panics if `f` uses this buffer again.
+    pub fn with_line<R>(&self, f: impl FnOnce(&[u8], &[u8]) -> R) -> Result<R, LineBufferRxError> {
+        self.with(|line_buffer| line_buffer.with_line(f))
+    }
+
+    /// Whether a complete line is buffered, see `LineBuffer::has_complete_line`.
+    pub fn has_complete_line(&self) -> bool {
+        self.with(|line_buffer| line_buffer.has_complete_line())
+    }
+
+    /// The number of bytes buffered.
+    pub fn len(&self) -> usize {
+        self.with(|line_buffer| line_buffer.len())
+    }
+
+    /// Whether the buffer is empty.
+    pub fn is_empty(&self) -> bool {
+        self.with(|line_buffer| line_buffer.is_empty())
+    }
+}
+
+impl<'a> LineBufferSlice<'a> {
+    /// Create a new LineBufferSlice over `buffer`, with the default configuration.
+    pub fn new(buffer: &'a mut [u8]) -> Self {
+        Self::new_with_config(buffer, &LineBufferConfig::DEFAULT)
+    }
+
+    /// Create a new LineBufferSlice over `buffer`, with the specified configuration.
+    pub fn new_with_config(buffer: &'a mut [u8], config: &'static LineBufferConfig) -> Self {
+        Self::new_typed(buffer, config)
+    }
+}
+
+impl<'a, I: RingIndex, T: TerminatorSpec> LineBufferSlice<'a, I, T> {
+    /// Create a new LineBufferSlice over `buffer` with a different index type or a compile-time
+    /// terminator, which can't be inferred from `new` and `new_with_config`.
+    /// ```rust
+    /// use strlinebuf::{LineBufferConfig, LineBufferSlice};
+    ///
+    /// let mut storage = [0u8; 64];
+    /// let line_buffer = LineBufferSlice::<u8>::new_typed(&mut storage, &LineBufferConfig::DEFAULT);
+    /// assert_eq!(line_buffer.capacity(), 64);
+    /// ```
+    ///
+    /// Panics if the length of `buffer` does not fit in the index type.
+    pub fn new_typed(buffer: &'a mut [u8], config: &'static LineBufferConfig) -> Self {
+        assert!(
+            buffer.len() <= I::MAX,
+            "buffer does not fit in the index type"
+        );
+        Self::from_storage(buffer, config)
+    }
+
+    /// Read a line from the buffer into a provided auxiliary buffer of at least the same capacity.
+    /// A smaller buffer works as long as the line fits, otherwise `LineBufferRxError::AuxBufferTooSmall` is returned.
+    /// Returns the number of bytes read.
+    ///
+    /// Note: The transferred contents will not include the terminator character(s), nor any escape bytes.
+    /// With `Terminator::None`, everything buffered is read.
+    pub fn read_line_bytes(&mut self, aux_buffer: &mut [u8]) -> Result<usize, LineBufferRxError> {
+        self.read_line_into(aux_buffer)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl LineBufferVec {
+    /// Create a new LineBufferVec starting at `capacity` bytes, free to grow without limit.
+    pub fn new(capacity: usize) -> Self {
+        Self::new_with_config(capacity, None, &LineBufferConfig::DEFAULT)
+    }
+
+    /// Create a new LineBufferVec starting at `capacity` bytes, growing to at most `max_capacity`.
+    pub fn with_max_capacity(capacity: usize, max_capacity: usize) -> Self {
+        Self::new_with_config(capacity, Some(max_capacity), &LineBufferConfig::DEFAULT)
+    }
+
+    /// Create a new LineBufferVec starting at `capacity` bytes, growing to at most `max_capacity`,
+    /// with the specified configuration.
+    pub fn new_with_config(
+        capacity: usize,
+        max_capacity: Option<usize>,
+        config: &'static LineBufferConfig,
+    ) -> Self {
+        Self::new_typed(capacity, max_capacity, config)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<I: RingIndex, T: TerminatorSpec> LineBufferVec<I, T> {
+    /// Create a new LineBufferVec with a different index type or a compile-time terminator,
+    /// which can't be inferred from `new` and `new_with_config`. It starts at `capacity` bytes,
+    /// growing to at most `max_capacity` (or as far as the index type allows).
+    /// ```rust
+    /// use strlinebuf::{LineBufferConfig, LineBufferVec};
+    ///
+    /// let line_buffer = LineBufferVec::<u8>::new_typed(16, None, &LineBufferConfig::DEFAULT);
+    /// assert_eq!(line_buffer.capacity(), 16);
+    /// ```
+    ///
+    /// Panics if `capacity` does not fit in the index type.
+    pub fn new_typed(
+        capacity: usize,
+        max_capacity: Option<usize>,
+        config: &'static LineBufferConfig,
+    ) -> Self {
+        assert!(
+            capacity <= I::MAX,
+            "capacity does not fit in the index type"
+        );
+        let storage = VecStorage {
+            bytes: alloc::vec![0u8; capacity],
+            max_capacity: max_capacity.unwrap_or(usize::MAX).min(I::MAX),
+        };
+        Self::from_storage(storage, config)
+    }
+
+    /// Read a line from the buffer into a provided auxiliary buffer.
+    /// If the line doesn't fit, `LineBufferRxError::AuxBufferTooSmall` is returned.
+    /// Returns the number of bytes read.
+    ///
+    /// Note: The transferred contents will not include the terminator character(s), nor any escape bytes.
+    /// With `Terminator::None`, everything buffered is read.
+    pub fn read_line_bytes(&mut self, aux_buffer: &mut [u8]) -> Result<usize, LineBufferRxError> {
+        self.read_line_into(aux_buffer)
+    }
+}
+
+impl<S: Storage, I: RingIndex, T: TerminatorSpec> RawLineBuffer<S, I, T> {
+    /// Wrap `buffer` in an empty line buffer. Callers check that its capacity fits in `I`.
+    const fn from_storage(buffer: S, config: &'static LineBufferConfig) -> Self {
+        Self {
+            buffer,
+            scan: ScanState::EMPTY,
+            config_changed: false,
+            config,
+            start: I::ZERO,
+            end: I::ZERO,
+            empty: true,
+            rewindable: I::ZERO,
+            rewind_lost: false,
+            high_watermark: I::ZERO,
+            flow: FlowState::Flowing,
+            flow_change: None,
+            truncated: false,
+            in_line_handler: false,
+            mid_line: false,
+            terminator_left: false,
+            scan_pos: I::ZERO,
+            scan_pos_state: ScanState::EMPTY,
+            idle_flush: None,
+            #[cfg(feature = "stats")]
+            stats: LineBufferStats::ZERO,
+            terminator: PhantomData,
+        }
+    }
+
+    /// The configuration the buffer was created with or last given by `set_config`.
+    pub fn config(&self) -> &'static LineBufferConfig {
+        self.config
+    }
+
+    /// Switch to `config`. The buffered bytes stay, and are split into lines by the new
+    /// terminator and escape byte from the next push or read on.
+    /// ```rust
+    /// use strlinebuf::{LineBuffer, LineBufferConfig, Terminator};
+    ///
+    /// static CR: LineBufferConfig = LineBufferConfig {
+    ///     terminator: Terminator::CarriageReturn,
+    ///     ..LineBufferConfig::DEFAULT
+    /// };
+    ///
+    /// let mut line_buffer = LineBuffer::<16>::new();
+    /// line_buffer.push_bytes(b"AT\rOK\r").unwrap();
+    /// assert_eq!(line_buffer.line_count(), 0);
+    /// line_buffer.set_config(&CR);
+    /// assert_eq!(line_buffer.line_count(), 2);
+    /// ```
+    pub fn set_config(&mut self, config: &'static LineBufferConfig) {
+        self.config = config;
+        self.config_changed = true;
+    }
+
+    /// Number of bytes the buffer can hold.
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    /// Check if the buffer is empty.
+    /// The buffer is empty if the start and end pointers are equal.
+    pub fn is_empty(&self) -> bool {
+        self.empty
+    }
+
+    /// Check if the buffer is full.
+    pub fn is_full(&self) -> bool {
+        !self.empty && self.start == self.end
+    }
+
+    /// Number of bytes currently stored in the buffer.
+    pub fn len(&self) -> usize {
+        if self.empty {
+            0
+        } else if self.end.get() > self.start.get() {
+            self.end.get() - self.start.get()
+        } else {
+            self.capacity() - self.start.get() + self.end.get()
+        }
+    }
+
+    /// Number of bytes `read_exact_bytes` can take, which is all of them, whatever lines they
+    /// make up.
+    pub fn available(&self) -> usize {
+        self.len()
+    }
+
+    /// Number of bytes that can still be pushed before the buffer is full.
+    pub fn remaining_capacity(&self) -> usize {
+        self.capacity() - self.len()
+    }
+
+    /// Number of bytes that can be written contiguously starting at the write position,
+    /// before the free region wraps around the end of the storage.
+    pub fn contiguous_write_len(&self) -> usize {
+        self.remaining_capacity()
+            .min(self.capacity() - self.end.get())
+    }
+
+    /// The most bytes the buffer has held at once, since it was created or `reset_high_watermark` was called.
+    /// Useful for sizing `CAPACITY` from how full buffers get in practice.
+    pub fn high_watermark(&self) -> usize {
+        self.high_watermark.get()
+    }
+
+    /// Restart high watermark tracking from the current length.
+    pub fn reset_high_watermark(&mut self) {
+        self.high_watermark = I::new(self.len());
+    }
+
+    /// Whether the sender should be paused, according to `config.flow_thresholds`.
+    /// Always `FlowState::Flowing` without thresholds.
+    pub fn flow_state(&self) -> FlowState {
+        self.flow
+    }
+
+    /// The change of `flow_state` since the last call, if any, for toggling RTS or sending XON/XOFF.
+    ///
+    /// Each crossing is reported exactly once. If the buffer paused and resumed again in between
+    /// calls, the two cancel out and nothing is reported.
+    /// ```rust
+    /// use strlinebuf::{FlowChange, FlowThresholds, LineBuffer, LineBufferConfig};
+    ///
+    /// let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+    ///     flow_thresholds: Some(FlowThresholds { high: 12, low: 4 }),
+    ///     ..LineBufferConfig::DEFAULT
+    /// });
+    /// line_buffer.push_bytes(b"0123456789\nabc").unwrap();
+    /// assert_eq!(line_buffer.poll_flow_change(), Some(FlowChange::Pause));
+    /// assert_eq!(line_buffer.poll_flow_change(), None);
+    ///
+    /// line_buffer.read_line_bytes(&mut [0u8; 16]).unwrap();
+    /// assert_eq!(line_buffer.poll_flow_change(), Some(FlowChange::Resume));
+    /// ```
+    pub fn poll_flow_change(&mut self) -> Option<FlowChange> {
+        self.flow_change.take()
+    }
+
+    /// Number of bytes that can be read contiguously starting at the read position,
+    /// before the buffered data wraps around the end of the storage.
+    pub fn contiguous_read_len(&self) -> usize {
+        self.len().min(self.capacity() - self.start.get())
+    }
+
+    /// Make the buffered data one contiguous slice and return it.
+    ///
+    /// If the data wraps around the end of the storage, the storage is rotated in place so that
+    /// the data starts at index 0. This is O(capacity) and needs no extra memory.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<8>::new();
+    /// line_buffer.push_bytes(b"abcdef\n").unwrap();
+    /// line_buffer.read_line_bytes(&mut [0u8; 8]).unwrap();
+    /// line_buffer.push_bytes(b"ghi\nj").unwrap();
+    /// assert_eq!(line_buffer.contiguous_read_len(), 1);
+    ///
+    /// assert_eq!(line_buffer.make_contiguous(), b"ghi\nj");
+    /// assert_eq!(line_buffer.contiguous_read_len(), 5);
+    /// ```
+    pub fn make_contiguous(&mut self) -> &mut [u8] {
+        let len = self.len();
+        let start = self.start.get();
+        if len > 0 && self.end.get() <= start {
+            // Rotating the whole storage keeps the already-read bytes just before `start` too,
+            // so `rewind` still works.
+            let capacity = self.capacity();
+            self.buffer.as_mut_slice()[..capacity].rotate_left(start);
+            self.start = I::ZERO;
+            self.end = I::new(self.wrap_add(0, len));
+            return self.buffer.range_mut(0..len);
+        }
+        self.buffer.range_mut(start..start + len)
+    }
+
+    /// Check if a complete line is available to be read.
+    ///
+    /// Note: With `Terminator::None`, any buffered bytes count as a complete line.
+    pub fn has_complete_line(&self) -> bool {
+        self.line_count() > 0
+    }
+
+    /// Number of complete lines currently buffered.
+    ///
+    /// The count is kept up to date as bytes are pushed and lines are consumed, so this is O(1).
+    /// Only `set_config` forces a rescan of the buffered bytes.
+    ///
+    /// Note: With `Terminator::None`, any buffered bytes count as a single complete line.
+    pub fn line_count(&self) -> usize {
+        if self.terminator_bytes().is_empty() {
+            return if self.empty { 0 } else { 1 };
+        }
+        if self.scan_is_stale() {
+            return self.count_lines().lines.get();
+        }
+        self.scan.lines.get()
+    }
+
+    /// An upper bound on `line_count`, from the buffered length alone, for sizing storage for
+    /// the lines before they have arrived, such as a `Vec` that more bytes will be pushed into.
+    ///
+    /// Every complete line takes at least its terminator, so this is `len() / terminator.len()`.
+    /// A bound of `len() / (terminator.len() + 1)` would be tighter, but empty lines break it.
+    ///
+    /// Note: With `Terminator::None`, this is the same as `line_count`.
+    /// ```rust
+    /// use strlinebuf::{LineBuffer, LineBufferConfig, Terminator};
+    ///
+    /// let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+    ///     terminator: Terminator::CarriageReturnNewline,
+    ///     ..LineBufferConfig::DEFAULT
+    /// });
+    /// line_buffer.push_bytes(b"AT\r\nOK\r\n+").unwrap();
+    /// assert_eq!(line_buffer.max_possible_lines(), 4);
+    /// assert_eq!(line_buffer.line_count(), 2);
+    /// ```
+    pub fn max_possible_lines(&self) -> usize {
+        match self.terminator_bytes().len() {
+            0 => self.line_count(),
+            terminator_len => self.len() / terminator_len,
+        }
+    }
+
+    /// Check if the buffered bytes end with an unescaped terminator, so that appending a line
+    /// doesn't need to add one first.
+    ///
+    /// Note: With `Terminator::None`, this is always false.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<16>::new();
+    /// line_buffer.push_bytes(b"AT").unwrap();
+    /// assert!(!line_buffer.ends_with_terminator());
+    /// line_buffer.push_bytes(b"\n").unwrap();
+    /// assert!(line_buffer.ends_with_terminator());
+    /// ```
+    pub fn ends_with_terminator(&self) -> bool {
+        let terminator = self.terminator_bytes();
+        let len = self.len();
+        if terminator.is_empty() || len < terminator.len() {
+            return false;
+        }
+        let from = len - terminator.len();
+        let ignore_case = self.scanner().ignore_case;
+        terminator.iter().enumerate().all(|(i, byte)| {
+            let found = self.byte_at(from + i);
+            found == *byte || (ignore_case && found.eq_ignore_ascii_case(byte))
+        }) && !self.is_escaped(from)
+    }
+
+    /// Check if the buffered bytes end part way into a terminator, such as a `\r` still waiting
+    /// for its `\n` with `Terminator::CarriageReturnNewline`. This explains a last line that
+    /// `has_complete_line` doesn't count yet.
+    ///
+    /// Note: With a single-byte terminator or `Terminator::None`, this is always false.
+    /// ```rust
+    /// use strlinebuf::{LineBuffer, LineBufferConfig, Terminator};
+    ///
+    /// let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+    ///     terminator: Terminator::CarriageReturnNewline,
+    ///     ..LineBufferConfig::DEFAULT
+    /// });
+    /// line_buffer.push_bytes(b"hi\r").unwrap();
+    /// assert!(!line_buffer.has_complete_line());
+    /// assert!(line_buffer.has_dangling_terminator());
+    /// ```
+    pub fn has_dangling_terminator(&self) -> bool {
+        let scan = if self.scan_is_stale() {
+            self.count_lines()
+        } else {
+            self.scan
+        };
+        scan.matched != I::ZERO
+    }
+
+    /// The terminator that ends the next line, or `None` if no complete line is buffered.
+    ///
+    /// This is the configured terminator, except that a `\n` line whose `\r` is dropped by
+    /// `config.strip_trailing_cr` reports `Terminator::CarriageReturnNewline`, so data mixing
+    /// `\n` and `\r\n` endings can be written back out the way it came in. A terminator fixed by
+    /// `T` that isn't a built-in one is reported as a `Terminator::Sequence`.
+    /// ```rust
+    /// use strlinebuf::{LineBuffer, LineBufferConfig, Terminator};
+    ///
+    /// let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+    ///     strip_trailing_cr: true,
+    ///     ..LineBufferConfig::DEFAULT
+    /// });
+    /// line_buffer.push_bytes(b"dos\r\nunix\n").unwrap();
+    /// assert_eq!(
+    ///     line_buffer.peek_next_terminator(),
+    ///     Some(Terminator::CarriageReturnNewline)
+    /// );
+    /// ```
+    pub fn peek_next_terminator(&self) -> Option<Terminator> {
+        let (line_len, _) = self.find_line()?;
+        Some(self.terminator_kind(self.has_trailing_cr(line_len)))
+    }
+
+    /// End the partial line at the back of the buffer by pushing a terminator after it, so it can
+    /// be read like any other line. Returns whether there was a partial line to end.
+    ///
+    /// Nothing is pushed if the buffer is empty or already ends with a terminator, or with
+    /// `Terminator::None`. A partial line that ends in an escape byte is left alone too, since the
+    /// escape byte would only make the terminator part of the line.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<16>::new();
+    /// line_buffer.push_bytes(b"no newline").unwrap();
+    /// assert_eq!(line_buffer.force_line(), Ok(true));
+    /// assert_eq!(line_buffer.force_line(), Ok(false));
+    /// assert_eq!(line_buffer.line_count(), 1);
+    /// ```
+    pub fn force_line(&mut self) -> Result<bool, LineBufferTxError> {
+        let terminator = self.terminator_bytes();
+        if terminator.is_empty()
+            || self.is_empty()
+            || self.ends_with_terminator()
+            || self.is_escaped(self.len())
+        {
+            return Ok(false);
+        }
+        let requested = terminator.len();
+        if matches!(
+            self.config.overflow,
+            OverflowPolicy::Reject | OverflowPolicy::RejectAll
+        ) && requested > self.remaining_capacity()
+            && !self.grow_for(requested)
+        {
+            // Half a terminator would leave the line unended and change how it reads later.
+            return Err(LineBufferTxError::BufferFull {
+                requested,
+                available: self.remaining_capacity(),
+                written: 0,
+            });
+        }
+        self.push_bytes(terminator)?;
+
+        Ok(true)
+    }
+
+    /// End a partial line with `force_line` once `ticks` ticks pass without a push, for protocols
+    /// that mark the end of a message with a pause rather than a terminator. `0` turns it off.
+    ///
+    /// Time is whatever the caller counts in, passed to `tick`.
+    pub fn set_idle_flush(&mut self, ticks: u32) {
+        self.idle_flush = (ticks > 0).then_some(IdleFlush {
+            ticks,
+            last_push: 0,
+            // Start timing from the first tick.
+            pushed: true,
+        });
+    }
+
+    /// Advance the idle-flush clock to `now`, a counter from the caller's timer that may wrap
+    /// around. If `set_idle_flush` is on and nothing has been pushed for its number of ticks,
+    /// the partial line is ended as by `force_line`. Returns whether a line was ended.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<16>::new();
+    /// line_buffer.set_idle_flush(5);
+    ///
+    /// line_buffer.push_bytes(b"\x01\x03\x00\x00").unwrap();
+    /// assert!(!line_buffer.tick(100));
+    /// assert!(!line_buffer.tick(104));
+    /// assert!(line_buffer.tick(105));
+    /// assert!(line_buffer.has_complete_line());
+    /// ```
+    pub fn tick(&mut self, now: u32) -> bool {
+        let Some(idle_flush) = &mut self.idle_flush else {
+            return false;
+        };
+        if idle_flush.pushed {
+            idle_flush.pushed = false;
+            idle_flush.last_push = now;
+            return false;
+        }
+        if now.wrapping_sub(idle_flush.last_push) < idle_flush.ticks {
+            return false;
+        }
+        self.force_line() == Ok(true)
+    }
+
+    /// Look for the end of the first line, carrying on from where the last call left off, and
+    /// return the line's length once it's found. The length excludes the terminator but
+    /// includes any escape bytes.
+    ///
+    /// Each call only looks at the bytes pushed since the previous one, so polling a slowly
+    /// filling buffer costs nothing extra. Consuming anything from the front starts the search
+    /// over.
+    ///
+    /// Note: With `Terminator::None`, any buffered bytes count as a complete line.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"AT+C").unwrap();
+    /// assert_eq!(line_buffer.scan(), None);
+    /// line_buffer.push_bytes(b"SQ\nOK").unwrap();
+    /// assert_eq!(line_buffer.scan(), Some(6));
+    /// ```
+    pub fn scan(&mut self) -> Option<usize> {
+        self.sync_line_count();
+        let terminator = self.terminator_bytes();
+        if terminator.is_empty() {
+            return (!self.empty).then(|| self.len());
+        }
+        if self.scan_pos_state.lines == I::ZERO {
+            let from = self.scan_pos.get();
+            let (first, second) = self.slices(from, self.len());
+            let scanner = self.scanner();
+            let mut state = self.scan_pos_state;
+            let found = match scanner.scan(&mut state, first) {
+                Some(i) => Some(i),
+                None => scanner.scan(&mut state, second).map(|i| first.len() + i),
+            };
+            match found {
+                Some(i) => {
+                    self.scan_pos = I::new(from + i + 1);
+                    state.lines = I::new(1);
+                }
+                None => self.scan_pos = I::new(self.len()),
+            }
+            self.scan_pos_state = state;
+        }
+        (self.scan_pos_state.lines != I::ZERO).then(|| self.scan_pos.get() - terminator.len())
+    }
+
+    /// Running totals of the traffic through the buffer.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> &LineBufferStats {
+        &self.stats
+    }
+
+    /// Zero the running totals. `max_fill` restarts from the current length.
+    #[cfg(feature = "stats")]
+    pub fn reset_stats(&mut self) {
+        self.stats = LineBufferStats {
+            max_fill: self.len(),
+            ..LineBufferStats::ZERO
+        };
+    }
+
+    /// Bytes lost so far: refused by a full buffer, or thrown away without being read.
+    /// The sum of `bytes_rejected` and `bytes_dropped` in `stats`.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<4>::new();
+    /// let _ = line_buffer.push_bytes(b"abcdef");
+    /// assert_eq!(line_buffer.dropped_bytes(), 2);
+    ///
+    /// line_buffer.reset_dropped();
+    /// assert_eq!(line_buffer.dropped_bytes(), 0);
+    /// ```
+    #[cfg(feature = "stats")]
+    pub fn dropped_bytes(&self) -> usize {
+        self.stats.bytes_rejected as usize + self.stats.bytes_dropped as usize
+    }
+
+    /// Zero the counts of lost bytes and lines, leaving the other totals alone.
+    #[cfg(feature = "stats")]
+    pub fn reset_dropped(&mut self) {
+        self.stats.bytes_rejected = 0;
+        self.stats.bytes_dropped = 0;
+        self.stats.lines_dropped = 0;
+    }
+
+    /// Write a byte to the buffer.
+    /// If the buffer is full and `config.overflow` is `OverflowPolicy::Reject`, an error will be returned.
+    pub fn push_byte(&mut self, byte: u8) -> Result<(), LineBufferTxError> {
+        // println!("start: {}, end: {}, byte: {}", self.start, self.end, byte);
+        if self.remaining_capacity() == 0
+            && !self.grow_for(1)
+            && !self.overflow(1, self.config.overflow)
+        {
+            self.record_rejected(1);
+            return Err(LineBufferTxError::BufferFull {
+                requested: 1,
+                available: 0,
+                written: 0,
+            });
+        }
+
+        self.sync_line_count();
+        if self.empty {
+            self.empty = false;
+        }
+        let lines = self.scan.lines.get();
+        let end = self.end.get();
+        self.buffer.range_mut(end..end + 1)[0] = byte;
+        self.end = I::new(self.wrap_add(self.end.get(), 1));
+        self.count_pushed_byte();
+        self.forget_overwritten();
+        self.record_pushed(1);
+        self.handle_lines(lines);
+
+        Ok(())
+    }
+
+    /// Write a byte to the buffer, evicting the oldest data if the buffer is full.
+    /// Returns the oldest evicted byte, or `None` if nothing was evicted.
+    ///
+    /// The whole oldest line is evicted if `config.overflow` is `OverflowPolicy::DropOldestLine`,
+    /// and the byte is refused like `push_byte` would if only a partial line is buffered.
+    /// Otherwise the single oldest byte is, whatever the policy.
+    pub fn push_byte_overwrite(&mut self, byte: u8) -> Option<u8> {
+        let evicted = if self.is_full() && !self.grow_for(1) {
+            let oldest = self.byte_at(0);
+            let policy = match self.config.overflow {
+                OverflowPolicy::DropOldestLine => OverflowPolicy::DropOldestLine,
+                _ => OverflowPolicy::OverwriteOldest,
+            };
+            if !self.overflow(1, policy) {
+                self.record_rejected(1);
+                return None;
+            }
+            Some(oldest)
+        } else {
+            None
+        };
+        // There is always room at this point.
+        let _ = self.push_byte(byte);
+
+        evicted
+    }
+
+    /// Write a slice of bytes to the buffer.
+    /// This can be a &[u8] or a &str.ß
+    pub fn push_bytes(&mut self, bytes: &[u8]) -> Result<(), LineBufferTxError> {
+        self.push_bytes_under(bytes, self.config.overflow)
+    }
+
+    /// Push bytes like `push_bytes`, but with `policy` in place of `config.overflow` for this call
+    /// only. Returns the number of bytes accepted.
+    ///
+    /// `OverflowPolicy::Reject` accepts as many bytes as fit, `RejectAll` accepts them all or
+    /// none, `OverwriteOldest` accepts them all, and `DropOldestLine` accepts them all or, if even
+    /// dropping every complete line wouldn't make room, none.
+    /// ```rust
+    /// use strlinebuf::{LineBuffer, OverflowPolicy};
+    ///
+    /// let mut line_buffer = LineBuffer::<8>::new();
+    /// line_buffer.push_bytes(b"log\n").unwrap();
+    ///
+    /// assert_eq!(line_buffer.push_bytes_with_policy(b"12345", OverflowPolicy::RejectAll), 0);
+    /// assert_eq!(line_buffer.push_bytes_with_policy(b"12345", OverflowPolicy::Reject), 4);
+    /// assert_eq!(line_buffer.push_bytes_with_policy(b"6\n", OverflowPolicy::DropOldestLine), 2);
+    /// assert_eq!(line_buffer.len(), 6);
+    /// ```
+    pub fn push_bytes_with_policy(&mut self, bytes: &[u8], policy: OverflowPolicy) -> usize {
+        match self.push_bytes_under(bytes, policy) {
+            Ok(()) => bytes.len(),
+            Err(LineBufferTxError::BufferFull { written, .. }) => written,
+            Err(LineBufferTxError::Overflow { accepted, .. }) => accepted,
+        }
+    }
+
+    /// `push_bytes` with `policy` deciding what happens when the buffer is full.
+    fn push_bytes_under(
+        &mut self,
+        bytes: &[u8],
+        policy: OverflowPolicy,
+    ) -> Result<(), LineBufferTxError> {
+        let result = self.try_push_bytes(bytes, policy);
+        if let Err(LineBufferTxError::BufferFull {
+            requested, written, ..
+        }) = result
+        {
+            self.record_rejected(requested - written);
+        }
+        result
+    }
+
+    /// `push_bytes` without counting refused bytes as lost, for callers that keep them.
+    fn try_push_bytes(
+        &mut self,
+        mut bytes: &[u8],
+        policy: OverflowPolicy,
+    ) -> Result<(), LineBufferTxError> {
+        self.sync_line_count();
+        let requested = bytes.len();
+        if requested > self.remaining_capacity() && !self.grow_for(requested) {
+            let available = self.remaining_capacity();
+            match policy {
+                OverflowPolicy::RejectAll => {
+                    return Err(LineBufferTxError::BufferFull {
+                        requested,
+                        available,
+                        written: 0,
+                    });
+                }
+                OverflowPolicy::DropOldestLine => {
+                    // Drop whole lines or nothing, so a push that can't fit leaves the buffer
+                    // as it was.
+                    if available + self.complete_lines_len() < requested {
+                        return Err(LineBufferTxError::BufferFull {
+                            requested,
+                            available,
+                            written: 0,
+                        });
+                    }
+                    self.overflow(requested, policy);
+                }
+                OverflowPolicy::Reject | OverflowPolicy::OverwriteOldest => {}
+            }
+        }
+        let available = self.remaining_capacity();
+        while !bytes.is_empty() {
+            let mut region = self.contiguous_write_len().min(bytes.len());
+            if region == 0 {
+                if !self.overflow(bytes.len(), policy) {
+                    return Err(LineBufferTxError::BufferFull {
+                        requested,
+                        available,
+                        written: requested - bytes.len(),
+                    });
+                }
+                region = self.contiguous_write_len().min(bytes.len());
+            }
+            let end = self.end.get();
+            self.buffer
+                .range_mut(end..end + region)
+                .copy_from_slice(&bytes[..region]);
+            self.commit_written(region);
+            bytes = &bytes[region..];
+        }
+
+        Ok(())
+    }
+
+    /// Push bytes like `push_bytes`, copying whatever this buffer accepts into `mirror` as well,
+    /// such as a debug buffer watching a pipeline.
+    ///
+    /// Only this buffer's result is returned. The mirror is best-effort: bytes it can't take are
+    /// dropped without an error.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<16>::new();
+    /// let mut mirror = LineBuffer::<64>::new();
+    /// line_buffer.push_bytes_tee(b"AT\r\n", &mut mirror).unwrap();
+    /// assert_eq!(mirror.len(), 4);
+    /// ```
+    pub fn push_bytes_tee<M: Storage, J: RingIndex, U: TerminatorSpec>(
+        &mut self,
+        bytes: &[u8],
+        mirror: &mut RawLineBuffer<M, J, U>,
+    ) -> Result<(), LineBufferTxError> {
+        let result = self.push_bytes(bytes);
+        let written = match result {
+            Ok(()) => bytes.len(),
+            Err(LineBufferTxError::BufferFull { written, .. }) => written,
+            Err(LineBufferTxError::Overflow { accepted, .. }) => accepted,
+        };
+        let _ = mirror.push_bytes(&bytes[..written]);
+
+        result
+    }
+
+    /// Replace the contents of `dst` with a copy of the bytes buffered here, which stay buffered.
+    /// `dst` may have a different capacity, index type or terminator.
+    ///
+    /// If `dst` can't hold them all, `LineBufferTxError::BufferFull` is returned and `dst` is left
+    /// as it was.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<16>::new();
+    /// line_buffer.push_bytes(b"AT\r\nOK").unwrap();
+    ///
+    /// let mut snapshot = LineBuffer::<64>::new();
+    /// snapshot.push_bytes(b"stale").unwrap();
+    /// line_buffer.clone_contents_into(&mut snapshot).unwrap();
+    /// assert_eq!(snapshot.len(), 6);
+    /// assert_eq!(line_buffer.len(), 6);
+    /// ```
+    pub fn clone_contents_into<D: Storage, J: RingIndex, U: TerminatorSpec>(
+        &self,
+        dst: &mut RawLineBuffer<D, J, U>,
+    ) -> Result<(), LineBufferTxError> {
+        let len = self.len();
+        if len > dst.capacity() && !dst.grow_for(len - dst.len()) {
+            return Err(LineBufferTxError::BufferFull {
+                requested: len,
+                available: dst.capacity(),
+                written: 0,
+            });
+        }
+
+        dst.clear();
+        let (first, second) = self.as_slices();
+        dst.try_push_bytes(first, dst.config.overflow)?;
+        dst.try_push_bytes(second, dst.config.overflow)
+    }
+
+    /// Write the configuration and the buffered bytes to `out`, for `LineBuffer::restore_state`
+    /// to pick up later, for example from battery-backed RAM across a deep sleep.
+    /// Returns the number of bytes written, which is the buffered length plus a fixed header.
+    ///
+    /// The encoding is versioned and the same for the same contents and configuration, however
+    /// the bytes happen to sit in the storage. The `line_handler` can't be saved, and neither can
+    /// running state such as `rewind` history, flow changes waiting to be polled, or `stats`. A line part way through `read_line_chunk` is restored whole.
+    /// ```rust
+    /// use strlinebuf::{LineBuffer, LineBufferConfig};
+    ///
+    /// let mut line_buffer = LineBuffer::<16>::new();
+    /// line_buffer.push_bytes(b"OK\n+CSQ").unwrap();
+    ///
+    /// let mut saved = [0u8; 64];
+    /// let len = line_buffer.save_state(&mut saved).unwrap();
+    /// let restored =
+    ///     LineBuffer::<16>::restore_state(&saved[..len], line_buffer.config()).unwrap();
+    /// assert_eq!(restored.len(), 7);
+    /// assert_eq!(restored.line_count(), 1);
+    /// ```
+    pub fn save_state(&self, out: &mut [u8]) -> Result<usize, SaveError> {
+        let config = self.config.to_state()?;
+        let needed = STATE_HEADER_LEN + self.len();
+        if out.len() < needed {
+            return Err(SaveError::OutputTooSmall { needed });
+        }
+        out[..STATE_CONFIG_LEN].copy_from_slice(&config);
+        out[STATE_CONFIG_LEN..STATE_HEADER_LEN].copy_from_slice(&(self.len() as u64).to_le_bytes());
+        self.peek_all(&mut out[STATE_HEADER_LEN..needed]);
+
+        Ok(needed)
+    }
+
+    /// Move the bytes buffered in `other` to the end of this buffer, as they are, whatever
+    /// either buffer's terminator. Returns the number of bytes moved.
+    ///
+    /// If this buffer can't take them all, as many as fit are moved and the rest stay in `other`,
+    /// with `LineBufferTxError::BufferFull` reporting how many were written. `config.overflow`
+    /// doesn't apply, so nothing already in this buffer is dropped to make room.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<16>::new();
+    /// let mut other = LineBuffer::<16>::new();
+    /// line_buffer.push_bytes(b"AT+").unwrap();
+    /// other.push_bytes(b"CSQ\r\n").unwrap();
+    ///
+    /// assert_eq!(line_buffer.append_from(&mut other), Ok(5));
+    /// assert!(other.is_empty());
+    /// assert_eq!(line_buffer.len(), 8);
+    /// ```
+    pub fn append_from<O: Storage, J: RingIndex, U: TerminatorSpec>(
+        &mut self,
+        other: &mut RawLineBuffer<O, J, U>,
+    ) -> Result<usize, LineBufferTxError> {
+        let requested = other.len();
+        let available = self.remaining_capacity();
+        let (first, second) = other.as_slices();
+        let mut moved = 0;
+        let mut result = Ok(());
+        for part in [first, second] {
+            // Bytes that don't fit stay in `other`, so they aren't lost, and none of this buffer's
+            // are dropped to make room whatever `config.overflow` says.
+            match self.try_push_bytes(part, OverflowPolicy::Reject) {
+                Ok(()) => moved += part.len(),
+                Err(error) => {
+                    if let LineBufferTxError::BufferFull { written, .. } = error {
+                        moved += written;
+                    }
+                    result = Err(LineBufferTxError::BufferFull {
+                        requested,
+                        available,
+                        written: moved,
+                    });
+                    break;
+                }
+            }
+        }
+        #[cfg(feature = "stats")]
+        {
+            other.stats.bytes_read += moved as u64;
+        }
+        other.consume(moved);
+
+        result.map(|()| moved)
+    }
+
+    /// Push a chunk of bytes, such as one DMA transfer, calling `on_line` with each line it
+    /// completes, in place as with `with_line`, and consuming the line straight after.
+    /// Returns the number of bytes accepted.
+    ///
+    /// Lines are taken out as soon as they complete, so the buffer only ever has to hold the
+    /// longest partial line rather than the whole chunk. It only fills up, and the rest of the
+    /// chunk is handled by `config.overflow`, when a single line doesn't fit. Lines over
+    /// `config.max_line_length` are handled by `config.line_too_long`, and are never passed to
+    /// `on_line` under `LineTooLongPolicy::Error` or `Discard`.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<16>::new();
+    /// let mut lines = 0;
+    /// let accepted = line_buffer
+    ///     .feed(b"OK\r\n+CSQ: 20,0\r\nOK\r\n+C", |_, _| lines += 1)
+    ///     .unwrap();
+    /// assert_eq!((accepted, lines), (22, 3));
+    /// assert_eq!(line_buffer.len(), 2);
+    /// ```
+    pub fn feed(
+        &mut self,
+        mut bytes: &[u8],
+        mut on_line: impl FnMut(&[u8], &[u8]),
+    ) -> Result<usize, LineBufferTxError> {
+        let requested = bytes.len();
+        loop {
+            // A line dropped by `LineTooLongPolicy::Error` mustn't hold up the ones after it.
+            while matches!(
+                self.with_line(&mut on_line),
+                Ok(()) | Err(LineBufferRxError::LineTooLong { .. })
+            ) {}
+            if bytes.is_empty() {
+                return Ok(requested);
+            }
+            let room = self.remaining_capacity();
+            if room == 0 {
+                // A partial line fills the buffer, so room has to be made a byte at a time.
+                if self.push_byte(bytes[0]).is_err() {
+                    // `push_byte` counted the first of them.
+                    self.record_rejected(bytes.len() - 1);
+                    return Err(LineBufferTxError::BufferFull {
+                        requested,
+                        available: 0,
+                        written: requested - bytes.len(),
+                    });
+                }
+                bytes = &bytes[1..];
+                continue;
+            }
+            let (chunk, rest) = bytes.split_at(room.min(bytes.len()));
+            self.push_bytes(chunk)?;
+            bytes = rest;
+        }
+    }
+
+    /// Push bytes pulled from an iterator until it ends or the buffer fills.
+    /// Returns the number of bytes accepted.
+    ///
+    /// Bytes are written straight into the free regions of the storage.
+    /// If the buffer fills while the iterator still has bytes and `config.overflow` is
+    /// `OverflowPolicy::Reject`, `LineBufferTxError::Overflow` reports how many were accepted
+    /// along with the one extra byte pulled to find out.
+    pub fn push_from_iter<It: IntoIterator<Item = u8>>(
+        &mut self,
+        iter: It,
+    ) -> Result<usize, LineBufferTxError> {
+        self.sync_line_count();
+        let mut iter = iter.into_iter();
+        let mut accepted = 0;
+
+        loop {
+            if self.remaining_capacity() == 0 && !self.grow_for(1) {
+                // Only make room once another byte has actually turned up.
+                let Some(byte) = iter.next() else {
+                    return Ok(accepted);
+                };
+                if !self.overflow(iter.size_hint().0 + 1, self.config.overflow) {
+                    self.record_rejected(1);
+                    return Err(LineBufferTxError::Overflow {
+                        accepted,
+                        rejected: byte,
+                    });
+                }
+                let end = self.end.get();
+                self.buffer.range_mut(end..end + 1)[0] = byte;
+                self.commit_written(1);
+                accepted += 1;
+                continue;
+            }
+            let region = self.contiguous_write_len();
+            let mut written = 0;
+            let end = self.end.get();
+            for slot in self.buffer.range_mut(end..end + region) {
+                match iter.next() {
+                    Some(byte) => *slot = byte,
+                    None => break,
+                }
+                written += 1;
+            }
+            self.commit_written(written);
+            accepted += written;
+            if written < region {
+                return Ok(accepted);
+            }
+        }
+    }
+
+    /// Read from `src` straight into the free region after the write position, with a single
+    /// `read` call, returning the number of bytes ingested.
+    ///
+    /// The free region may wrap around the end of the storage, so call again to fill the rest.
+    /// A full buffer returns `Ok(0)` without calling `src`.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut uart: &[u8] = b"OK\n";
+    /// let mut line_buffer = LineBuffer::<16>::new();
+    /// assert_eq!(line_buffer.read_from(&mut uart), Ok(3));
+    /// assert!(line_buffer.has_complete_line());
+    /// ```
+    #[cfg(feature = "embedded-io")]
+    pub fn read_from<R: embedded_io::Read>(&mut self, src: &mut R) -> Result<usize, R::Error> {
+        self.sync_line_count();
+        if self.remaining_capacity() == 0 && !self.grow_for(1) {
+            return Ok(0);
+        }
+        let end = self.end.get();
+        let region = self.contiguous_write_len();
+        let read = src.read(self.buffer.range_mut(end..end + region))?;
+        self.commit_written(read);
+
+        Ok(read)
+    }
+
+    /// Read everything `src` has ready straight into the buffer, free region by free region,
+    /// returning the number of bytes read.
+    ///
+    /// Stops without blocking once `src` has nothing ready, reaches its end, or the buffer is
+    /// full. If `src` fails, the bytes read before that stay buffered and `FillError` counts them.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut uart: &[u8] = b"+CSQ: 20,0\r\nOK\r\n";
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// assert_eq!(line_buffer.fill_from(&mut uart), Ok(16));
+    /// assert_eq!(line_buffer.line_count(), 2);
+    /// ```
+    #[cfg(feature = "embedded-io")]
+    pub fn fill_from<R: embedded_io::Read + embedded_io::ReadReady>(
+        &mut self,
+        src: &mut R,
+    ) -> Result<usize, FillError<R::Error>> {
+        let mut filled = 0;
+        loop {
+            if self.remaining_capacity() == 0 && !self.grow_for(1) {
+                return Ok(filled);
+            }
+            match src.read_ready().and_then(|ready| match ready {
+                true => self.read_from(src),
+                false => Ok(0),
+            }) {
+                Ok(0) => return Ok(filled),
+                Ok(read) => filled += read,
+                Err(error) => return Err(FillError { error, filled }),
+            }
+        }
+    }
+
+    /// Write each complete line to `dst`, followed by its terminator or by
+    /// `config.drain_terminator`, until no complete line is left. Returns how many lines and
+    /// bytes were forwarded.
+    ///
+    /// Lines are written as they are buffered, escape bytes included. Short writes are carried on
+    /// until the whole line is through, and a line only leaves the buffer once it is. If `dst`
+    /// fails, the line being written stays buffered and `DrainError` reports how far it got.
+    /// ```rust
+    /// use strlinebuf::{DrainReport, LineBuffer, LineBufferConfig, Terminator};
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new_with_config(&LineBufferConfig {
+    ///     drain_terminator: Some(Terminator::CarriageReturnNewline),
+    ///     ..LineBufferConfig::DEFAULT
+    /// });
+    /// line_buffer.push_bytes(b"AT\nATI\nAT+").unwrap();
+    ///
+    /// let mut out = [0u8; 32];
+    /// let mut dst = &mut out[..];
+    /// let report = line_buffer.drain_to(&mut dst).unwrap();
+    /// assert_eq!(report, DrainReport { lines: 2, bytes: 9 });
+    /// assert_eq!(&out[..9], b"AT\r\nATI\r\n");
+    /// assert_eq!(line_buffer.len(), 3);
+    /// ```
+    #[cfg(feature = "embedded-io")]
+    pub fn drain_to<W: embedded_io::Write>(
+        &mut self,
+        dst: &mut W,
+    ) -> Result<DrainReport, DrainError<W::Error>> {
+        let mut report = DrainReport { lines: 0, bytes: 0 };
+        loop {
+            let (line_len, terminator_len) = match self.next_read() {
+                Ok(found) => found,
+                // The line was dropped, so carry on with the next one.
+                Err(LineBufferRxError::LineTooLong { .. }) => continue,
+                Err(_) => return Ok(report),
+            };
+            let (first, second) = self.slices(0, line_len);
+            let (terminator, terminator_rest) = match self.config.drain_terminator {
+                Some(terminator) => (terminator.bytes(), &[][..]),
+                None => self.slices(line_len, line_len + terminator_len),
+            };
+
+            let mut written = 0;
+            for mut part in [first, second, terminator, terminator_rest] {
+                while !part.is_empty() {
+                    let error = match dst.write(part) {
+                        Ok(0) => None,
+                        Ok(n) => {
+                            written += n;
+                            part = &part[n..];
+                            continue;
+                        }
+                        Err(error) => Some(error),
+                    };
+                    return Err(DrainError {
+                        error,
+                        report,
+                        written,
+                    });
+                }
+            }
+            self.finish_read(line_len, terminator_len);
+            report.lines += 1;
+            report.bytes += written;
+        }
+    }
+
+    /// Put bytes back in front of the buffered data, so they are read before anything else.
+    /// Useful for returning bytes that were read out but turned out not to be wanted yet.
+    /// If there isn't room for all of them, nothing is written and `LineBufferTxError::BufferFull` is returned.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<16>::new();
+    /// line_buffer.push_bytes(b"world\n").unwrap();
+    /// line_buffer.unread(b"hello ").unwrap();
+    ///
+    /// let mut aux_buffer = [0u8; 16];
+    /// let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+    /// assert_eq!(&aux_buffer[..len], b"hello world");
+    /// ```
+    pub fn unread(&mut self, bytes: &[u8]) -> Result<(), LineBufferTxError> {
+        if bytes.len() > self.remaining_capacity() && !self.grow_for(bytes.len()) {
+            return Err(LineBufferTxError::BufferFull {
+                requested: bytes.len(),
+                available: self.remaining_capacity(),
+                written: 0,
+            });
+        }
+        if bytes.is_empty() {
+            return Ok(());
+        }
+
+        self.sync_line_count();
+        self.forget_rewind();
+        let start = self.wrap_sub(self.start.get(), bytes.len());
+        for (i, byte) in bytes.iter().enumerate() {
+            let index = self.wrap_add(start, i);
+            self.buffer.range_mut(index..index + 1)[0] = *byte;
+        }
+        self.start = I::new(start);
+        self.empty = false;
+        self.truncated = false;
+        self.terminator_left = false;
+        self.reset_scan_pos();
+        self.record_fill();
+        // The new bytes can complete lines or change how the ones after them split, so recount.
+        self.scan = self.count_lines();
+
+        Ok(())
+    }
+
+    /// Drop every complete line at once, keeping a trailing partial line for later reads.
+    /// Returns the number of lines dropped.
+    ///
+    /// Note: With `Terminator::None`, everything buffered counts as one complete line.
+    pub fn clear_complete_lines(&mut self) -> usize {
+        self.sync_line_count();
+        let lines = self.line_count();
+        if lines == 0 {
+            return 0;
+        }
+        let count = if self.terminator_bytes().is_empty() {
+            self.len()
+        } else {
+            self.complete_lines_len()
+        };
+        self.record_dropped(count);
+        self.advance_start(count);
+        self.mid_line = false;
+        // What's left is the partial line the count was already tracking.
+        self.scan.lines = I::ZERO;
+
+        lines
+    }
+
+    /// Throw away everything before logical `offset`, such as a prefix found to be garbage,
+    /// keeping the rest for later reads. An `offset` past the end empties the buffer.
+    ///
+    /// The offset counts buffered bytes, terminators and escape bytes included, so it may fall
+    /// in the middle of a line, which then reads from there.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<16>::new();
+    /// line_buffer.push_bytes(b"\xff\xfe+OK\n").unwrap();
+    /// line_buffer.discard_to(2);
+    ///
+    /// let mut aux_buffer = [0u8; 16];
+    /// let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+    /// assert_eq!(&aux_buffer[..len], b"+OK");
+    /// ```
+    pub fn discard_to(&mut self, offset: usize) {
+        let count = offset.min(self.len());
+        self.record_dropped(count);
+        self.consume(count);
+    }
+
+    /// Clear the buffer.
+    /// This will reset the start and end pointers to 0 and set the buffer to empty.
+    ///
+    /// Warning: This will not clear the buffer contents, only the pointers.
+    pub fn clear(&mut self) {
+        self.record_dropped(self.len());
+        self.start = I::ZERO;
+        self.end = I::ZERO;
+        self.empty = true;
+        self.scan = ScanState::EMPTY;
+        self.rewindable = I::ZERO;
+        self.rewind_lost = false;
+        self.truncated = false;
+        self.mid_line = false;
+        self.terminator_left = false;
+        self.reset_scan_pos();
+        self.update_flow();
+    }
+
+    /// Move the read position back to where the buffered data began at the last `clear`,
+    /// so everything read since then can be read again.
+    ///
+    /// Fails with `RewindError::Overwritten` once writes have reused any of the space those bytes
+    /// occupied, after which rewinding is only possible again after a `clear`.
+    ///
+    /// Anything else that moves bytes around in the storage gives up the history the same way:
+    /// a read that cuts a line short under `LineTooLongPolicy::Truncate`, `unread`, and growing
+    /// the storage. `make_contiguous` rotates the whole storage, read bytes included, so it keeps
+    /// the history.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<16>::new();
+    /// line_buffer.push_bytes(b"HEAD\nBODY\n").unwrap();
+    ///
+    /// let mut aux_buffer = [0u8; 16];
+    /// line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+    /// line_buffer.rewind().unwrap();
+    /// let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+    /// assert_eq!(&aux_buffer[..len], b"HEAD");
+    /// ```
+    pub fn rewind(&mut self) -> Result<(), RewindError> {
+        if self.rewind_lost {
+            return Err(RewindError::Overwritten);
+        }
+        let count = self.rewindable.get();
+        if count == 0 {
+            return Ok(());
+        }
+
+        self.sync_line_count();
+        self.start = I::new(self.wrap_sub(self.start.get(), count));
+        self.empty = false;
+        self.rewindable = I::ZERO;
+        self.mid_line = false;
+        self.terminator_left = false;
+        self.reset_scan_pos();
+        self.record_fill();
+        self.scan = self.count_lines();
+
+        Ok(())
+    }
+
+    /// Read the next line into several output slices, filling each in order before moving to the next.
+    /// Returns the total number of bytes read.
+    ///
+    /// If the slices can't hold the whole line together, `LineBufferRxError::AuxBufferTooSmall`
+    /// is returned and the line is left in the buffer.
+    pub fn read_line_vectored(
+        &mut self,
+        bufs: &mut [&mut [u8]],
+    ) -> Result<usize, LineBufferRxError> {
+        if self.is_empty() {
+            return Err(LineBufferRxError::BufferEmpty);
+        }
+        let (line_len, terminator_len) = self.next_text_read()?;
+
+        let mut bytes = self.line_bytes(line_len);
+        let mut copied = 0;
+        for (slot, byte) in bufs
+            .iter_mut()
+            .flat_map(|buf| buf.iter_mut())
+            .zip(&mut bytes)
+        {
+            *slot = byte;
+            copied += 1;
+        }
+        if bytes.next().is_some() {
+            return Err(LineBufferRxError::AuxBufferTooSmall);
+        }
+        drop(bytes);
+        self.finish_read(line_len, terminator_len);
+
+        Ok(copied)
+    }
+
+    /// Read the next line into `first` until it is full, then into `second`.
+    /// Returns the number of bytes placed in each, for splitting a fixed-size header from the body.
+    ///
+    /// If the two can't hold the whole line together, `LineBufferRxError::AuxBufferTooSmall`
+    /// is returned and the line is left in the buffer.
+    pub fn read_line_scatter(
+        &mut self,
+        first: &mut [u8],
+        second: &mut [u8],
+    ) -> Result<(usize, usize), LineBufferRxError> {
+        let copied = self.read_line_vectored(&mut [&mut *first, second])?;
+        let in_first = copied.min(first.len());
+
+        Ok((in_first, copied - in_first))
+    }
+
+    /// Read the next line into `out` like `read_line_bytes`, also reporting whether it was cut
+    /// short by `LineTooLongPolicy::Truncate`.
+    /// ```rust
+    /// use strlinebuf::{LineBuffer, LineBufferConfig, LineTooLongPolicy};
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new_with_config(&LineBufferConfig {
+    ///     max_line_length: Some(4),
+    ///     line_too_long: LineTooLongPolicy::Truncate,
+    ///     ..LineBufferConfig::DEFAULT
+    /// });
+    /// line_buffer.push_bytes(b"warning: disk full\n").unwrap();
+    ///
+    /// let mut out = [0u8; 32];
+    /// let info = line_buffer.read_line_info(&mut out).unwrap();
+    /// assert_eq!(&out[..info.len], b"warn");
+    /// assert!(info.truncated);
+    /// ```
+    pub fn read_line_info(&mut self, out: &mut [u8]) -> Result<LineInfo, LineBufferRxError> {
+        if self.is_empty() {
+            return Err(LineBufferRxError::BufferEmpty);
+        }
+        let (line_len, terminator_len) = self.next_text_read()?;
+
+        let len = self
+            .copy_line(line_len, out)
+            .ok_or(LineBufferRxError::AuxBufferTooSmall)?;
+        let truncated = self.truncated;
+        self.finish_read(line_len, terminator_len);
+
+        Ok(LineInfo { len, truncated })
+    }
+
+    /// Read the next line into `out` like `read_line_bytes`, also returning the terminator that
+    /// ended it, as `peek_next_terminator` reports it.
+    /// ```rust
+    /// use strlinebuf::{LineBuffer, LineBufferConfig, Terminator};
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new_with_config(&LineBufferConfig {
+    ///     strip_trailing_cr: true,
+    ///     ..LineBufferConfig::DEFAULT
+    /// });
+    /// line_buffer.push_bytes(b"dos\r\nunix\n").unwrap();
+    ///
+    /// let mut out = [0u8; 32];
+    /// assert_eq!(
+    ///     line_buffer.read_line_bytes_kind(&mut out),
+    ///     Ok((3, Terminator::CarriageReturnNewline))
+    /// );
+    /// assert_eq!(
+    ///     line_buffer.read_line_bytes_kind(&mut out),
+    ///     Ok((4, Terminator::Newline))
+    /// );
+    /// ```
+    pub fn read_line_bytes_kind(
+        &mut self,
+        out: &mut [u8],
+    ) -> Result<(usize, Terminator), LineBufferRxError> {
+        if self.is_empty() {
+            return Err(LineBufferRxError::BufferEmpty);
+        }
+        let (line_len, terminator_len) = self.next_text_read()?;
+
+        let len = self
+            .copy_line(line_len, out)
+            .ok_or(LineBufferRxError::AuxBufferTooSmall)?;
+        let terminator = self.terminator_kind(terminator_len > self.terminator_bytes().len());
+        self.finish_read(line_len, terminator_len);
+
+        Ok((len, terminator))
+    }
+
+    /// Run `f` on each complete line in place, as `with_line` does, consuming each line once `f`
+    /// returns `Ok`. Returns the number of lines handled.
+    ///
+    /// The first time `f` fails, visiting stops and its error is returned in a `VisitError`.
+    /// The line it failed on and the ones after it stay buffered, untouched.
+    /// ```rust
+    /// use strlinebuf::{LineBuffer, VisitError};
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"one\ntwo\nthree\n").unwrap();
+    ///
+    /// let mut queue_space = 2;
+    /// let result = line_buffer.visit_lines(|_, _| match queue_space {
+    ///     0 => Err("queue full"),
+    ///     _ => Ok(queue_space -= 1),
+    /// });
+    /// assert_eq!(result, Err(VisitError { error: "queue full", handled: 2 }));
+    /// assert_eq!(line_buffer.line_count(), 1);
+    /// ```
+    pub fn visit_lines<E>(
+        &mut self,
+        mut f: impl FnMut(&[u8], &[u8]) -> Result<(), E>,
+    ) -> Result<usize, VisitError<E>> {
+        let mut handled = 0;
+        loop {
+            let (line_len, terminator_len) = match self.next_read() {
+                Ok(found) => found,
+                // The line was dropped, so carry on with the next one.
+                Err(LineBufferRxError::LineTooLong { .. }) => continue,
+                Err(_) => return Ok(handled),
+            };
+            let (first, second) = self.slices(0, line_len);
+            if let Err(error) = f(first, second) {
+                return Err(VisitError { error, handled });
+            }
+            self.finish_read(line_len, terminator_len);
+            handled += 1;
+        }
+    }
+
+    /// Read the next line into `out` like `read_line_bytes`, also reporting whether another
+    /// complete line is ready, as `has_complete_line` would after the read.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"one\ntwo\nthr").unwrap();
+    ///
+    /// let mut out = [0u8; 32];
+    /// assert_eq!(line_buffer.read_line_status(&mut out), Ok((3, true)));
+    /// assert_eq!(line_buffer.read_line_status(&mut out), Ok((3, false)));
+    /// ```
+    pub fn read_line_status(&mut self, out: &mut [u8]) -> Result<(usize, bool), LineBufferRxError> {
+        let len = self.read_line_into(out)?;
+
+        Ok((len, self.has_complete_line()))
+    }
+
+    /// Read the next line into `out` as UTF-8 text without the terminator or escape bytes,
+    /// replacing each invalid sequence with U+FFFD, and return it.
+    ///
+    /// Each replacement takes three bytes, so a line with invalid bytes in it can need more room
+    /// than it takes in the buffer. If it doesn't fit, `LineBufferRxError::AuxBufferTooSmall` is
+    /// returned and the line is left in the buffer.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"temp=2\xff1\n").unwrap();
+    ///
+    /// let mut aux_buffer = [0u8; 32];
+    /// let line = line_buffer.read_line_str_lossy(&mut aux_buffer).unwrap();
+    /// assert_eq!(line, "temp=2\u{FFFD}1");
+    /// ```
+    pub fn read_line_str_lossy<'a>(
+        &mut self,
+        out: &'a mut [u8],
+    ) -> Result<&'a str, LineBufferRxError> {
+        if self.is_empty() {
+            return Err(LineBufferRxError::BufferEmpty);
+        }
+        let (line_len, terminator_len) = self.next_read()?;
+
+        let len = copy_lossy(self.line_bytes(line_len), out)
+            .ok_or(LineBufferRxError::AuxBufferTooSmall)?;
+        self.finish_read(line_len, terminator_len);
+
+        // SAFETY: `copy_lossy` only writes whole, valid UTF-8 characters.
+        Ok(unsafe { core::str::from_utf8_unchecked(&out[..len]) })
+    }
+
+    /// Read the next line a piece at a time, for output buffers smaller than the line. Each call
+    /// copies as much of the line as fits in `out` and consumes it, and the call that reaches the
+    /// end of the line also consumes the terminator and sets `LineChunk::finished`.
+    ///
+    /// Until the line is finished, the other read methods return `LineBufferRxError::MidLine`
+    /// rather than read the rest of it as a line of its own. Escape bytes are dropped, as by
+    /// `read_line_bytes`.
+    /// ```rust
+    /// use strlinebuf::{LineBuffer, LineChunk};
+    ///
+    /// let mut line_buffer = LineBuffer::<64>::new();
+    /// line_buffer.push_bytes(b"a line longer than the scratch buffer\n").unwrap();
+    ///
+    /// let mut scratch = [0u8; 16];
+    /// let mut len = 0;
+    /// loop {
+    ///     let chunk = line_buffer.read_line_chunk(&mut scratch).unwrap();
+    ///     len += chunk.len;
+    ///     if chunk.finished {
+    ///         break;
+    ///     }
+    /// }
+    /// assert_eq!(len, 37);
+    /// assert!(line_buffer.is_empty());
+    /// ```
+    pub fn read_line_chunk(&mut self, out: &mut [u8]) -> Result<LineChunk, LineBufferRxError> {
+        if self.is_empty() {
+            return Err(LineBufferRxError::BufferEmpty);
+        }
+        let (line_len, terminator_len) = if self.mid_line {
+            // The line-too-long policy was applied when the line was started.
+            self.next_read_line()?
+        } else {
+            self.next_text_read()?
+        };
+
+        let escape = self.config.escape;
+        let mut consumed = 0;
+        let mut len = 0;
+        let mut escaped = false;
+        // An escape byte is never last in a line, so the byte it escapes always comes along too.
+        while consumed < line_len && len < out.len() {
+            let byte = self.byte_at(consumed);
+            escaped = !escaped && Some(byte) == escape;
+            if !escaped {
+                out[len] = byte;
+                len += 1;
+            }
+            consumed += 1;
+        }
+
+        if consumed == line_len {
+            self.finish_read(line_len, terminator_len);
+            self.mid_line = false;
+        } else {
+            #[cfg(feature = "stats")]
+            {
+                self.stats.bytes_read += consumed as u64;
+            }
+            self.consume(consumed);
+            self.mid_line = true;
+        }
+
+        Ok(LineChunk {
+            len,
+            finished: !self.mid_line,
+        })
+    }
+
+    /// Read the next line into `out` like `read_line_bytes`, but leave its terminator buffered, so
+    /// the next read starts on it. Returns the number of bytes read.
+    ///
+    /// The terminator still counts as ending a line, so the next `read_line_bytes` reads it as an
+    /// empty line, while calling this again keeps returning `Ok(0)` without moving.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"key\nvalue\n").unwrap();
+    ///
+    /// let mut out = [0u8; 32];
+    /// assert_eq!(line_buffer.read_line_leave_terminator(&mut out), Ok(3));
+    /// assert_eq!(line_buffer.peek_range(0, &mut out[..1]), 1);
+    /// assert_eq!(out[0], b'\n');
+    /// assert_eq!(line_buffer.line_count(), 2);
+    /// ```
+    pub fn read_line_leave_terminator(
+        &mut self,
+        out: &mut [u8],
+    ) -> Result<usize, LineBufferRxError> {
+        if self.is_empty() {
+            return Err(LineBufferRxError::BufferEmpty);
+        }
+        let (line_len, _) = self.next_text_read()?;
+
+        let bytes_read = self
+            .copy_line(line_len, out)
+            .ok_or(LineBufferRxError::AuxBufferTooSmall)?;
+        #[cfg(feature = "stats")]
+        {
+            self.stats.bytes_read += line_len as u64;
+            self.stats.max_line_len = self.stats.max_line_len.max(line_len);
+        }
+        self.consume(line_len);
+        self.terminator_left = true;
+
+        Ok(bytes_read)
+    }
+
+    /// Read the next line straight into `w`, such as a `heapless::String`, as UTF-8 text without
+    /// the terminator or escape bytes. Returns the number of bytes written.
+    ///
+    /// If the line isn't valid UTF-8, `LineBufferRxError::InvalidUtf8` is returned and the line is
+    /// left in the buffer. If `w` fails, such as by running out of room, the line is also left in
+    /// the buffer and `LineBufferRxError::AuxBufferTooSmall` is returned, but `w` may have taken
+    /// part of it.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes("température: 21°C\n".as_bytes()).unwrap();
+    ///
+    /// let mut line = String::new();
+    /// assert_eq!(line_buffer.read_line_to(&mut line), Ok(19));
+    /// assert_eq!(line, "température: 21°C");
+    /// ```
+    pub fn read_line_to<W: core::fmt::Write>(
+        &mut self,
+        w: &mut W,
+    ) -> Result<usize, LineBufferRxError> {
+        if self.is_empty() {
+            return Err(LineBufferRxError::BufferEmpty);
+        }
+        let (line_len, terminator_len) = self.next_text_read()?;
+
+        // Check the whole line first, so `w` never gets part of an invalid one.
+        if let Err(Utf8Stop::Invalid { valid_up_to }) = self.line_text(line_len, &mut |_| Ok(())) {
+            return Err(LineBufferRxError::InvalidUtf8 { valid_up_to });
+        }
+        let mut len = 0;
+        self.line_text(line_len, &mut |text| {
+            len += text.len();
+            w.write_str(text)
+        })
+        .map_err(|_| LineBufferRxError::AuxBufferTooSmall)?;
+        self.finish_read(line_len, terminator_len);
+
+        Ok(len)
+    }
+
+    /// Read the bytes before the first occurrence of `needle` into `out`, then drop them along
+    /// with `needle`. Returns the number of bytes copied.
+    ///
+    /// This splits on `needle` instead of `config.terminator`, and copies the bytes as they are,
+    /// escape bytes and terminators included. Returns `LineBufferRxError::NoLines` if `needle`
+    /// hasn't fully arrived yet, and `LineBufferRxError::AuxBufferTooSmall` if the bytes before it
+    /// don't fit in `out`; either way nothing is consumed.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"field one||field two").unwrap();
+    ///
+    /// let mut out = [0u8; 32];
+    /// let len = line_buffer.read_until_subsequence(b"||", &mut out).unwrap();
+    /// assert_eq!(&out[..len], b"field one");
+    /// ```
+    pub fn read_until_subsequence(
+        &mut self,
+        needle: &[u8],
+        out: &mut [u8],
+    ) -> Result<usize, LineBufferRxError> {
+        if self.is_empty() {
+            return Err(LineBufferRxError::BufferEmpty);
+        }
+        let offset = self.find(needle).ok_or_else(|| self.no_lines())?;
+        self.read_prefix(offset, offset + needle.len(), out)
+    }
+
+    /// Read an HTTP-style header block, everything up to the first empty line of a `\r\n`
+    /// delimited stream, into `out`, then drop it along with the empty line. Returns the number of
+    /// bytes copied, which include the `\r\n` ending each header but not the empty line.
+    ///
+    /// This looks for `\r\n\r\n` whatever `config.terminator` is, and copies the bytes as they
+    /// are. Returns `LineBufferRxError::NoLines` until the empty line has fully arrived, and
+    /// `LineBufferRxError::AuxBufferTooSmall` if the block doesn't fit in `out`; either way nothing
+    /// is consumed. A body that follows can then be read with `read_exact_bytes`.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<64>::new();
+    /// line_buffer.push_bytes(b"RTSP/1.0 200 OK\r\nCSeq: 1\r\n\r").unwrap();
+    ///
+    /// let mut out = [0u8; 64];
+    /// assert!(line_buffer.read_header_block(&mut out).is_err());
+    /// line_buffer.push_bytes(b"\nbody").unwrap();
+    /// let len = line_buffer.read_header_block(&mut out).unwrap();
+    /// assert_eq!(&out[..len], b"RTSP/1.0 200 OK\r\nCSeq: 1\r\n");
+    /// assert_eq!(line_buffer.len(), 4);
+    /// ```
+    pub fn read_header_block(&mut self, out: &mut [u8]) -> Result<usize, LineBufferRxError> {
+        if self.is_empty() {
+            return Err(LineBufferRxError::BufferEmpty);
+        }
+        if self.len() >= 2 && self.byte_at(0) == b'\r' && self.byte_at(1) == b'\n' {
+            // No headers, just the empty line.
+            return self.read_prefix(0, 2, out);
+        }
+        let offset = self.find(b"\r\n\r\n").ok_or_else(|| self.no_lines())?;
+        self.read_prefix(offset + 2, offset + 4, out)
+    }
+
+    /// Read exactly `n` bytes into the start of `aux_buffer` as they are, terminators and escape
+    /// bytes included, such as a body whose length a header gave. Lines after them read as usual.
+    ///
+    /// Returns `LineBufferRxError::NotEnoughData` until `n` bytes are buffered, see `available`,
+    /// and `LineBufferRxError::AuxBufferTooSmall` if they don't fit in `aux_buffer`; either way
+    /// nothing is consumed.
+    /// ```rust
+    /// use strlinebuf::{LineBuffer, LineBufferRxError};
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"LEN 4\n\x00\n").unwrap();
+    ///
+    /// let mut aux_buffer = [0u8; 32];
+    /// line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+    /// assert_eq!(
+    ///     line_buffer.read_exact_bytes(4, &mut aux_buffer),
+    ///     Err(LineBufferRxError::NotEnoughData { available: 2 })
+    /// );
+    /// line_buffer.push_bytes(b"\x01\x02OK\n").unwrap();
+    /// line_buffer.read_exact_bytes(4, &mut aux_buffer).unwrap();
+    /// assert_eq!(&aux_buffer[..4], b"\x00\n\x01\x02");
+    /// assert_eq!(line_buffer.line_count(), 1);
+    /// ```
+    pub fn read_exact_bytes(
+        &mut self,
+        n: usize,
+        aux_buffer: &mut [u8],
+    ) -> Result<(), LineBufferRxError> {
+        if n > self.len() {
+            return Err(LineBufferRxError::NotEnoughData {
+                available: self.len(),
+            });
+        }
+        self.read_prefix(n, n, aux_buffer)?;
+        Ok(())
+    }
+
+    /// Copy the first `len` bytes into `out` as they are, then drop the first `consumed`.
+    /// Nothing is dropped if they don't fit.
+    fn read_prefix(
+        &mut self,
+        len: usize,
+        consumed: usize,
+        out: &mut [u8],
+    ) -> Result<usize, LineBufferRxError> {
+        if len > out.len() {
+            return Err(LineBufferRxError::AuxBufferTooSmall);
+        }
+
+        let (first, second) = self.slices(0, len);
+        out[..first.len()].copy_from_slice(first);
+        out[first.len()..len].copy_from_slice(second);
+        #[cfg(feature = "stats")]
+        {
+            self.stats.bytes_read += consumed as u64;
+        }
+        self.consume(consumed);
+
+        Ok(len)
+    }
+
+    /// Read the next line into a new `heapless::Vec`, without the terminator.
+    /// If the line is longer than `N`, `LineBufferRxError::AuxBufferTooSmall` is returned and the line is left in the buffer.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"OK\n").unwrap();
+    ///
+    /// let line = line_buffer.read_line_vec::<8>().unwrap();
+    /// assert_eq!(&line[..], b"OK");
+    /// ```
+    #[cfg(feature = "heapless")]
+    pub fn read_line_vec<const N: usize>(
+        &mut self,
+    ) -> Result<heapless::Vec<u8, N>, LineBufferRxError> {
+        if self.is_empty() {
+            return Err(LineBufferRxError::BufferEmpty);
+        }
+        let (line_len, terminator_len) = self.next_text_read()?;
+
+        let mut line = heapless::Vec::new();
+        for byte in self.line_bytes(line_len) {
+            line.push(byte)
+                .map_err(|_| LineBufferRxError::AuxBufferTooSmall)?;
+        }
+        self.finish_read(line_len, terminator_len);
+
+        Ok(line)
+    }
+
+    /// Read the next line into `out`, requiring it to be exactly `expected` bytes long.
+    ///
+    /// A line of any other length is consumed and reported as `LineBufferRxError::UnexpectedLineLength`,
+    /// so a malformed record doesn't block the ones behind it.
+    /// If `out` is shorter than `expected`, `LineBufferRxError::AuxBufferTooSmall` is returned and the line is left in the buffer.
+    /// ```rust
+    /// use strlinebuf::{LineBuffer, LineBufferRxError};
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"0042\n123\n").unwrap();
+    ///
+    /// let mut record = [0u8; 4];
+    /// line_buffer.read_line_exact(&mut record, 4).unwrap();
+    /// assert_eq!(&record, b"0042");
+    /// assert!(matches!(
+    ///     line_buffer.read_line_exact(&mut record, 4),
+    ///     Err(LineBufferRxError::UnexpectedLineLength { found: 3, expected: 4 })
+    /// ));
+    /// ```
+    pub fn read_line_exact(
+        &mut self,
+        out: &mut [u8],
+        expected: usize,
+    ) -> Result<(), LineBufferRxError> {
+        if self.is_empty() {
+            return Err(LineBufferRxError::BufferEmpty);
+        }
+        let (line_len, terminator_len) = self.next_text_read()?;
+
+        let found = self.line_bytes(line_len).count();
+        if found != expected {
+            self.finish_read(line_len, terminator_len);
+            return Err(LineBufferRxError::UnexpectedLineLength { found, expected });
+        }
+        if out.len() < expected {
+            return Err(LineBufferRxError::AuxBufferTooSmall);
+        }
+        self.copy_line(line_len, out);
+        self.finish_read(line_len, terminator_len);
+
+        Ok(())
+    }
+
+    /// Read the next line into `aux_buffer` and split it on `delimiter`, pointing the first
+    /// entries of `fields` at the pieces. Returns the number of fields.
+    ///
+    /// Every delimiter starts a new field, so `a,,b` is three fields with an empty one in the
+    /// middle, and an empty line is one empty field. Escape bytes are dropped before the line is
+    /// split, so they don't protect a delimiter. A line with more fields than `fields` holds is
+    /// consumed and reported as `LineBufferRxError::TooManyFields`, with `fields` holding the
+    /// first of them, so a malformed record doesn't block the ones behind it.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"$GPGLL,,,,,,V,N*64\n").unwrap();
+    ///
+    /// let mut aux_buffer = [0u8; 32];
+    /// let mut fields = [&b""[..]; 8];
+    /// let count = line_buffer.read_line_fields(&mut aux_buffer, b',', &mut fields).unwrap();
+    /// assert_eq!(count, 8);
+    /// assert_eq!(fields[0], b"$GPGLL");
+    /// assert_eq!(fields[1], b"");
+    /// assert_eq!(fields[7], b"N*64");
+    /// ```
+    pub fn read_line_fields<'a>(
+        &mut self,
+        aux_buffer: &'a mut [u8],
+        delimiter: u8,
+        fields: &mut [&'a [u8]],
+    ) -> Result<usize, LineBufferRxError> {
+        if self.is_empty() {
+            return Err(LineBufferRxError::BufferEmpty);
+        }
+        let (line_len, terminator_len) = self.next_text_read()?;
+
+        let len = self
+            .copy_line(line_len, aux_buffer)
+            .ok_or(LineBufferRxError::AuxBufferTooSmall)?;
+        self.finish_read(line_len, terminator_len);
+        let aux_buffer: &'a [u8] = aux_buffer;
+        let mut found = 0;
+        for field in aux_buffer[..len].split(|byte| *byte == delimiter) {
+            if let Some(slot) = fields.get_mut(found) {
+                *slot = field;
+            }
+            found += 1;
+        }
+
+        if found > fields.len() {
+            return Err(LineBufferRxError::TooManyFields { found });
+        }
+        Ok(found)
+    }
+
+    /// Read the next line if it starts with `prefix`, copying the remainder after the prefix into `out`.
+    /// Returns `Some(len)` with the number of bytes copied, or `None` if the line doesn't start with
+    /// `prefix`, in which case the line is left in the buffer.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"+CSQ: 21,99\n").unwrap();
+    ///
+    /// let mut value = [0u8; 32];
+    /// let len = line_buffer.read_line_stripping(b"+CSQ: ", &mut value).unwrap();
+    /// assert_eq!(len, Some(5));
+    /// assert_eq!(&value[..5], b"21,99");
+    /// ```
+    pub fn read_line_stripping(
+        &mut self,
+        prefix: &[u8],
+        out: &mut [u8],
+    ) -> Result<Option<usize>, LineBufferRxError> {
+        if self.is_empty() {
+            return Err(LineBufferRxError::BufferEmpty);
+        }
+        let (line_len, terminator_len) = self.next_text_read()?;
+
+        let mut bytes = self.line_bytes(line_len);
+        if !prefix.iter().all(|byte| bytes.next() == Some(*byte)) {
+            return Ok(None);
+        }
+        let copied = copy_into(bytes, out).ok_or(LineBufferRxError::AuxBufferTooSmall)?;
+        self.finish_read(line_len, terminator_len);
+
+        Ok(Some(copied))
+    }
+
+    /// Pass the next line to `f` in place, then consume it. Returns what `f` returns.
+    ///
+    /// The line is given as two slices, the second of which is only non-empty if the line wraps
+    /// around the end of the storage. The terminator is excluded, but escape bytes are not stripped.
+    /// `f` is not called if there is no complete line.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"temp=21\n").unwrap();
+    ///
+    /// let len = line_buffer.with_line(|first, second| first.len() + second.len()).unwrap();
+    /// assert_eq!(len, 7);
+    /// assert!(line_buffer.is_empty());
+    /// ```
+    pub fn with_line<R>(
+        &mut self,
+        f: impl FnOnce(&[u8], &[u8]) -> R,
+    ) -> Result<R, LineBufferRxError> {
+        self.try_with_line(|first, second| Ok::<R, core::convert::Infallible>(f(first, second)))
+            .map(|result| match result {
+                Ok(value) => value,
+                Err(never) => match never {},
+            })
+    }
+
+    /// Like `with_line`, but the line is only consumed if `f` returns `Ok`.
+    /// On `Err` the line is left in the buffer and the error is passed back.
+    pub fn try_with_line<R, E>(
+        &mut self,
+        f: impl FnOnce(&[u8], &[u8]) -> Result<R, E>,
+    ) -> Result<Result<R, E>, LineBufferRxError> {
+        if self.is_empty() {
+            return Err(LineBufferRxError::BufferEmpty);
+        }
+        let (line_len, terminator_len) = self.next_read()?;
+
+        let (first, second) = self.slices(0, line_len);
+        let result = f(first, second);
+        if result.is_ok() {
+            self.finish_read(line_len, terminator_len);
+        }
+
+        Ok(result)
+    }
+
+    /// Consume the next line only if `pred` holds for it, returning whether it did.
+    ///
+    /// Like `with_line`, the line is given as two slices and escape bytes are not stripped.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"# comment\nvalue\n").unwrap();
+    ///
+    /// let is_comment = |first: &[u8], _: &[u8]| first.starts_with(b"#");
+    /// assert_eq!(line_buffer.consume_line_if(is_comment), Ok(true));
+    /// assert_eq!(line_buffer.consume_line_if(is_comment), Ok(false));
+    /// assert_eq!(line_buffer.line_count(), 1);
+    /// ```
+    pub fn consume_line_if<F: Fn(&[u8], &[u8]) -> bool>(
+        &mut self,
+        pred: F,
+    ) -> Result<bool, LineBufferRxError> {
+        self.try_with_line(|first, second| if pred(first, second) { Ok(()) } else { Err(()) })
+            .map(|result| result.is_ok())
+    }
+
+    /// Borrow the next line straight from the buffer as a `&str`, without copying it anywhere.
+    /// The line is only consumed by `LineStr::consume`, so dropping the `LineStr` leaves it in the
+    /// buffer.
+    ///
+    /// If the line wraps around the end of the storage, the storage is first rotated with
+    /// `make_contiguous`. Like `with_line`, escape bytes are not stripped. If the line isn't valid
+    /// UTF-8, `LineBufferRxError::InvalidUtf8` is returned and the line is left in the buffer.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"temp=21\n").unwrap();
+    ///
+    /// let line = line_buffer.next_line_str().unwrap();
+    /// let value: u32 = line.strip_prefix("temp=").unwrap().parse().unwrap();
+    /// line.consume();
+    /// assert_eq!(value, 21);
+    /// assert!(line_buffer.is_empty());
+    /// ```
+    ///
+    /// The buffer can't be used while the line is borrowed:
+    /// ```compile_fail
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"temp=21\n").unwrap();
+    ///
+    /// let line = line_buffer.next_line_str().unwrap();
+    /// line_buffer.push_bytes(b"temp=22\n").unwrap();
+    /// assert_eq!(&*line, "temp=21");
+    /// ```
+    pub fn next_line_str(&mut self) -> Result<LineStr<'_, S, I, T>, LineBufferRxError> {
+        if self.is_empty() {
+            return Err(LineBufferRxError::BufferEmpty);
+        }
+        let (line_len, terminator_len) = self.next_text_read()?;
+
+        if !self.slices(0, line_len).1.is_empty() {
+            self.make_contiguous();
+        }
+        let (line, _) = self.slices(0, line_len);
+        if let Err(error) = core::str::from_utf8(line) {
+            return Err(LineBufferRxError::InvalidUtf8 {
+                valid_up_to: error.valid_up_to(),
+            });
+        }
+
+        Ok(LineStr {
+            line_buffer: self,
+            line_len,
+            terminator_len,
+        })
+    }
+
+    /// Pass each of the next `n` complete lines to `f` without consuming them.
+    ///
+    /// Like `with_line`, each line is given as two slices, the second of which is only non-empty if
+    /// the line wraps around the end of the storage, and escape bytes are not stripped.
+    /// Stops early if fewer than `n` complete lines are buffered.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"+CMGL: 1\nhello\nOK\n").unwrap();
+    ///
+    /// let mut lengths = [0; 2];
+    /// let mut i = 0;
+    /// line_buffer.peek_lines(2, |first, second| {
+    ///     lengths[i] = first.len() + second.len();
+    ///     i += 1;
+    /// });
+    /// assert_eq!(lengths, [8, 5]);
+    /// assert_eq!(line_buffer.line_count(), 3);
+    /// ```
+    pub fn peek_lines<F: FnMut(&[u8], &[u8])>(&self, n: usize, mut f: F) {
+        if n == 0 {
+            return;
+        }
+        let scanner = self.scanner();
+        let terminator = scanner.terminator;
+        if terminator.is_empty() {
+            if !self.empty {
+                let (first, second) = self.as_slices();
+                f(first, second);
+            }
+            return;
+        }
+
+        let mut scan = ScanState::<I>::EMPTY;
+        let mut line_start = 0;
+        let mut peeked = 0;
+        for i in 0..self.len() {
+            if scan.advance(self.byte_at(i), &scanner) {
+                let (first, second) = self.slices(line_start, i + 1 - terminator.len());
+                f(first, second);
+                peeked += 1;
+                if peeked == n {
+                    return;
+                }
+                line_start = i + 1;
+            }
+        }
+    }
+
+    /// Read up to `max_lines` lines in one call, copying each into `aux_buffer` followed by
+    /// `config.batch_separator`. Returns how many lines and bytes were transferred.
+    ///
+    /// Stops early once the next line (plus its separator) does not fit; that line stays buffered.
+    /// An error is only returned if not even one line could be read.
+    pub fn read_lines(
+        &mut self,
+        aux_buffer: &mut [u8],
+        max_lines: usize,
+    ) -> Result<BatchInfo, LineBufferRxError> {
+        if self.is_empty() {
+            return Err(LineBufferRxError::BufferEmpty);
+        }
+        let separator = match self.config.batch_separator {
+            BatchSeparator::Newline => b'\n',
+            BatchSeparator::NULL => b'\0',
+        };
+
+        let mut info = BatchInfo { lines: 0, bytes: 0 };
+        while info.lines < max_lines {
+            if info.lines > 0 && self.config.line_too_long == LineTooLongPolicy::Error {
+                // Leave an over-long line for the next call to report, rather than losing it mid-batch.
+                if let Some((line_len, terminator_len)) = self.next_line() {
+                    if self.is_line_too_long(line_len, terminator_len) {
+                        break;
+                    }
+                }
+            }
+            let (line_len, terminator_len) = match self.next_text_read() {
+                Ok(line) => line,
+                Err(error) if info.lines == 0 => return Err(error),
+                Err(_) => break,
+            };
+            let out = &mut aux_buffer[info.bytes..];
+            match self.copy_line(line_len, out) {
+                Some(copied) if copied < out.len() => {
+                    out[copied] = separator;
+                    info.bytes += copied + 1;
+                }
+                _ => break,
+            }
+            self.finish_read(line_len, terminator_len);
+            info.lines += 1;
+        }
+
+        if info.lines == 0 && max_lines > 0 {
+            return Err(LineBufferRxError::AuxBufferTooSmall);
+        }
+        Ok(info)
+    }
+
+    /// Read the lines up to the next blank line into `aux_buffer`, each ended according to
+    /// `config.paragraph_line_end`, then drop them along with the blank line. Returns the number
+    /// of bytes copied. Suits records made of several lines, such as HTTP headers.
+    ///
+    /// Blank lines before the record are skipped. Until the blank line after it has arrived,
+    /// `LineBufferRxError::NoLines` is returned, and `LineBufferRxError::AuxBufferTooSmall` if
+    /// the record doesn't fit in `aux_buffer`; either way the record stays buffered.
+    /// Escape bytes are dropped as for other reads, but read policies such as `max_line_length`
+    /// and `require_utf8` don't apply.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<64>::new();
+    /// line_buffer.push_bytes(b"\n[wifi]\nssid=home\n\n[mqtt]\n").unwrap();
+    ///
+    /// let mut aux_buffer = [0u8; 64];
+    /// let len = line_buffer.read_paragraph(&mut aux_buffer).unwrap();
+    /// assert_eq!(&aux_buffer[..len], b"[wifi]\nssid=home\n");
+    /// assert!(line_buffer.read_paragraph(&mut aux_buffer).is_err());
+    /// ```
+    pub fn read_paragraph(&mut self, aux_buffer: &mut [u8]) -> Result<usize, LineBufferRxError> {
+        if self.mid_line {
+            return Err(LineBufferRxError::MidLine);
+        }
+        while let Some((line_len, terminator_len)) = self.next_line() {
+            if !self.is_blank_line(0, line_len) {
+                break;
+            }
+            self.finish_read(line_len, terminator_len);
+        }
+        if self.is_empty() {
+            return Err(LineBufferRxError::BufferEmpty);
+        }
+        let lines = self
+            .line_positions()
+            .position(|(offset, line_len)| self.is_blank_line(offset, line_len))
+            .ok_or_else(|| self.no_lines())?;
+
+        let terminator_len = self.terminator_bytes().len();
+        let mut len = 0;
+        for (offset, line_len) in self.line_positions().take(lines) {
+            let line_end = offset + line_len;
+            let out = &mut aux_buffer[len..];
+            len += match self.config.paragraph_line_end {
+                ParagraphLineEnd::Keep => {
+                    let ending = (line_end..line_end + terminator_len).map(|i| self.byte_at(i));
+                    copy_into(self.unescaped(offset, line_end).chain(ending), out)
+                }
+                ParagraphLineEnd::Newline => {
+                    let text_end = line_end - usize::from(self.has_trailing_cr(line_end));
+                    copy_into(self.unescaped(offset, text_end).chain([b'\n']), out)
+                }
+            }
+            .ok_or(LineBufferRxError::AuxBufferTooSmall)?;
+        }
+        // The record's lines, then the blank line after it.
+        for _ in 0..=lines {
+            if let Some((line_len, terminator_len)) = self.next_line() {
+                self.finish_read(line_len, terminator_len);
+            }
+        }
+
+        Ok(len)
+    }
+
+    /// The logical offset from the read position and the length of each complete line, without
+    /// consuming them. Lengths exclude the terminator but include any escape bytes.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"GET\nHost: a\n\npartial").unwrap();
+    ///
+    /// let mut positions = line_buffer.line_positions();
+    /// assert_eq!(positions.next(), Some((0, 3)));
+    /// assert_eq!(positions.next(), Some((4, 7)));
+    /// assert_eq!(positions.next(), Some((12, 0)));
+    /// assert_eq!(positions.next(), None);
+    /// ```
+    pub fn line_positions(&self) -> LinePositions<'_> {
+        let (first, second) = self.as_slices();
+        LinePositions {
+            first,
+            second,
+            scanner: self.scanner(),
+            offset: 0,
+        }
+    }
+
+    /// Iterate over the buffered bytes in chunks of up to `chunk_size` bytes, such as one radio
+    /// packet each, without copying or consuming them. Chunks follow the logical order, however
+    /// the bytes lie in the storage.
+    ///
+    /// Panics if `chunk_size` is 0.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<16>::new();
+    /// line_buffer.push_bytes(b"hello, world\n").unwrap();
+    ///
+    /// let mut chunks = line_buffer.chunks(5);
+    /// assert_eq!(chunks.next(), Some((&b"hello"[..], &b""[..])));
+    /// assert_eq!(chunks.next(), Some((&b", wor"[..], &b""[..])));
+    /// assert_eq!(chunks.next(), Some((&b"ld\n"[..], &b""[..])));
+    /// assert_eq!(chunks.next(), None);
+    /// ```
+    pub fn chunks(&self, chunk_size: usize) -> Chunks<'_> {
+        assert!(chunk_size > 0, "chunk size must be > 0");
+        let (first, second) = self.as_slices();
+        Chunks {
+            first,
+            second,
+            chunk_size,
+        }
+    }
+
+    /// Hand out the buffered bytes a chunk of up to `chunk_size` bytes at a time, dropping each
+    /// only once `DrainChunks::consume` acknowledges it, for links that may need to resend.
+    ///
+    /// Panics if `chunk_size` is 0.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<16>::new();
+    /// line_buffer.push_bytes(b"hello, world\n").unwrap();
+    ///
+    /// let mut drain = line_buffer.drain_chunks(8);
+    /// while let Some((first, second)) = drain.next_chunk() {
+    ///     let sent = first.len() + second.len();
+    ///     drain.consume(sent);
+    /// }
+    /// assert!(line_buffer.is_empty());
+    /// ```
+    pub fn drain_chunks(&mut self, chunk_size: usize) -> DrainChunks<'_, S, I, T> {
+        assert!(chunk_size > 0, "chunk size must be > 0");
+        DrainChunks {
+            line_buffer: self,
+            chunk_size,
+        }
+    }
+
+    /// Copy as many buffered bytes as fit into `out`, in logical order, without consuming them.
+    /// Returns the number of bytes copied.
+    pub fn peek_all(&self, out: &mut [u8]) -> usize {
+        let (first, second) = self.as_slices();
+        let first_len = first.len().min(out.len());
+        out[..first_len].copy_from_slice(&first[..first_len]);
+        let second_len = second.len().min(out.len() - first_len);
+        out[first_len..first_len + second_len].copy_from_slice(&second[..second_len]);
+
+        first_len + second_len
+    }
+
+    /// Copy buffered bytes starting at logical `offset` into `out`, as many as fit, without
+    /// consuming them. Returns the number of bytes copied, which is 0 if `offset` is past the end.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"$GPGGA,123519,4807.038,N\n").unwrap();
+    ///
+    /// let mut time = [0u8; 6];
+    /// assert_eq!(line_buffer.peek_range(7, &mut time), 6);
+    /// assert_eq!(&time, b"123519");
+    /// ```
+    pub fn peek_range(&self, offset: usize, out: &mut [u8]) -> usize {
+        let len = self.len();
+        if offset >= len {
+            return 0;
+        }
+        let count = out.len().min(len - offset);
+        let (first, second) = self.slices(offset, offset + count);
+        out[..first.len()].copy_from_slice(first);
+        out[first.len()..count].copy_from_slice(second);
+
+        count
+    }
+
+    /// The byte `n` positions back from the write position, where `0` is the most recently pushed byte.
+    /// Returns `None` if fewer than `n + 1` bytes are buffered.
+    pub fn recent_byte(&self, n: usize) -> Option<u8> {
+        let len = self.len();
+        if n >= len {
+            return None;
+        }
+        Some(self.byte_at(len - 1 - n))
+    }
+
+    /// Whether `needle` appears anywhere in the buffered bytes, including across line boundaries.
+    /// An empty needle is always found.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"AT+RST\r\nOK").unwrap();
+    ///
+    /// assert!(line_buffer.contains(b"RST"));
+    /// assert!(!line_buffer.contains(b"ERROR"));
+    /// ```
+    pub fn contains(&self, needle: &[u8]) -> bool {
+        self.find(needle).is_some()
+    }
+
+    /// Logical offset from the read position of the first occurrence of `needle`, or `None` if it
+    /// isn't buffered. An empty needle is found at offset `0`.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"key=value;").unwrap();
+    ///
+    /// assert_eq!(line_buffer.find(b"="), Some(3));
+    /// assert_eq!(line_buffer.find(b";;"), None);
+    /// ```
+    pub fn find(&self, needle: &[u8]) -> Option<usize> {
+        let len = self.len();
+        if needle.len() > len {
+            return None;
+        }
+        (0..=len - needle.len()).find(|offset| {
+            needle
+                .iter()
+                .enumerate()
+                .all(|(i, byte)| self.byte_at(offset + i) == *byte)
+        })
+    }
+
+    /// Whether the buffered bytes are valid UTF-8, including a character split across the end of
+    /// the storage. A character still waiting for its last bytes counts as invalid.
+    pub fn is_valid_utf8(&self) -> bool {
+        let (first, second) = self.as_slices();
+        let valid = match core::str::from_utf8(first) {
+            Ok(_) => return core::str::from_utf8(second).is_ok(),
+            Err(error) if error.error_len().is_some() => return false,
+            Err(error) => error.valid_up_to(),
+        };
+
+        // `first` ends part way into a character, so finish it with bytes from `second`.
+        let head = &first[valid..];
+        let mut carry = [0u8; 4];
+        carry[..head.len()].copy_from_slice(head);
+        for taken in 1..=(carry.len() - head.len()).min(second.len()) {
+            carry[head.len() + taken - 1] = second[taken - 1];
+            match core::str::from_utf8(&carry[..head.len() + taken]) {
+                Ok(_) => return core::str::from_utf8(&second[taken..]).is_ok(),
+                Err(error) if error.error_len().is_some() => return false,
+                Err(_) => {}
+            }
+        }
+        false
+    }
+
+    /// Count the buffered bytes for which `pred` returns `true`, without consuming them.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<16>::new();
+    /// line_buffer.push_bytes(b"ok\x01\xff\n").unwrap();
+    ///
+    /// let noise = line_buffer.count_matching(|byte| !byte.is_ascii_graphic() && byte != b'\n');
+    /// assert_eq!(noise, 2);
+    /// ```
+    pub fn count_matching<F: Fn(u8) -> bool>(&self, pred: F) -> usize {
+        let (first, second) = self.as_slices();
+        first
+            .iter()
+            .chain(second)
+            .filter(|byte| pred(**byte))
+            .count()
+    }
+
+    /// 32-bit FNV-1a hash of the next line, without the terminator or escape bytes, leaving it in the buffer.
+    /// Handy for skipping repeated lines without copying them out first.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"STATUS OK\nSTATUS OK\n").unwrap();
+    ///
+    /// let first = line_buffer.peek_line_hash().unwrap();
+    /// line_buffer.read_line_bytes(&mut [0u8; 32]).unwrap();
+    /// assert_eq!(line_buffer.peek_line_hash().unwrap(), first);
+    /// ```
+    pub fn peek_line_hash(&self) -> Result<u32, LineBufferRxError> {
+        if self.is_empty() {
+            return Err(LineBufferRxError::BufferEmpty);
+        }
+        let (line_len, _) = self.find_line().ok_or_else(|| self.no_lines())?;
+
+        Ok(self.line_bytes(line_len).fold(0x811c_9dc5, |hash, byte| {
+            (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+        }))
+    }
+
+    /// XOR of all the buffered bytes, terminators and escape bytes included, as NMEA sentences
+    /// use for their checksum. 0 for an empty buffer.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"GPGLL,,,,,,V,N").unwrap();
+    /// assert_eq!(line_buffer.checksum_xor(), 0x64);
+    /// ```
+    pub fn checksum_xor(&self) -> u8 {
+        let (first, second) = self.as_slices();
+        first
+            .iter()
+            .chain(second)
+            .fold(0, |checksum, byte| checksum ^ byte)
+    }
+
+    /// CRC-16/CCITT-FALSE (polynomial `0x1021`, initial value `0xFFFF`, no reflection or final
+    /// XOR) of all the buffered bytes, terminators and escape bytes included.
+    ///
+    /// It is computed a bit at a time rather than from a lookup table, so it costs no flash.
+    /// ```rust
+    /// use strlinebuf::LineBuffer;
+    ///
+    /// let mut line_buffer = LineBuffer::<32>::new();
+    /// line_buffer.push_bytes(b"123456789").unwrap();
+    /// assert_eq!(line_buffer.checksum_crc16(), 0x29B1);
+    /// ```
+    pub fn checksum_crc16(&self) -> u16 {
+        let (first, second) = self.as_slices();
+        first.iter().chain(second).fold(0xFFFF, |crc, byte| {
+            (0..8).fold(crc ^ (u16::from(*byte) << 8), |crc, _| match crc & 0x8000 {
+                0 => crc << 1,
+                _ => (crc << 1) ^ 0x1021,
+            })
+        })
+    }
+
+    /// The buffered bytes in logical order, split in two where they wrap around the end of the storage.
+    fn as_slices(&self) -> (&[u8], &[u8]) {
+        if self.empty {
+            (&[], &[])
+        } else if self.end.get() > self.start.get() {
+            (
+                &self.buffer.as_slice()[self.start.get()..self.end.get()],
+                &[],
+            )
+        } else {
+            (
+                &self.buffer.as_slice()[self.start.get()..],
+                &self.buffer.as_slice()[..self.end.get()],
+            )
+        }
+    }
+
+    /// The bytes at logical positions `from..to`, split in two where they wrap around the end of the storage.
+    fn slices(&self, from: usize, to: usize) -> (&[u8], &[u8]) {
+        let (first, second) = self.as_slices();
+        if from >= first.len() {
+            return (&second[from - first.len()..to - first.len()], &[]);
+        }
+        let first_end = first.len().min(to);
+        (&first[from..first_end], &second[..to - first_end])
+    }
+
+    /// Wrap a physical index that has run at most one capacity past the end of the storage.
+    ///
+    /// Power-of-two capacities use a mask, everything else a compare-and-subtract.
+    /// Both avoid a `%`, which is a library call on cores without a hardware divider.
+    #[inline(always)]
+    fn wrap(&self, index: usize) -> usize {
+        let capacity = self.capacity();
+        if capacity.is_power_of_two() {
+            index & (capacity - 1)
+        } else if index >= capacity {
+            index - capacity
+        } else {
+            index
+        }
+    }
+
+    /// Physical index `i` moved `n` places forward around the ring.
+    ///
+    /// Requires `i < capacity` and `n <= capacity`.
+    #[inline(always)]
+    fn wrap_add(&self, i: usize, n: usize) -> usize {
+        self.wrap(i + n)
+    }
+
+    /// Physical index `i` moved `n` places back around the ring.
+    ///
+    /// Requires `i < capacity` and `n <= capacity`.
+    #[inline(always)]
+    fn wrap_sub(&self, i: usize, n: usize) -> usize {
+        self.wrap(i + self.capacity() - n)
+    }
+
+    /// The byte at logical `index`, counted from `start`.
+    fn byte_at(&self, index: usize) -> u8 {
+        self.buffer.as_slice()[self.wrap_add(self.start.get(), index)]
+    }
+
+    /// Locate the next complete line, returning its length and the length of its terminator.
+    /// The line length includes any escape bytes.
+    fn find_line(&self) -> Option<(usize, usize)> {
+        let terminator = self.terminator_bytes();
+        if terminator.is_empty() {
+            return if self.empty {
+                None
+            } else {
+                Some((self.len(), 0))
+            };
+        }
+        if !self.scan_is_stale() && self.scan.lines == I::ZERO {
+            return None;
+        }
+        let scanner = self.scanner();
+        let (first, second) = self.as_slices();
+        let mut scan = ScanState::<I>::EMPTY;
+        let end = match scanner.scan(&mut scan, first) {
+            Some(i) => i,
+            None => first.len() + scanner.scan(&mut scan, second)?,
+        };
+        Some((end + 1 - terminator.len(), terminator.len()))
+    }
+
+    /// A scanner for the current terminator and escape byte.
+    #[inline(always)]
+    fn scanner(&self) -> LineScanner {
+        LineScanner {
+            terminator: self.terminator_bytes(),
+            escape: self.config.escape,
+            ignore_case: T::BYTES.is_none() && self.config.terminator.ignores_case(),
+        }
+    }
+
+    /// Like `find_line`, but first brings the line count up to date with `config`, so that a
+    /// reader polling for a line that hasn't arrived yet only pays for the rescan once.
+    fn next_line(&mut self) -> Option<(usize, usize)> {
+        self.sync_line_count();
+        self.find_line()
+    }
+
+    /// Hand the first `line_len` bytes, with escape bytes dropped, to `emit` as UTF-8 text.
+    fn line_text(
+        &self,
+        line_len: usize,
+        emit: &mut impl FnMut(&str) -> core::fmt::Result,
+    ) -> Result<(), Utf8Stop> {
+        let (first, second) = self.slices(0, line_len);
+        let escape = self.config.escape;
+        let mut escaped = false;
+        let mut stream = Utf8Stream::EMPTY;
+        for mut piece in [first, second] {
+            while !piece.is_empty() {
+                // Feed up to the next escape byte, leaving it out.
+                let split = piece
+                    .iter()
+                    .position(|byte| {
+                        escaped = !escaped && Some(*byte) == escape;
+                        escaped
+                    })
+                    .unwrap_or(piece.len());
+                stream.feed(&piece[..split], emit)?;
+                piece = &piece[(split + 1).min(piece.len())..];
+            }
+        }
+        stream.finish()
+    }
+
+    /// The first `line_len` bytes in logical order, with escape bytes dropped.
+    fn line_bytes(&self, line_len: usize) -> impl Iterator<Item = u8> + '_ {
+        self.unescaped(0, line_len)
+    }
+
+    /// The bytes from logical `from` up to `to`, with escape bytes dropped. `from` has to be the
+    /// start of a line, so the first byte isn't escaped by one before it.
+    fn unescaped(&self, from: usize, to: usize) -> impl Iterator<Item = u8> + '_ {
+        let escape = self.config.escape;
+        let mut escaped = false;
+        (from..to).map(|i| self.byte_at(i)).filter(move |byte| {
+            let is_escape = !escaped && Some(*byte) == escape;
+            escaped = is_escape;
+            !is_escape
+        })
+    }
+
+    /// Consume a line that a read method has handed out.
+    fn finish_read(&mut self, line_len: usize, terminator_len: usize) {
+        #[cfg(feature = "stats")]
+        {
+            let stats = &mut self.stats;
+            stats.bytes_read += (line_len + terminator_len) as u64;
+            stats.lines_read = stats.lines_read.saturating_add(1);
+            stats.max_line_len = stats.max_line_len.max(line_len);
+        }
+        self.consume_line(line_len, terminator_len);
+    }
+
+    /// Count `count` bytes accepted by a push.
+    #[inline(always)]
+    fn record_pushed(&mut self, count: usize) {
+        #[cfg(feature = "stats")]
+        {
+            self.stats.bytes_pushed += count as u64;
+            self.stats.max_fill = self.stats.max_fill.max(self.len());
+        }
+        let _ = count;
+        if let Some(idle_flush) = &mut self.idle_flush {
+            idle_flush.pushed = true;
+        }
+        self.record_fill();
+    }
+
+    /// Raise the high watermark if the buffer has never been this full.
+    #[inline(always)]
+    fn record_fill(&mut self) {
+        let len = self.len();
+        if len > self.high_watermark.get() {
+            self.high_watermark = I::new(len);
+        }
+        self.update_flow();
+    }
+
+    /// Latch a flow change if the length crossed one of `config.flow_thresholds`.
+    fn update_flow(&mut self) {
+        let Some(thresholds) = self.config.flow_thresholds else {
+            return;
+        };
+        let len = self.len();
+        let change = match self.flow {
+            FlowState::Flowing if len >= thresholds.high => FlowChange::Pause,
+            FlowState::Paused if len <= thresholds.low => FlowChange::Resume,
+            _ => return,
+        };
+        self.flow = match change {
+            FlowChange::Pause => FlowState::Paused,
+            FlowChange::Resume => FlowState::Flowing,
+        };
+        // A change that undoes one nobody has seen yet leaves nothing to report.
+        self.flow_change = match self.flow_change {
+            Some(_) => None,
+            None => Some(change),
+        };
+    }
+
+    /// Make room for up to `additional` more bytes, as `policy` allows.
+    /// Returns whether there is now room for at least one.
+    fn overflow(&mut self, additional: usize, policy: OverflowPolicy) -> bool {
+        match policy {
+            OverflowPolicy::Reject | OverflowPolicy::RejectAll => return false,
+            OverflowPolicy::OverwriteOldest => {
+                let count = additional
+                    .saturating_sub(self.remaining_capacity())
+                    .min(self.len());
+                self.consume(count);
+                self.record_dropped(count);
+            }
+            OverflowPolicy::DropOldestLine => {
+                while self.remaining_capacity() < additional {
+                    match self.next_line() {
+                        Some((line_len, terminator_len)) if terminator_len > 0 => {
+                            self.consume_line(line_len, terminator_len);
+                            self.record_dropped(line_len + terminator_len);
+                            self.record_dropped_line();
+                        }
+                        _ => break,
+                    }
+                }
+            }
+        }
+        self.remaining_capacity() > 0
+    }
+
+    /// Number of bytes from the front up to the end of the last complete line, terminator included.
+    fn complete_lines_len(&self) -> usize {
+        let scanner = self.scanner();
+        if scanner.terminator.is_empty() || !self.has_complete_line() {
+            return 0;
+        }
+        let mut scan = ScanState::<I>::EMPTY;
+        let mut complete = 0;
+        for i in 0..self.len() {
+            if scan.advance(self.byte_at(i), &scanner) {
+                complete = i + 1;
+            }
+        }
+        complete
+    }
+
+    /// Count a whole line evicted to make room for new bytes.
+    #[inline(always)]
+    fn record_dropped_line(&mut self) {
+        #[cfg(feature = "stats")]
+        {
+            self.stats.lines_dropped = self.stats.lines_dropped.saturating_add(1);
+        }
+    }
+
+    /// Count a line of `count` bytes passed over by a read, see `is_skipped_line`.
+    #[inline(always)]
+    fn record_skipped(&mut self, count: usize) {
+        #[cfg(feature = "stats")]
+        {
+            let skipped = u32::try_from(count).unwrap_or(u32::MAX);
+            self.stats.bytes_skipped = self.stats.bytes_skipped.saturating_add(skipped);
+            self.stats.lines_skipped = self.stats.lines_skipped.saturating_add(1);
+        }
+        let _ = count;
+    }
+
+    /// Count `count` bytes refused by a push.
+    #[inline(always)]
+    fn record_rejected(&mut self, count: usize) {
+        #[cfg(feature = "stats")]
+        {
+            let rejected = u32::try_from(count).unwrap_or(u32::MAX);
+            self.stats.bytes_rejected = self.stats.bytes_rejected.saturating_add(rejected);
+        }
+        let _ = count;
+    }
+
+    /// Count `count` bytes thrown away without being read.
+    #[inline(always)]
+    fn record_dropped(&mut self, count: usize) {
+        #[cfg(feature = "stats")]
+        {
+            let dropped = u32::try_from(count).unwrap_or(u32::MAX);
+            self.stats.bytes_dropped = self.stats.bytes_dropped.saturating_add(dropped);
+        }
+        let _ = count;
+    }
+
+    /// Find the next line for a read, first applying `config.line_too_long` to an over-long one.
+    fn next_read(&mut self) -> Result<(usize, usize), LineBufferRxError> {
+        if self.mid_line {
+            return Err(LineBufferRxError::MidLine);
+        }
+        loop {
+            let (line_len, terminator_len) = self.next_read_line()?;
+            if self.is_skipped_line(line_len, terminator_len) {
+                self.consume_line(line_len, terminator_len);
+                self.record_skipped(line_len + terminator_len);
+                continue;
+            }
+            if !self.is_line_too_long(line_len, terminator_len) {
+                return Ok((line_len, terminator_len));
+            }
+            match self.config.line_too_long {
+                LineTooLongPolicy::Truncate => {
+                    return Ok((self.truncate_line(line_len), terminator_len));
+                }
+                LineTooLongPolicy::Discard => {
+                    self.consume_line(line_len, terminator_len);
+                    self.record_dropped(line_len + terminator_len);
+                }
+                LineTooLongPolicy::Error => {
+                    self.consume_line(line_len, terminator_len);
+                    self.record_dropped(line_len + terminator_len);
+                    return Err(LineBufferRxError::LineTooLong { len: line_len });
+                }
+            }
+        }
+    }
+
+    /// Like `next_read`, but for reads that copy the line out as data, also applying
+    /// `config.invalid_utf8` to a line that isn't valid UTF-8 if `config.require_utf8` is set.
+    fn next_text_read(&mut self) -> Result<(usize, usize), LineBufferRxError> {
+        loop {
+            let (line_len, terminator_len) = self.next_read()?;
+            if !self.config.require_utf8 {
+                return Ok((line_len, terminator_len));
+            }
+            let valid_up_to = match self.line_text(line_len, &mut |_| Ok(())) {
+                Err(Utf8Stop::Invalid { valid_up_to }) => valid_up_to,
+                _ => return Ok((line_len, terminator_len)),
+            };
+            match self.config.invalid_utf8 {
+                InvalidUtf8Policy::Discard => {
+                    self.consume_line(line_len, terminator_len);
+                    self.record_dropped(line_len + terminator_len);
+                }
+                InvalidUtf8Policy::Error => {
+                    return Err(LineBufferRxError::InvalidUtf8 { valid_up_to });
+                }
+            }
+        }
+    }
+
+    /// Find the next line like `next_line`, reading a `\r` that `config.strip_trailing_cr` drops as
+    /// part of the terminator.
+    fn next_read_line(&mut self) -> Result<(usize, usize), LineBufferRxError> {
+        let (mut line_len, mut terminator_len) = self.next_line().ok_or_else(|| self.no_lines())?;
+        if self.has_trailing_cr(line_len) {
+            line_len -= 1;
+            terminator_len += 1;
+        }
+        Ok((line_len, terminator_len))
+    }
+
+    /// Whether the line found by `find_line` ends in a `\r` that `config.strip_trailing_cr` drops.
+    /// An escaped `\r` is part of the line.
+    fn has_trailing_cr(&self, line_len: usize) -> bool {
+        if !self.config.strip_trailing_cr
+            || self.terminator_bytes() != b"\n"
+            || line_len == 0
+            || self.byte_at(line_len - 1) != b'\r'
+        {
+            return false;
+        }
+        !self.is_escaped(line_len - 1)
+    }
+
+    /// Whether the line at logical `offset` found by `line_positions` reads as empty, counting a
+    /// `\r` that `config.strip_trailing_cr` drops as part of the terminator.
+    fn is_blank_line(&self, offset: usize, line_len: usize) -> bool {
+        line_len == 0 || (line_len == 1 && self.has_trailing_cr(offset + 1))
+    }
+
+    /// Whether the byte at logical `index` is escaped, by an odd run of escape bytes before it.
+    fn is_escaped(&self, index: usize) -> bool {
+        match self.config.escape {
+            Some(escape) => {
+                let escapes = (0..index)
+                    .rev()
+                    .take_while(|&i| self.byte_at(i) == escape)
+                    .count();
+                escapes % 2 == 1
+            }
+            None => false,
+        }
+    }
+
+    /// Whether a line found by `next_read_line` is one that `config.skip_empty_lines` or
+    /// `config.skip_comment_prefixes` has reads drop. Only lines ended by a terminator are.
+    fn is_skipped_line(&self, line_len: usize, terminator_len: usize) -> bool {
+        if terminator_len == 0 {
+            return false;
+        }
+        if line_len == 0 {
+            return self.config.skip_empty_lines && !self.terminator_left;
+        }
+        match self.config.skip_comment_prefixes {
+            Some(prefixes) => (0..line_len)
+                .map(|i| self.byte_at(i))
+                .find(|byte| !byte.is_ascii_whitespace())
+                .is_some_and(|byte| prefixes.contains(&byte)),
+            None => false,
+        }
+    }
+
+    /// Whether a line found by `find_line` is over `config.max_line_length`.
+    fn is_line_too_long(&self, line_len: usize, terminator_len: usize) -> bool {
+        match self.config.max_line_length {
+            Some(max) => terminator_len > 0 && line_len > max,
+            None => false,
+        }
+    }
+
+    /// Cut the line at the front down to its first `max_line_length` bytes by dropping the rest,
+    /// so every read method sees the shortened line. Returns the new line length.
+    fn truncate_line(&mut self, line_len: usize) -> usize {
+        let mut keep = self.config.max_line_length.unwrap_or(line_len);
+        if self.config.truncate_at_char_boundary {
+            // Back up over the continuation bytes of a character the cut would split.
+            let mut backed_up = 0;
+            while keep > 0 && backed_up < 3 && matches!(self.byte_at(keep), 0x80..=0xBF) {
+                keep -= 1;
+                backed_up += 1;
+            }
+        }
+        // An escape byte whose partner is cut off would escape the terminator instead.
+        if let Some(escape) = self.config.escape {
+            let mut escaped = false;
+            for i in 0..keep {
+                escaped = !escaped && self.byte_at(i) == escape;
+            }
+            if escaped {
+                keep -= 1;
+            }
+        }
+
+        // Slide the kept bytes up against the terminator, then drop the gap left at the front.
+        let excess = line_len - keep;
+        let start = self.start.get();
+        for i in (0..keep).rev() {
+            let byte = self.buffer.as_slice()[self.wrap_add(start, i)];
+            let to = self.wrap_add(start, i + excess);
+            self.buffer.range_mut(to..to + 1)[0] = byte;
+        }
+        self.forget_rewind();
+        self.start = I::new(self.wrap_add(start, excess));
+        self.reset_scan_pos();
+        self.update_flow();
+        self.record_dropped(excess);
+        self.truncated = true;
+
+        keep
+    }
+
+    /// The error for a read attempted before a complete line has arrived.
+    fn no_lines(&self) -> LineBufferRxError {
+        LineBufferRxError::NoLines {
+            pending: self.len(),
+        }
+    }
+
+    /// Read the next line into `out`, for `read_line_bytes`.
+    fn read_line_into(&mut self, out: &mut [u8]) -> Result<usize, LineBufferRxError> {
+        if self.is_empty() {
+            return Err(LineBufferRxError::BufferEmpty);
+        }
+        let (line_len, terminator_len) = self.next_text_read()?;
+
+        let bytes_read = self
+            .copy_line(line_len, out)
+            .ok_or(LineBufferRxError::AuxBufferTooSmall)?;
+        self.finish_read(line_len, terminator_len);
+
+        Ok(bytes_read)
+    }
+
+    /// Copy the first `line_len` bytes into `out`, dropping escape bytes.
+    /// Returns the number of bytes copied, or `None` if they don't fit.
+    fn copy_line(&self, line_len: usize, out: &mut [u8]) -> Option<usize> {
+        copy_into(self.line_bytes(line_len), out)
+    }
+
+    /// The terminator bytes, fixed by `T` or taken from `config.terminator`.
+    #[inline(always)]
+    fn terminator_bytes(&self) -> &'static [u8] {
+        match T::BYTES {
+            Some(bytes) => bytes,
+            None => self.config.terminator.bytes(),
+        }
+    }
+
+    /// The terminator a line ended with, `\r\n` if `config.strip_trailing_cr` took a `\r` off it.
+    fn terminator_kind(&self, stripped_cr: bool) -> Terminator {
+        if stripped_cr {
+            return Terminator::CarriageReturnNewline;
+        }
+        match T::BYTES {
+            Some(bytes) => Terminator::try_from(bytes).unwrap_or(Terminator::Sequence(bytes)),
+            None => self.config.terminator,
+        }
+    }
+
+    /// Whether `config` was replaced since the lines were last counted.
+    fn scan_is_stale(&self) -> bool {
+        self.config_changed
+    }
+
+    /// Count the complete lines in the buffer from scratch, using the current `config`.
+    fn count_lines(&self) -> ScanState<I> {
+        let scanner = self.scanner();
+        let (first, second) = self.as_slices();
+        let mut scan = ScanState::EMPTY;
+        scan.advance_slice(first, &scanner);
+        scan.advance_slice(second, &scanner);
+        scan
+    }
+
+    /// Recount the lines if `config` was replaced since they were last counted.
+    fn sync_line_count(&mut self) {
+        // Every push and read comes through here.
+        debug_assert!(
+            !self.in_line_handler,
+            "the line handler must not call back into the buffer"
+        );
+        if self.scan_is_stale() {
+            self.config_changed = false;
+            self.scan = self.count_lines();
+            self.reset_scan_pos();
+        }
+    }
+
+    /// Restart `scan` from `start`, for when `start` moves or the bytes after it change.
+    fn reset_scan_pos(&mut self) {
+        self.scan_pos = I::ZERO;
+        self.scan_pos_state = ScanState::EMPTY;
+    }
+
+    /// Pass the lines completed by a push to `config.line_handler`, given how many complete lines
+    /// there were before it.
+    fn handle_lines(&mut self, lines_before: usize) {
+        let Some(handler) = self.config.line_handler else {
+            return;
+        };
+        if self.scan.lines.get() <= lines_before {
+            return;
+        }
+        if handler.consume {
+            while let Some((line_len, terminator_len)) = self.next_line() {
+                self.in_line_handler = true;
+                let (first, second) = self.slices(0, line_len);
+                (handler.on_line)(first, second);
+                self.in_line_handler = false;
+                self.finish_read(line_len, terminator_len);
+            }
+        } else {
+            self.in_line_handler = true;
+            for (offset, len) in self.line_positions().skip(lines_before) {
+                let (first, second) = self.slices(offset, offset + len);
+                (handler.on_line)(first, second);
+            }
+            self.in_line_handler = false;
+        }
+    }
+
+    /// Count the line completed by the most recently pushed byte, if any.
+    fn count_pushed_byte(&mut self) {
+        let byte = self.byte_at(self.len() - 1);
+        let scanner = self.scanner();
+        self.scan.advance(byte, &scanner);
+    }
+
+    /// Account for `count` bytes already written into the storage at the write position.
+    ///
+    /// Requires `count <= contiguous_write_len()`.
+    fn commit_written(&mut self, count: usize) {
+        if count == 0 {
+            return;
+        }
+        let lines = self.scan.lines.get();
+        let end = self.end.get();
+        let scanner = self.scanner();
+        self.scan
+            .advance_slice(&self.buffer.as_slice()[end..end + count], &scanner);
+        self.empty = false;
+        self.end = I::new(self.wrap_add(end, count));
+        self.forget_overwritten();
+        self.record_pushed(count);
+        self.handle_lines(lines);
+    }
+
+    /// Drop any already-read bytes that the latest write landed on from the rewind history.
+    fn forget_overwritten(&mut self) {
+        let room = self.remaining_capacity();
+        if self.rewindable.get() > room {
+            self.rewindable = I::new(room);
+            self.rewind_lost = true;
+        }
+    }
+
+    /// Give up the rewind history, for operations that move bytes around in the storage.
+    fn forget_rewind(&mut self) {
+        if self.rewindable != I::ZERO {
+            self.rewindable = I::ZERO;
+            self.rewind_lost = true;
+        }
+    }
+
+    /// Try to make room for `additional` more bytes by growing the storage.
+    /// Returns whether there is now room for all of them.
+    fn grow_for(&mut self, additional: usize) -> bool {
+        let capacity = self.capacity();
+        let len = self.len();
+        if !self.buffer.try_grow(len + additional) {
+            return false;
+        }
+        self.forget_rewind();
+        if len > 0 && self.end.get() <= self.start.get() {
+            // The storage grew at the end, so data that wrapped around it must be put back in order.
+            self.buffer.as_mut_slice()[..capacity].rotate_left(self.start.get());
+            self.start = I::ZERO;
+            self.end = I::new(len);
+        }
+        self.remaining_capacity() >= additional
+    }
+
+    /// Advance `start` by `count` bytes without touching the line count.
+    fn advance_start(&mut self, count: usize) {
+        self.start = I::new(self.wrap_add(self.start.get(), count));
+        self.truncated = false;
+        self.terminator_left = false;
+        self.reset_scan_pos();
+        self.rewindable = I::new(self.rewindable.get() + count);
+        if self.start == self.end {
+            self.empty = true;
+        }
+        self.update_flow();
+    }
+
+    /// Drop the next line, as returned by `find_line`, along with its terminator.
+    fn consume_line(&mut self, line_len: usize, terminator_len: usize) {
+        if terminator_len == 0 {
+            self.consume(line_len);
+            return;
+        }
+        self.sync_line_count();
+        self.advance_start(line_len + terminator_len);
+        self.mid_line = false;
+        self.scan.lines = I::new(self.scan.lines.get() - 1);
+    }
+
+    /// Drop `count` bytes from the front of the buffer, keeping the line count coherent.
+    fn consume(&mut self, count: usize) {
+        self.sync_line_count();
+        if count >= self.len() {
+            self.rewindable = I::new(self.rewindable.get() + self.len());
+            self.start = self.end;
+            self.empty = true;
+            self.truncated = false;
+            self.mid_line = false;
+            self.terminator_left = false;
+            self.reset_scan_pos();
+            self.scan = ScanState::EMPTY;
+            self.update_flow();
+            return;
+        }
+        let mut remaining = count;
+        let mut torn = false;
+        while let Some((line_len, terminator_len)) = self.find_line() {
+            if terminator_len == 0 || remaining < line_len + terminator_len {
+                torn = remaining > line_len;
+                break;
+            }
+            self.consume_line(line_len, terminator_len);
+            remaining -= line_len + terminator_len;
+        }
+        self.advance_start(remaining);
+
+        // Whole lines come off the front without disturbing the count. Anything else may:
+        // a torn terminator, a partial match that started in the dropped bytes, or a dropped
+        // escape byte can all change how the remaining bytes split into lines.
+        if torn
+            || self.scan.matched.get() > self.len()
+            || (remaining > 0 && self.config.escape.is_some())
+        {
+            self.scan = self.count_lines();
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+    use super::*;
+
+    /// Small xorshift generator so randomized tests stay deterministic and dependency-free.
+    struct Rng(u32);
+
+    impl Rng {
+        fn next(&mut self) -> u32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            self.next() as usize % n
+        }
+
+        /// A byte biased towards terminator characters.
+        fn byte(&mut self) -> u8 {
+            [b'a', b'b', b'\\', b'\r', b'\n', b'\0'][self.below(6)]
+        }
+    }
+
+    /// Keep a configuration built at run time for the rest of the test run.
+    fn leak(config: LineBufferConfig) -> &'static LineBufferConfig {
+        extern crate std;
+        std::boxed::Box::leak(std::boxed::Box::new(config))
+    }
+
+    /// Largest capacity the reference implementations below can handle.
+    const NAIVE_CAPACITY: usize = 512;
+
+    /// Copy the buffered bytes out in logical order, returning how many there are.
+    fn contents<S: Storage, I: RingIndex>(
+        line_buffer: &RawLineBuffer<S, I>,
+        out: &mut [u8],
+    ) -> usize {
+        let len = line_buffer.len();
+        let storage = line_buffer.buffer.as_slice();
+        for (i, byte) in out[..len].iter_mut().enumerate() {
+            *byte = storage[(line_buffer.start.get() + i) % storage.len()];
+        }
+        len
+    }
+
+    /// Reference implementation: scan the whole buffer from scratch for the first terminator.
+    fn naive_find_line<S: Storage, I: RingIndex>(
+        line_buffer: &RawLineBuffer<S, I>,
+    ) -> Option<(usize, usize)> {
+        let mut data = [0u8; NAIVE_CAPACITY];
+        let len = contents(line_buffer, &mut data);
+        let terminator = line_buffer.config.terminator.bytes();
+        if terminator.is_empty() {
+            return if len == 0 { None } else { Some((len, 0)) };
+        }
+        let mut escaped = false;
+        for i in 0..len {
+            if !escaped && data[i..len].starts_with(terminator) {
+                return Some((i, terminator.len()));
+            }
+            escaped = !escaped && Some(data[i]) == line_buffer.config.escape;
+        }
+        None
+    }
+
+    /// Reference implementation: count complete lines by splitting the whole buffer from scratch.
+    fn naive_line_count<S: Storage, I: RingIndex>(line_buffer: &RawLineBuffer<S, I>) -> usize {
+        let mut data = [0u8; NAIVE_CAPACITY];
+        let len = contents(line_buffer, &mut data);
+        let terminator = line_buffer.config.terminator.bytes();
+        if terminator.is_empty() {
+            return if len == 0 { 0 } else { 1 };
+        }
+        let mut lines = 0;
+        let mut escaped = false;
+        let mut i = 0;
+        while i < len {
+            if !escaped && data[i..len].starts_with(terminator) {
+                lines += 1;
+                i += terminator.len();
+                continue;
+            }
+            escaped = !escaped && Some(data[i]) == line_buffer.config.escape;
+            i += 1;
+        }
+        lines
+    }
+
+    /// Checks every storage has to pass, run against each one by `storage_tests!`. Each check
+    /// gets an empty buffer with a capacity of 16 and the default configuration.
+    mod shared {
+        use super::*;
+
+        pub fn push_and_read<S: Storage>(line_buffer: &mut RawLineBuffer<S>) {
+            let mut aux_buffer = [0u8; 16];
+            line_buffer.push_bytes(b"Hello\nWorld\n").unwrap();
+            assert_eq!(line_buffer.line_count(), 2);
+            assert_eq!(line_buffer.read_line_into(&mut aux_buffer), Ok(5));
+            assert_eq!(&aux_buffer[..5], b"Hello");
+            assert_eq!(line_buffer.read_line_into(&mut aux_buffer), Ok(5));
+            assert_eq!(&aux_buffer[..5], b"World");
+            assert_eq!(
+                line_buffer.read_line_into(&mut aux_buffer),
+                Err(LineBufferRxError::BufferEmpty)
+            );
+        }
+
+        pub fn partial_line<S: Storage>(line_buffer: &mut RawLineBuffer<S>) {
+            let mut aux_buffer = [0u8; 16];
+            line_buffer.push_bytes(b"partial").unwrap();
+            assert!(!line_buffer.has_complete_line());
+            assert_eq!(
+                line_buffer.read_line_into(&mut aux_buffer),
+                Err(LineBufferRxError::NoLines { pending: 7 })
+            );
+            line_buffer.push_byte(b'\n').unwrap();
+            assert_eq!(line_buffer.read_line_into(&mut aux_buffer), Ok(7));
+        }
+
+        pub fn fill_up<S: Storage>(line_buffer: &mut RawLineBuffer<S>) {
+            line_buffer.push_bytes(b"0123456789abcde\n").unwrap();
+            assert!(line_buffer.is_full());
+            assert_eq!(line_buffer.remaining_capacity(), 0);
+            assert_eq!(
+                line_buffer.push_byte(b'x'),
+                Err(LineBufferTxError::BufferFull {
+                    requested: 1,
+                    available: 0,
+                    written: 0,
+                })
+            );
+            line_buffer.clear();
+            assert!(line_buffer.is_empty());
+            assert_eq!(line_buffer.remaining_capacity(), 16);
+        }
+
+        pub fn wrap<S: Storage>(line_buffer: &mut RawLineBuffer<S>) {
+            let mut aux_buffer = [0u8; 16];
+            line_buffer.set_config(leak(LineBufferConfig {
+                terminator: Terminator::CarriageReturnNewline,
+                ..*line_buffer.config()
+            }));
+            line_buffer.push_bytes(b"0123456789\r\n").unwrap();
+            line_buffer.read_line_into(&mut aux_buffer).unwrap();
+
+            // The terminator is split across the end of the storage.
+            line_buffer.push_bytes(b"abc\r\nde").unwrap();
+            assert!(line_buffer.end.get() < line_buffer.start.get());
+            assert_eq!(line_buffer.line_count(), 1);
+            let mut out = [0u8; 16];
+            assert_eq!(line_buffer.peek_all(&mut out), 7);
+            assert_eq!(&out[..7], b"abc\r\nde");
+            assert_eq!(line_buffer.read_line_into(&mut aux_buffer), Ok(3));
+            assert_eq!(&aux_buffer[..3], b"abc");
+            assert_eq!(line_buffer.len(), 2);
+        }
+
+        pub fn escape<S: Storage>(line_buffer: &mut RawLineBuffer<S>) {
+            let mut aux_buffer = [0u8; 16];
+            line_buffer.set_config(leak(LineBufferConfig {
+                escape: Some(b'\\'),
+                ..*line_buffer.config()
+            }));
+            line_buffer.push_bytes(b"a\\\nb\n").unwrap();
+            assert_eq!(line_buffer.line_count(), 1);
+            assert_eq!(line_buffer.read_line_into(&mut aux_buffer), Ok(3));
+            assert_eq!(&aux_buffer[..3], b"a\nb");
+        }
+
+        pub fn unread<S: Storage>(line_buffer: &mut RawLineBuffer<S>) {
+            let mut aux_buffer = [0u8; 16];
+            line_buffer.push_bytes(b"world\n").unwrap();
+            line_buffer.unread(b"hello ").unwrap();
+            assert_eq!(line_buffer.read_line_into(&mut aux_buffer), Ok(11));
+            assert_eq!(&aux_buffer[..11], b"hello world");
+        }
+
+        pub fn drop_oldest_line<S: Storage>(line_buffer: &mut RawLineBuffer<S>) {
+            let mut aux_buffer = [0u8; 16];
+            line_buffer.set_config(leak(LineBufferConfig {
+                overflow: OverflowPolicy::DropOldestLine,
+                ..*line_buffer.config()
+            }));
+            line_buffer.push_bytes(b"first\nsecond\n").unwrap();
+            line_buffer.push_bytes(b"third\n").unwrap();
+            assert_eq!(line_buffer.read_line_into(&mut aux_buffer), Ok(6));
+            assert_eq!(&aux_buffer[..6], b"second");
+            assert_eq!(line_buffer.read_line_into(&mut aux_buffer), Ok(5));
+            assert_eq!(&aux_buffer[..5], b"third");
+        }
+
+        /// Random pushes, unreads and reads give the same results as a `LineBuffer`.
+        pub fn matches_array<S: Storage>(line_buffer: &mut RawLineBuffer<S>) {
+            let mut rng = Rng(0x0017_1717);
+            let mut reference = LineBuffer::<16>::new();
+            let mut bytes = [0u8; 8];
+            let (mut expected, mut actual) = ([0u8; 16], [0u8; 16]);
+
+            for _ in 0..2000 {
+                let len = rng.below(bytes.len());
+                for byte in &mut bytes[..len] {
+                    *byte = rng.byte();
+                }
+                let bytes = &bytes[..len];
+                let byte = rng.byte();
+                match rng.below(5) {
+                    0 => assert_eq!(line_buffer.push_byte(byte), reference.push_byte(byte)),
+                    1 => assert_eq!(line_buffer.push_bytes(bytes), reference.push_bytes(bytes)),
+                    2 => assert_eq!(
+                        line_buffer.push_from_iter(bytes.iter().copied()),
+                        reference.push_from_iter(bytes.iter().copied())
+                    ),
+                    3 => assert_eq!(line_buffer.unread(bytes), reference.unread(bytes)),
+                    _ => assert_eq!(
+                        line_buffer.read_line_into(&mut actual),
+                        reference.read_line_into(&mut expected)
+                    ),
+                }
+                assert_eq!(line_buffer.line_count(), reference.line_count());
+                let len = reference.peek_all(&mut expected);
+                assert_eq!(line_buffer.peek_all(&mut actual), len);
+                assert_eq!(actual[..len], expected[..len]);
+            }
+        }
+    }
+
+    /// Run the `shared` checks against each storage, given how to make a buffer with it.
+    macro_rules! storage_tests {
+        ($($name:ident => $make:expr;)*) => {$(
+            mod $name {
+                use super::*;
+
+                storage_tests!(@checks $make;
+                    push_and_read, partial_line, fill_up, wrap, escape, unread, drop_oldest_line,
+                    matches_array);
+            }
+        )*};
+        (@checks $make:expr; $($check:ident),*) => {$(
+            #[test]
+            fn $check() {
+                shared::$check(&mut $make);
+            }
+        )*};
+    }
+
+    storage_tests! {
+        array_storage => LineBuffer::<16>::new();
+        uninit_storage => LineBufferUninit::<16>::new();
+        slice_storage => LineBufferSlice::new(&mut [0u8; 16]);
+    }
+
+    #[cfg(feature = "alloc")]
+    storage_tests! {
+        vec_storage => LineBufferVec::with_max_capacity(16, 16);
+    }
+
+    /// Simple test to check if the library is working.
+    #[test]
+    fn test_new() {
+        let line_buffer = LineBuffer::<10>::new();
+
+        assert_eq!(line_buffer.buffer, [0; 10]);
+        assert_eq!(line_buffer.start, 0);
+        assert_eq!(line_buffer.end, 0);
+        assert_eq!(line_buffer.config.terminator, Terminator::Newline);
+    }
+
+    /// Test pushing a byte at a time, up until and including when the buffer is full.
+    #[test]
+    fn test_push_byte() {
+        let mut line_buffer = LineBuffer::<10>::new();
+
+        line_buffer.push_byte(65).unwrap();
+
+        assert_eq!(line_buffer.buffer, [65, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(line_buffer.start, 0);
+        assert_eq!(line_buffer.end, 1);
+        assert_eq!(line_buffer.empty, false);
+
+        line_buffer.push_byte(66).unwrap();
+
+        assert_eq!(line_buffer.buffer, [65, 66, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(line_buffer.start, 0);
+        assert_eq!(line_buffer.end, 2);
+        assert_eq!(line_buffer.empty, false);
+
+        line_buffer.push_byte(67).unwrap();
+        line_buffer.push_byte(68).unwrap();
+        line_buffer.push_byte(69).unwrap();
+        line_buffer.push_byte(70).unwrap();
+        line_buffer.push_byte(71).unwrap();
+        line_buffer.push_byte(72).unwrap();
+        line_buffer.push_byte(73).unwrap();
+        line_buffer.push_byte(74).unwrap();
+
+        assert_eq!(line_buffer.buffer, [65, 66, 67, 68, 69, 70, 71, 72, 73, 74]);
+        assert_eq!(line_buffer.start, 0);
+        assert_eq!(line_buffer.end, 0);
+        assert_eq!(line_buffer.empty, false);
+
+        line_buffer
+            .push_byte(75)
+            .expect_err("Expected buffer full error");
+    }
+
+    /// Test pushing multiple bytes at a time, up until and including when the buffer is full.
+    #[test]
+    fn test_push_bytes() {
+        // Test adding a full capacity of bytes:
+        let mut line_buffer = LineBuffer::<10>::new();
+
+        line_buffer
+            .push_bytes(&[65, 66, 67, 68, 69, 70, 71, 72, 73, 74])
+            .unwrap();
+
+        assert_eq!(line_buffer.buffer, [65, 66, 67, 68, 69, 70, 71, 72, 73, 74]);
+        assert_eq!(line_buffer.start, 0);
+        assert_eq!(line_buffer.end, 0);
+        assert_eq!(line_buffer.empty, false);
+        assert_eq!(line_buffer.is_full(), true);
+
+        line_buffer
+            .push_bytes(&[75])
+            .expect_err("Expected buffer full error");
+
+        // Test adding more than the capacity of bytes:
+        let mut line_buffer = LineBuffer::<10>::new();
+
+        line_buffer
+            .push_bytes(&[65, 66, 67, 68, 69, 70, 71, 72, 73, 74, 75])
+            .expect_err("Expected buffer full error");
+    }
+
+    #[test]
+    fn test_is_empty() {
+        let mut line_buffer = LineBuffer::<10>::new();
+
+        assert_eq!(line_buffer.is_empty(), true);
+
+        line_buffer.push_byte(65).unwrap();
+
+        assert_eq!(line_buffer.is_empty(), false);
+    }
+
+    #[test]
+    fn test_is_full() {
+        let mut line_buffer = LineBuffer::<10>::new();
+
+        assert_eq!(line_buffer.is_full(), false);
+
+        for i in 0..10 {
+            line_buffer.push_byte(i as u8).expect("Failed to push byte");
+        }
+
+        assert_eq!(line_buffer.is_full(), true);
+
+        line_buffer
+            .push_byte(10)
+            .expect_err("Expected buffer full error");
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut line_buffer = LineBuffer::<10>::new();
+
+        line_buffer.push_byte(65).unwrap();
+
+        assert_eq!(line_buffer.buffer, [65, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(line_buffer.start, 0);
+        assert_eq!(line_buffer.end, 1);
+        assert_eq!(line_buffer.empty, false);
+
+        line_buffer.clear();
+
+        // We expect the contents of the buffer to remain the same, but the pointers to be reset.
+        assert_eq!(line_buffer.buffer, [65, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(line_buffer.start, 0);
+        assert_eq!(line_buffer.end, 0);
+        assert_eq!(line_buffer.empty, true);
+        assert_eq!(line_buffer.is_empty(), true);
+    }
+
+    #[test]
+    fn test_read_line() {
+        let mut line_buffer = LineBuffer::<10>::new();
+
+        line_buffer.push_bytes(b"Hello\n").unwrap();
+
+        let mut aux_buffer = [0u8; 10];
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+
+        assert_eq!(bytes_read, 5);
+        assert_eq!(aux_buffer, [b'H', b'e', b'l', b'l', b'o', 0, 0, 0, 0, 0]);
+        assert_eq!(line_buffer.is_empty(), true);
+    }
+
+    #[test]
+    fn test_read_line_when_empty() {
+        let mut line_buffer = LineBuffer::<10>::new();
+
+        let mut aux_buffer = [0u8; 10];
+        line_buffer
+            .read_line_bytes(&mut aux_buffer)
+            .expect_err("Expected buffer empty error");
+    }
+
+    #[test]
+    fn test_read_line_with_null_terminator() {
+        let mut line_buffer = LineBuffer::<11>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::NULL,
+            ..LineBufferConfig::DEFAULT
+        });
+
+        line_buffer.push_bytes(b"Hello\0World").unwrap();
+
+        let mut aux_buffer = [0u8; 11];
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+
+        assert_eq!(bytes_read, 5);
+        assert_eq!(aux_buffer, [b'H', b'e', b'l', b'l', b'o', 0, 0, 0, 0, 0, 0]);
+        assert_eq!(line_buffer.is_empty(), false);
+    }
+
+    #[test]
+    fn test_read_line_when_full() {
+        let mut line_buffer = LineBuffer::<6>::new();
+
+        line_buffer.push_bytes(b"Hello\n").unwrap();
+
+        let mut aux_buffer = [0u8; 6];
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+
+        assert_eq!(bytes_read, 5);
+        assert_eq!(aux_buffer, [b'H', b'e', b'l', b'l', b'o', 0]);
+        assert_eq!(line_buffer.is_empty(), true);
+
+        line_buffer.push_bytes(b"World\n").unwrap();
+
+        let mut aux_buffer = [0u8; 6];
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+
+        assert_eq!(bytes_read, 5);
+        assert_eq!(aux_buffer, [b'W', b'o', b'r', b'l', b'd', 0]);
+        assert_eq!(line_buffer.is_empty(), true);
+    }
+
+    #[test]
+    fn test_read_multiple_lines_after_bulk_insert() {
+        let mut line_buffer = LineBuffer::<1024>::new();
+
+        for _i in 0..10 {
+            line_buffer.push_bytes(b"Hello World\n").unwrap();
+        }
+        line_buffer.push_bytes(b"Hello World").unwrap();
+
+        for _i in 0..10 {
+            let mut aux_buffer = [0u8; 1024];
+            let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+            assert_eq!(bytes_read, 11);
+            assert_eq!(
+                aux_buffer[..11],
+                [b'H', b'e', b'l', b'l', b'o', b' ', b'W', b'o', b'r', b'l', b'd']
+            );
+        }
+
+        assert_eq!(line_buffer.is_empty(), false);
+    }
+
+    #[test]
+    fn test_read_multiple_lines_while_inserting() {
+        let mut line_buffer = LineBuffer::<15>::new();
+
+        for _i in 0..100 {
+            line_buffer.push_bytes(b"Hello World\n").unwrap();
+            let mut aux_buffer = [0u8; 15];
+            let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+            assert_eq!(bytes_read, 11);
+            assert_eq!(
+                aux_buffer[..11],
+                [b'H', b'e', b'l', b'l', b'o', b' ', b'W', b'o', b'r', b'l', b'd']
+            );
+        }
+
+        assert_eq!(line_buffer.is_empty(), true);
+    }
+
+    #[test]
+    fn test_read_no_lines() {
+        let mut line_buffer = LineBuffer::<10>::new();
+
+        line_buffer.push_bytes(b"Hello").unwrap();
+
+        let mut aux_buffer = [0u8; 10];
+        line_buffer
+            .read_line_bytes(&mut aux_buffer)
+            .expect_err("Expected no lines error");
+
+        line_buffer.push_byte(b'\n').unwrap();
+
+        let mut aux_buffer = [0u8; 10];
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+
+        assert_eq!(bytes_read, 5);
+        assert_eq!(aux_buffer, [b'H', b'e', b'l', b'l', b'o', 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_read_line_as_str() {
+        let mut line_buffer = LineBuffer::<18>::new();
+
+        line_buffer.push_bytes(b"Hello\nHello\nHello\n").unwrap();
+        assert_eq!(line_buffer.is_empty(), false);
+        assert_eq!(line_buffer.is_full(), true);
+
+        for _ in 0..3 {
+            let mut aux_buffer = [0u8; 18];
+            let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+            let line = core::str::from_utf8(&aux_buffer[..bytes_read]).unwrap();
+            assert_eq!(bytes_read, 5);
+            assert_eq!(line, "Hello");
+        }
+
+        assert_eq!(line_buffer.is_empty(), true);
+    }
+
+    #[test]
+    fn test_read_line_with_carriage_return_newline() {
+        let mut line_buffer = LineBuffer::<21>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturnNewline,
+            ..LineBufferConfig::DEFAULT
+        });
+
+        line_buffer
+            .push_bytes(b"Hello\r\nHello\r\nHello\r\n")
+            .unwrap();
+        assert_eq!(line_buffer.is_empty(), false);
+        assert_eq!(line_buffer.is_full(), true);
+
+        for _ in 0..3 {
+            let mut aux_buffer = [0u8; 21];
+            let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+            let line = core::str::from_utf8(&aux_buffer[..bytes_read]).unwrap();
+            assert_eq!(bytes_read, 5);
+            assert_eq!(line, "Hello");
+        }
+
+        assert_eq!(line_buffer.is_empty(), true);
+    }
+
+    #[test]
+    fn test_read_line_with_newline_carriage_return() {
+        let mut line_buffer = LineBuffer::<21>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::NewlineCarriageReturn,
+            ..LineBufferConfig::DEFAULT
+        });
+
+        line_buffer
+            .push_bytes(b"Hello\n\rHello\n\rHello\n\r")
+            .unwrap();
+        assert_eq!(line_buffer.is_empty(), false);
+        assert_eq!(line_buffer.is_full(), true);
+
+        for _ in 0..3 {
+            let mut aux_buffer = [0u8; 21];
+            let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+            let line = core::str::from_utf8(&aux_buffer[..bytes_read]).unwrap();
+            assert_eq!(bytes_read, 5);
+            assert_eq!(line, "Hello");
+        }
+
+        assert_eq!(line_buffer.is_empty(), true);
+    }
+
+    #[test]
+    fn test_has_complete_line() {
+        let mut line_buffer = LineBuffer::<10>::new();
+
+        assert!(!line_buffer.has_complete_line());
+
+        line_buffer.push_bytes(b"Hello").unwrap();
+        assert!(!line_buffer.has_complete_line());
+
+        line_buffer.push_byte(b'\n').unwrap();
+        assert!(line_buffer.has_complete_line());
+
+        let mut aux_buffer = [0u8; 10];
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert!(!line_buffer.has_complete_line());
+    }
+
+    /// A two-byte terminator split across pushes and across the wrap boundary is only found once complete.
+    #[test]
+    fn test_has_complete_line_split_terminator_across_wrap() {
+        let mut line_buffer = LineBuffer::<8>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturnNewline,
+            ..LineBufferConfig::DEFAULT
+        });
+        let mut aux_buffer = [0u8; 8];
+
+        line_buffer.push_bytes(b"abcd\r\n").unwrap();
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer).unwrap(), 4);
+
+        // The next line starts at index 6 so the `\r` lands at index 7 and the `\n` at index 0.
+        line_buffer.push_bytes(b"x\r").unwrap();
+        assert!(!line_buffer.has_complete_line());
+        assert!(!line_buffer.has_complete_line());
+
+        line_buffer.push_byte(b'\n').unwrap();
+        assert!(line_buffer.has_complete_line());
+
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..bytes_read], b"x");
+        assert!(line_buffer.is_empty());
+    }
+
+    /// Changing the terminator in the config invalidates the cached scan.
+    #[test]
+    fn test_has_complete_line_after_config_change() {
+        let mut line_buffer = LineBuffer::<10>::new();
+
+        line_buffer.push_bytes(b"Hello\r").unwrap();
+        assert!(!line_buffer.has_complete_line());
+
+        line_buffer.set_config(leak(LineBufferConfig {
+            terminator: Terminator::CarriageReturn,
+            ..*line_buffer.config()
+        }));
+        assert!(line_buffer.has_complete_line());
+    }
+
+    /// Cross-check line detection against a from-scratch scan over randomized operation sequences.
+    #[test]
+    fn test_find_line_matches_naive_scan() {
+        let terminators = [
+            Terminator::Newline,
+            Terminator::CarriageReturn,
+            Terminator::NULL,
+            Terminator::CarriageReturnNewline,
+            Terminator::NewlineCarriageReturn,
+            Terminator::Sequence(b"aab"),
+            Terminator::Sequence(b"abab"),
+            Terminator::Sequence(b"aa"),
+            Terminator::None,
+        ];
+        let mut rng = Rng(0x1234_5678);
+
+        for terminator in terminators {
+            let mut line_buffer = LineBuffer::<7>::new_with_config(leak(LineBufferConfig {
+                terminator,
+                ..LineBufferConfig::DEFAULT
+            }));
+
+            for _ in 0..5000 {
+                match rng.below(10) {
+                    0..=4 => {
+                        let _ = line_buffer.push_byte(rng.byte());
+                    }
+                    5 | 6 => {
+                        let chunk = [rng.byte(), rng.byte(), rng.byte()];
+                        let _ = line_buffer.push_bytes(&chunk[..rng.below(4)]);
+                    }
+                    7 | 8 => {
+                        let expected = naive_find_line(&line_buffer);
+                        let mut expected_line = [0u8; 7];
+                        contents(&line_buffer, &mut expected_line);
+                        let mut aux_buffer = [0u8; 7];
+                        match line_buffer.read_line_bytes(&mut aux_buffer) {
+                            Ok(bytes_read) => {
+                                assert_eq!(Some(bytes_read), expected.map(|(len, _)| len));
+                                assert_eq!(aux_buffer[..bytes_read], expected_line[..bytes_read]);
+                            }
+                            Err(_) => assert_eq!(expected, None),
+                        }
+                    }
+                    _ => line_buffer.clear(),
+                }
+
+                assert_eq!(
+                    line_buffer.has_complete_line(),
+                    naive_find_line(&line_buffer).is_some()
+                );
+                assert_eq!(line_buffer.find_line(), naive_find_line(&line_buffer));
+                assert_eq!(line_buffer.line_count(), naive_line_count(&line_buffer));
+            }
+        }
+    }
+
+    #[test]
+    fn test_push_byte_overwrite() {
+        let mut line_buffer = LineBuffer::<4>::new();
+
+        assert_eq!(line_buffer.push_byte_overwrite(b'a'), None);
+        assert_eq!(line_buffer.push_byte_overwrite(b'b'), None);
+        assert_eq!(line_buffer.push_byte_overwrite(b'c'), None);
+        assert_eq!(line_buffer.push_byte_overwrite(b'\n'), None);
+        assert!(line_buffer.is_full());
+
+        assert_eq!(line_buffer.push_byte_overwrite(b'd'), Some(b'a'));
+        assert_eq!(line_buffer.push_byte_overwrite(b'e'), Some(b'b'));
+        assert_eq!(line_buffer.push_byte_overwrite(b'f'), Some(b'c'));
+        assert!(line_buffer.is_full());
+
+        // The terminator survived, so the remaining line is empty.
+        let mut aux_buffer = [0u8; 4];
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer).unwrap(), 0);
+
+        // Keep overwriting until the next line is complete.
+        line_buffer.push_bytes(b"g").unwrap();
+        assert_eq!(line_buffer.push_byte_overwrite(b'h'), Some(b'd'));
+        assert_eq!(line_buffer.push_byte_overwrite(b'\n'), Some(b'e'));
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..bytes_read], b"fgh");
+    }
+
+    #[test]
+    fn test_wrap_add_sub() {
+        let power_of_two = LineBuffer::<16>::new();
+        assert_eq!(power_of_two.wrap_add(15, 1), 0);
+        assert_eq!(power_of_two.wrap_add(14, 1), 15);
+        assert_eq!(power_of_two.wrap_add(15, 16), 15);
+        assert_eq!(power_of_two.wrap_sub(0, 1), 15);
+        assert_eq!(power_of_two.wrap_sub(1, 1), 0);
+        assert_eq!(power_of_two.wrap_sub(0, 16), 0);
+
+        let odd = LineBuffer::<10>::new();
+        assert_eq!(odd.wrap_add(9, 1), 0);
+        assert_eq!(odd.wrap_add(8, 1), 9);
+        assert_eq!(odd.wrap_add(8, 3), 1);
+        assert_eq!(odd.wrap_add(9, 10), 9);
+        assert_eq!(odd.wrap_sub(0, 1), 9);
+        assert_eq!(odd.wrap_sub(1, 2), 9);
+        assert_eq!(odd.wrap_sub(2, 2), 0);
+        assert_eq!(odd.wrap_sub(9, 10), 9);
+
+        for i in 0..10 {
+            for n in 0..=10 {
+                assert_eq!(odd.wrap_sub(odd.wrap_add(i, n), n), i);
+                assert_eq!(odd.wrap_add(odd.wrap_sub(i, n), n), i);
+            }
+        }
+    }
+
+    #[test]
+    fn test_ends_with_terminator() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        assert!(!line_buffer.ends_with_terminator());
+        line_buffer.push_bytes(b"abcde\n").unwrap();
+        assert!(line_buffer.ends_with_terminator());
+        line_buffer.read_line_bytes(&mut [0u8; 8]).unwrap();
+        line_buffer.push_bytes(b"fg").unwrap();
+        assert!(!line_buffer.ends_with_terminator());
+        // The terminator lands at the start of the storage.
+        line_buffer.push_bytes(b"\n").unwrap();
+        assert!(line_buffer.ends_with_terminator());
+
+        line_buffer.set_config(leak(LineBufferConfig {
+            escape: Some(b'\\'),
+            ..*line_buffer.config()
+        }));
+        line_buffer.push_bytes(b"h\\\n").unwrap();
+        assert!(!line_buffer.ends_with_terminator());
+        line_buffer.clear();
+        line_buffer.push_bytes(b"i\\\\\n").unwrap();
+        assert!(line_buffer.ends_with_terminator());
+
+        line_buffer.set_config(leak(LineBufferConfig {
+            terminator: Terminator::None,
+            ..*line_buffer.config()
+        }));
+        assert!(!line_buffer.ends_with_terminator());
+    }
+
+    #[test]
+    fn test_ends_with_terminator_crlf() {
+        let mut line_buffer = LineBuffer::<8>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturnNewline,
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"abcdef\r").unwrap();
+        assert!(!line_buffer.ends_with_terminator());
+        line_buffer.push_bytes(b"\n").unwrap();
+        assert!(line_buffer.ends_with_terminator());
+        line_buffer.read_line_bytes(&mut [0u8; 8]).unwrap();
+
+        // The terminator is split across the wrap.
+        line_buffer.push_bytes(b"gh\n\r").unwrap();
+        assert!(!line_buffer.ends_with_terminator());
+        line_buffer.push_bytes(b"\r\n").unwrap();
+        assert!(line_buffer.ends_with_terminator());
+    }
+
+    #[test]
+    fn test_checksums() {
+        let mut line_buffer = LineBuffer::<16, u8>::new();
+        assert_eq!(line_buffer.checksum_xor(), 0);
+        assert_eq!(line_buffer.checksum_crc16(), 0xFFFF);
+
+        // The check value, across the end of the storage.
+        line_buffer.push_bytes(b"0123456789\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 16]).unwrap();
+        line_buffer.push_bytes(b"123456789").unwrap();
+        assert!(!line_buffer.as_slices().1.is_empty());
+        assert_eq!(line_buffer.checksum_xor(), 0x31);
+        assert_eq!(line_buffer.checksum_crc16(), 0x29B1);
+
+        let mut line_buffer = LineBuffer::<16>::new();
+        line_buffer.push_bytes(b"$GPGLL").unwrap();
+        assert_eq!(line_buffer.checksum_crc16(), 0x67EA);
+    }
+
+    #[test]
+    fn test_resize() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        static LINES: AtomicUsize = AtomicUsize::new(0);
+
+        fn on_line(_first: &[u8], _second: &[u8]) {
+            LINES.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let mut line_buffer = LineBuffer::<8, u8>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturn,
+            line_handler: Some(LineHandler {
+                on_line,
+                consume: false,
+            }),
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"abcde\r").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 8]).unwrap();
+        line_buffer.push_bytes(b"fg\rhi").unwrap();
+        assert_eq!(LINES.load(Ordering::Relaxed), 2);
+
+        // Growing, from wrapped contents.
+        let Ok(mut larger) = line_buffer.resize::<32>() else {
+            panic!("growing can't fail");
+        };
+        assert_eq!(LINES.load(Ordering::Relaxed), 2);
+        assert_eq!(larger.len(), 5);
+        assert_eq!(larger.line_count(), 1);
+        larger.push_bytes(b"jklmnopq\r").unwrap();
+        assert_eq!(LINES.load(Ordering::Relaxed), 3);
+        let mut aux_buffer = [0u8; 32];
+        let len = larger.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"fg");
+
+        // Shrinking, while the contents still fit.
+        let Ok(mut smaller) = larger.resize::<12>() else {
+            panic!("10 bytes fit in 12");
+        };
+        let mut aux_buffer = [0u8; 12];
+        let len = smaller.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"hijklmnopq");
+        assert!(smaller.is_empty());
+
+        // Shrinking too far hands the buffer back with everything still in it.
+        smaller.push_bytes(b"01234\r56789").unwrap();
+        let Err((mut smaller, error)) = smaller.resize::<4>() else {
+            panic!("11 bytes don't fit in 4");
+        };
+        assert_eq!(
+            error,
+            LineBufferTxError::BufferFull {
+                requested: 11,
+                available: 4,
+                written: 0,
+            }
+        );
+        assert_eq!(smaller.len(), 11);
+        assert_eq!(smaller.line_count(), 1);
+        let len = smaller.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"01234");
+        assert_eq!(smaller.as_slices(), (&b"56789"[..], &b""[..]));
+    }
+
+    #[test]
+    fn test_clone_contents_into() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        line_buffer.push_bytes(b"abcde\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 8]).unwrap();
+        line_buffer.push_bytes(b"fg\nhi").unwrap();
+
+        // Larger, with something already in it.
+        let mut larger = LineBuffer::<32, u8>::new();
+        larger.push_bytes(b"old\nline").unwrap();
+        line_buffer.clone_contents_into(&mut larger).unwrap();
+        assert_eq!(larger.len(), 5);
+        assert_eq!(larger.line_count(), 1);
+        let mut aux_buffer = [0u8; 32];
+        assert_eq!(larger.peek_all(&mut aux_buffer), 5);
+        assert_eq!(&aux_buffer[..5], b"fg\nhi");
+        assert_eq!(line_buffer.len(), 5);
+
+        // Too small, and left alone.
+        let mut smaller = LineBuffer::<4>::new();
+        smaller.push_bytes(b"xy").unwrap();
+        assert_eq!(
+            line_buffer.clone_contents_into(&mut smaller),
+            Err(LineBufferTxError::BufferFull {
+                requested: 5,
+                available: 4,
+                written: 0,
+            })
+        );
+        assert_eq!(smaller.peek_all(&mut aux_buffer), 2);
+        assert_eq!(&aux_buffer[..2], b"xy");
+    }
+
+    #[test]
+    fn test_force_line() {
+        let mut line_buffer = LineBuffer::<8>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturnNewline,
+            escape: Some(b'\\'),
+            ..LineBufferConfig::DEFAULT
+        });
+        assert_eq!(line_buffer.force_line(), Ok(false));
+        line_buffer.push_bytes(b"ab\\").unwrap();
+        assert_eq!(line_buffer.force_line(), Ok(false));
+        line_buffer.push_bytes(b"\\").unwrap();
+        assert_eq!(line_buffer.force_line(), Ok(true));
+        assert_eq!(line_buffer.len(), 6);
+        let mut aux_buffer = [0u8; 8];
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(3));
+        assert_eq!(&aux_buffer[..3], b"ab\\");
+
+        // No room for the terminator.
+        line_buffer.push_bytes(b"cdefgh\r").unwrap();
+        assert_eq!(
+            line_buffer.force_line(),
+            Err(LineBufferTxError::BufferFull {
+                requested: 2,
+                available: 1,
+                written: 0,
+            })
+        );
+        assert_eq!(line_buffer.len(), 7);
+    }
+
+    #[test]
+    fn test_idle_flush() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        line_buffer.push_bytes(b"ignored").unwrap();
+        assert!(!line_buffer.tick(100));
+        line_buffer.clear();
+
+        line_buffer.set_idle_flush(5);
+        // Start near the end of the counter's range, so it wraps part way through.
+        let mut now = u32::MAX - 6;
+        let mut flushed = 0;
+        for burst in [&b"first"[..], b"second"] {
+            for byte in burst {
+                line_buffer.push_byte(*byte).unwrap();
+                assert!(!line_buffer.tick(now));
+                now = now.wrapping_add(1);
+            }
+            for _ in 0..8 {
+                if line_buffer.tick(now) {
+                    flushed += 1;
+                }
+                now = now.wrapping_add(1);
+            }
+        }
+        assert_eq!(flushed, 2);
+
+        let mut aux_buffer = [0u8; 16];
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(5));
+        assert_eq!(&aux_buffer[..5], b"first");
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(6));
+        assert_eq!(&aux_buffer[..6], b"second");
+
+        // Off again.
+        line_buffer.set_idle_flush(0);
+        line_buffer.push_bytes(b"third").unwrap();
+        assert!(!line_buffer.tick(now));
+        assert!(!line_buffer.tick(now.wrapping_add(100)));
+        assert!(!line_buffer.has_complete_line());
+    }
+
+    #[test]
+    fn test_scan() {
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturnNewline,
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"0123456789\r\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 16]).unwrap();
+
+        // Each call picks up where the last one stopped, across the wrap and a split terminator.
+        for (i, byte) in b"abcde\r".iter().enumerate() {
+            line_buffer.push_byte(*byte).unwrap();
+            assert_eq!(line_buffer.scan(), None);
+            assert_eq!(line_buffer.scan_pos.get(), i + 1);
+        }
+        assert_eq!(line_buffer.scan_pos_state.matched.get(), 1);
+        line_buffer.push_bytes(b"\nfg").unwrap();
+        assert_eq!(line_buffer.scan(), Some(5));
+        assert_eq!(line_buffer.scan_pos.get(), 7);
+        // Once found, later bytes aren't looked at.
+        line_buffer.push_bytes(b"\r\n").unwrap();
+        assert_eq!(line_buffer.scan(), Some(5));
+        assert_eq!(line_buffer.scan_pos.get(), 7);
+
+        // Reading the line starts the search over from the next one.
+        line_buffer.read_line_bytes(&mut [0u8; 16]).unwrap();
+        assert_eq!(line_buffer.scan_pos.get(), 0);
+        assert_eq!(line_buffer.scan(), Some(2));
+
+        // So does a change of terminator.
+        line_buffer.set_config(leak(LineBufferConfig {
+            terminator: Terminator::Newline,
+            ..*line_buffer.config()
+        }));
+        assert_eq!(line_buffer.scan(), Some(3));
+        line_buffer.clear();
+        assert_eq!(line_buffer.scan(), None);
+    }
+
+    #[test]
+    fn test_scan_matches_find_line() {
+        let mut rng = Rng(0x5ca1_ab1e);
+        let mut line_buffer = LineBuffer::<9>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturnNewline,
+            escape: Some(b'\\'),
+            ..LineBufferConfig::DEFAULT
+        });
+        for _ in 0..20000 {
+            match rng.below(5) {
+                0..=2 => {
+                    let _ = line_buffer.push_byte(rng.byte());
+                }
+                3 => {
+                    let _ = line_buffer.read_line_bytes(&mut [0u8; 9]);
+                }
+                _ => {
+                    line_buffer.push_byte_overwrite(rng.byte());
+                }
+            }
+            assert_eq!(
+                line_buffer.scan(),
+                line_buffer.find_line().map(|(line_len, _)| line_len)
+            );
+        }
+    }
+
+    #[test]
+    fn test_push_bytes_with_policy() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        line_buffer.push_bytes(b"ab\ncd").unwrap();
+
+        assert_eq!(
+            line_buffer.push_bytes_with_policy(b"efgh", OverflowPolicy::Reject),
+            3
+        );
+        assert!(line_buffer.is_full());
+        // Dropping the only complete line wouldn't make room, so nothing is written.
+        assert_eq!(
+            line_buffer.push_bytes_with_policy(b"\nijk", OverflowPolicy::DropOldestLine),
+            0
+        );
+        assert_eq!(
+            line_buffer.push_bytes_with_policy(b"\ni", OverflowPolicy::DropOldestLine),
+            2
+        );
+        assert_eq!(line_buffer.len(), 7);
+        assert_eq!(
+            line_buffer.push_bytes_with_policy(b"jklm", OverflowPolicy::OverwriteOldest),
+            4
+        );
+        assert_eq!(line_buffer.len(), 8);
+
+        // The configured policy is still in force afterwards.
+        assert_eq!(line_buffer.config.overflow, OverflowPolicy::Reject);
+        assert!(line_buffer.push_bytes(b"n").is_err());
+        let mut aux_buffer = [0u8; 8];
+        assert_eq!(line_buffer.peek_all(&mut aux_buffer), 8);
+        assert_eq!(&aux_buffer, b"fg\nijklm");
+
+        // All or nothing: one byte too many and none of them are written.
+        line_buffer.clear();
+        line_buffer.push_bytes(b"abc").unwrap();
+        assert_eq!(
+            line_buffer.push_bytes_with_policy(b"defghi", OverflowPolicy::RejectAll),
+            0
+        );
+        assert_eq!(line_buffer.len(), 3);
+        assert_eq!(
+            line_buffer.push_bytes_with_policy(b"defgh", OverflowPolicy::RejectAll),
+            5
+        );
+        assert!(line_buffer.is_full());
+    }
+
+    #[test]
+    fn test_save_restore_state() {
+        let config = || {
+            LineBufferConfig::builder()
+                .terminator(Terminator::CarriageReturnNewline)
+                .escape(Some(b'\\'))
+                .max_line_length(Some(6))
+                .line_too_long(LineTooLongPolicy::Truncate)
+                .require_utf8(true)
+                .flow_thresholds(Some(FlowThresholds { high: 12, low: 4 }))
+                .paragraph_line_end(ParagraphLineEnd::Newline)
+                .skip_empty_lines(true)
+                .build()
+                .unwrap()
+        };
+        let mut line_buffer = LineBuffer::<16>::new_with_config(leak(config()));
+        line_buffer.push_bytes(b"0123456789\r\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 16]).unwrap();
+        // Wrapped, with an escaped terminator, an overlong line and a partial one.
+        line_buffer.push_bytes(b"a\\\r\n\r\nlong!!!\r\nz").unwrap();
+        assert!(!line_buffer.as_slices().1.is_empty());
+
+        let mut saved = [0u8; 64];
+        let len = line_buffer.save_state(&mut saved).unwrap();
+        assert_eq!(len, STATE_HEADER_LEN + line_buffer.len());
+        // The same contents save the same wherever they sit in the storage.
+        let mut unwrapped = LineBuffer::<16>::new_with_config(leak(config()));
+        unwrapped.push_bytes(b"a\\\r\n\r\nlong!!!\r\nz").unwrap();
+        let mut saved_unwrapped = [0u8; 64];
+        assert_eq!(unwrapped.save_state(&mut saved_unwrapped), Ok(len));
+        assert_eq!(saved[..len], saved_unwrapped[..len]);
+
+        let saved_config = LineBufferConfig::from_state(&saved[..len]).unwrap();
+        assert_eq!(saved_config.escape, Some(b'\\'));
+        assert_eq!(saved_config.max_line_length, Some(6));
+        assert_eq!(saved_config.paragraph_line_end, ParagraphLineEnd::Newline);
+        assert!(saved_config.skip_empty_lines);
+        assert!(saved_config.line_handler.is_none());
+        let saved_config = leak(saved_config);
+        let mut restored = LineBuffer::<16>::restore_state(&saved[..len], saved_config).unwrap();
+        assert_eq!(restored.line_count(), line_buffer.line_count());
+        assert_eq!(restored.flow_state(), line_buffer.flow_state());
+        let mut larger = LineBuffer::<64, u8>::restore_state(&saved[..len], saved_config).unwrap();
+
+        let mut expected = [0u8; 16];
+        let mut aux_buffer = [0u8; 16];
+        let mut larger_buffer = [0u8; 64];
+        for _ in 0..2 {
+            let info = line_buffer.read_line_info(&mut expected).unwrap();
+            assert_eq!(restored.read_line_info(&mut aux_buffer), Ok(info));
+            assert_eq!(aux_buffer[..info.len], expected[..info.len]);
+            assert_eq!(larger.read_line_info(&mut larger_buffer), Ok(info));
+            assert_eq!(larger_buffer[..info.len], expected[..info.len]);
+        }
+        assert_eq!(
+            restored.read_line_bytes(&mut aux_buffer),
+            Err(LineBufferRxError::NoLines { pending: 1 })
+        );
+        assert!(line_buffer.read_line_bytes(&mut expected).is_err());
+    }
+
+    #[test]
+    fn test_save_restore_state_errors() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        line_buffer.push_bytes(b"0123456789\n").unwrap();
+        let mut saved = [0u8; 64];
+        let len = line_buffer.save_state(&mut saved).unwrap();
+
+        assert_eq!(
+            line_buffer.save_state(&mut saved[..len - 1]),
+            Err(SaveError::OutputTooSmall { needed: len })
+        );
+        assert_eq!(
+            LineBuffer::<8>::restore_state(&saved[..len], &LineBufferConfig::DEFAULT).err(),
+            Some(RestoreError::TooLarge {
+                len: 11,
+                capacity: 8
+            })
+        );
+        assert_eq!(
+            LineBuffer::<16>::restore_state(&saved[..len - 1], &LineBufferConfig::DEFAULT).err(),
+            Some(RestoreError::Corrupt)
+        );
+        assert_eq!(
+            LineBuffer::<16>::restore_state(&[], &LineBufferConfig::DEFAULT).err(),
+            Some(RestoreError::Corrupt)
+        );
+        let mut newer = saved;
+        newer[0] = STATE_VERSION + 1;
+        assert_eq!(
+            LineBuffer::<16>::restore_state(&newer[..len], &LineBufferConfig::DEFAULT).err(),
+            Some(RestoreError::VersionMismatch {
+                found: STATE_VERSION + 1
+            })
+        );
+        let mut bad_policy = saved;
+        bad_policy[7] = 4;
+        assert_eq!(
+            LineBuffer::<16>::restore_state(&bad_policy[..len], &LineBufferConfig::DEFAULT).err(),
+            Some(RestoreError::Corrupt)
+        );
+        assert_eq!(
+            LineBuffer::<16>::restore_state(
+                &saved[..len],
+                LineBuffer::<16>::new_overwriting().config()
+            )
+            .err(),
+            Some(RestoreError::ConfigMismatch)
+        );
+
+        let sequence = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::Sequence(b"END"),
+            ..LineBufferConfig::DEFAULT
+        });
+        assert_eq!(
+            sequence.save_state(&mut saved),
+            Err(SaveError::UnsupportedTerminator)
+        );
+        let comments = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            skip_comment_prefixes: Some(b"#"),
+            ..LineBufferConfig::DEFAULT
+        });
+        assert_eq!(
+            comments.save_state(&mut saved),
+            Err(SaveError::UnsupportedCommentPrefixes)
+        );
+    }
+
+    #[test]
+    fn test_discard_to() {
+        let mut line_buffer = LineBuffer::<16, u8>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturnNewline,
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"0123456789\r\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 16]).unwrap();
+        // Wrapped, with the garbage prefix ending part way through a terminator.
+        line_buffer.push_bytes(b"junk\r\nAT\r\nOK").unwrap();
+        line_buffer.discard_to(5);
+        assert_eq!(line_buffer.len(), 7);
+        // The `\n` left of the torn terminator no longer ends a line.
+        assert_eq!(line_buffer.line_count(), 1);
+
+        let mut aux_buffer = [0u8; 16];
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"\nAT");
+        line_buffer.push_bytes(b"\r\n").unwrap();
+
+        line_buffer.discard_to(0);
+        assert_eq!(line_buffer.len(), 4);
+        line_buffer.discard_to(100);
+        assert!(line_buffer.is_empty());
+        assert_eq!(line_buffer.line_count(), 0);
+        assert_eq!(
+            line_buffer.read_line_bytes(&mut aux_buffer),
+            Err(LineBufferRxError::BufferEmpty)
+        );
+
+        line_buffer.push_bytes(b"ERROR\r\n").unwrap();
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"ERROR");
+    }
+
+    #[test]
+    fn test_line_count() {
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturnNewline,
+            ..LineBufferConfig::DEFAULT
+        });
+
+        assert_eq!(line_buffer.line_count(), 0);
+
+        line_buffer.push_bytes(b"ab\r\ncd\r").unwrap();
+        assert_eq!(line_buffer.line_count(), 1);
+
+        // Only counted once the second byte of the terminator lands.
+        line_buffer.push_byte(b'\n').unwrap();
+        assert_eq!(line_buffer.line_count(), 2);
+
+        let mut aux_buffer = [0u8; 16];
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(line_buffer.line_count(), 1);
+
+        line_buffer.clear();
+        assert_eq!(line_buffer.line_count(), 0);
+    }
+
+    #[test]
+    fn test_max_possible_lines() {
+        let mut rng = Rng(0x0bad_cafe);
+        for terminator in [
+            Terminator::Newline,
+            Terminator::CarriageReturnNewline,
+            Terminator::Sequence(b"\r\n\0"),
+            Terminator::None,
+        ] {
+            let mut line_buffer = LineBuffer::<64>::new_with_config(leak(LineBufferConfig {
+                terminator,
+                escape: Some(b'\\'),
+                ..LineBufferConfig::DEFAULT
+            }));
+            for _ in 0..200 {
+                if line_buffer.push_byte(rng.byte()).is_err() {
+                    let _ = line_buffer.read_line_bytes(&mut [0u8; 64]);
+                    line_buffer.discard_to(rng.below(8));
+                }
+                assert!(line_buffer.max_possible_lines() >= line_buffer.line_count());
+            }
+        }
+
+        // Reached by a buffer of nothing but terminators.
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturnNewline,
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"\r\n\r\n\r\n\r").unwrap();
+        assert_eq!(line_buffer.max_possible_lines(), 3);
+        assert_eq!(line_buffer.line_count(), 3);
+    }
+
+    /// Cross-check the incremental line count against a from-scratch count after every operation,
+    /// including overwrites that tear terminators and changes of terminator.
+    #[test]
+    fn test_line_count_matches_naive_count() {
+        let terminators = [
+            Terminator::Newline,
+            Terminator::CarriageReturn,
+            Terminator::NULL,
+            Terminator::CarriageReturnNewline,
+            Terminator::NewlineCarriageReturn,
+            Terminator::Sequence(b"aab"),
+            Terminator::Sequence(b"abab"),
+            Terminator::Sequence(b"aa"),
+            Terminator::None,
+        ];
+        let mut rng = Rng(0x9e37_79b9);
+        let mut line_buffer = LineBuffer::<9>::new();
+
+        for _ in 0..20000 {
+            match rng.below(12) {
+                0..=3 => {
+                    let _ = line_buffer.push_byte(rng.byte());
+                }
+                4..=6 => {
+                    line_buffer.push_byte_overwrite(rng.byte());
+                }
+                7 | 8 => {
+                    let mut aux_buffer = [0u8; 9];
+                    let _ = line_buffer.read_line_bytes(&mut aux_buffer);
+                }
+                9 => {
+                    line_buffer.set_config(leak(LineBufferConfig {
+                        terminator: terminators[rng.below(terminators.len())],
+                        escape: [None, Some(b'\\')][rng.below(2)],
+                        ..*line_buffer.config()
+                    }));
+                }
+                10 => line_buffer.clear(),
+                _ => {
+                    let chunk = [rng.byte(), rng.byte(), rng.byte(), rng.byte()];
+                    let _ = line_buffer.push_bytes(&chunk[..rng.below(5)]);
+                }
+            }
+
+            assert_eq!(line_buffer.line_count(), naive_line_count(&line_buffer));
+            assert_eq!(
+                line_buffer.has_complete_line(),
+                naive_line_count(&line_buffer) > 0
+            );
+        }
+    }
+
+    #[test]
+    fn test_line_positions() {
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturnNewline,
+            ..LineBufferConfig::DEFAULT
+        });
+        assert_eq!(line_buffer.line_positions().next(), None);
+
+        line_buffer.push_bytes(b"xxxxxxxx\r\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 16]).unwrap();
+        // The second line and the terminator of the first straddle the end of the storage.
+        line_buffer.push_bytes(b"ab\r\ncde\r\n\r\nf").unwrap();
+        assert!(line_buffer.end < line_buffer.start);
+
+        let mut positions = line_buffer.line_positions();
+        assert_eq!(positions.next(), Some((0, 2)));
+        assert_eq!(positions.next(), Some((4, 3)));
+        assert_eq!(positions.next(), Some((9, 0)));
+        assert_eq!(positions.next(), None);
+        assert_eq!(positions.next(), None);
+        assert_eq!(line_buffer.line_count(), 3);
+
+        let mut out = [0u8; 16];
+        line_buffer.peek_all(&mut out);
+        assert_eq!(&out[4..7], b"cde");
+    }
+
+    #[test]
+    fn test_clear_complete_lines() {
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturnNewline,
+            ..LineBufferConfig::DEFAULT
+        });
+        assert_eq!(line_buffer.clear_complete_lines(), 0);
+
+        line_buffer.push_bytes(b"xxxxxxxx\r\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 16]).unwrap();
+        line_buffer.push_bytes(b"ab\r\ncd\r\nef\r").unwrap();
+
+        // The partial line keeps its half-received terminator.
+        assert_eq!(line_buffer.clear_complete_lines(), 2);
+        assert_eq!(line_buffer.len(), 3);
+        assert_eq!(line_buffer.clear_complete_lines(), 0);
+        line_buffer.push_byte(b'\n').unwrap();
+        let mut aux_buffer = [0u8; 16];
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"ef");
+
+        // Without a partial line, nothing is left.
+        line_buffer.push_bytes(b"gh\r\nij\r\n").unwrap();
+        assert_eq!(line_buffer.clear_complete_lines(), 2);
+        assert!(line_buffer.is_empty());
+        assert_eq!(line_buffer.line_count(), 0);
+    }
+
+    #[test]
+    fn test_peek_all() {
+        let mut line_buffer = LineBuffer::<10>::new();
+        line_buffer.push_bytes(b"Hello").unwrap();
+
+        let mut out = [0u8; 10];
+        assert_eq!(line_buffer.peek_all(&mut out), 5);
+        assert_eq!(&out[..5], b"Hello");
+
+        // Nothing was consumed.
+        assert_eq!(line_buffer.len(), 5);
+        assert_eq!(line_buffer.start, 0);
+
+        // A short output buffer only receives what fits.
+        let mut out = [0u8; 3];
+        assert_eq!(line_buffer.peek_all(&mut out), 3);
+        assert_eq!(&out, b"Hel");
+    }
+
+    #[test]
+    fn test_peek_all_wrapped() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        line_buffer.push_bytes(b"abcde\n").unwrap();
+        let mut aux_buffer = [0u8; 8];
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+
+        line_buffer.push_bytes(b"Hello").unwrap();
+        assert!(line_buffer.end < line_buffer.start);
+
+        let mut out = [0u8; 8];
+        assert_eq!(line_buffer.peek_all(&mut out), 5);
+        assert_eq!(&out[..5], b"Hello");
+
+        // Cut off within the first slice and within the second slice.
+        let mut out = [0u8; 1];
+        assert_eq!(line_buffer.peek_all(&mut out), 1);
+        assert_eq!(&out, b"H");
+        let mut out = [0u8; 4];
+        assert_eq!(line_buffer.peek_all(&mut out), 4);
+        assert_eq!(&out, b"Hell");
+        assert_eq!(line_buffer.len(), 5);
+    }
+
+    #[test]
+    fn test_peek_range() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        line_buffer.push_bytes(b"xxxxx\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 8]).unwrap();
+        line_buffer.push_bytes(b"abcdefg").unwrap();
+        assert!(line_buffer.end.get() < line_buffer.start.get());
+
+        // A middle range straddling the wrap.
+        let mut out = [0u8; 3];
+        assert_eq!(line_buffer.peek_range(1, &mut out), 3);
+        assert_eq!(&out, b"bcd");
+        let mut out = [0u8; 8];
+        assert_eq!(line_buffer.peek_range(4, &mut out), 3);
+        assert_eq!(&out[..3], b"efg");
+        assert_eq!(line_buffer.peek_range(0, &mut out), 7);
+        assert_eq!(&out[..7], b"abcdefg");
+
+        assert_eq!(line_buffer.peek_range(7, &mut out), 0);
+        assert_eq!(line_buffer.peek_range(100, &mut out), 0);
+        assert_eq!(line_buffer.peek_range(2, &mut []), 0);
+        assert_eq!(line_buffer.len(), 7);
+    }
+
+    /// Chunks cover the buffered bytes in order, each `chunk_size` long but the last, whatever
+    /// the layout.
+    #[test]
+    fn test_chunks() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        let mut expected = [0u8; 16];
+        let mut out = [0u8; 16];
+        for offset in 0..16 {
+            // Start the data at `offset` into the storage.
+            line_buffer.clear();
+            line_buffer.push_bytes(&[b'x'; 16][..offset]).unwrap();
+            line_buffer.consume(offset);
+            line_buffer.push_bytes(b"0123456789abc").unwrap();
+            let len = line_buffer.peek_all(&mut expected);
+
+            for chunk_size in [1, 3, 4, 5, 13, 20] {
+                let mut copied = 0;
+                let mut chunks = line_buffer.chunks(chunk_size).peekable();
+                while let Some((first, second)) = chunks.next() {
+                    let chunk_len = first.len() + second.len();
+                    assert!(!first.is_empty());
+                    match chunks.peek() {
+                        Some(_) => assert_eq!(chunk_len, chunk_size),
+                        None => assert_eq!(copied + chunk_len, len),
+                    }
+                    out[copied..copied + first.len()].copy_from_slice(first);
+                    copied += first.len();
+                    out[copied..copied + second.len()].copy_from_slice(second);
+                    copied += second.len();
+                }
+                assert_eq!(out[..copied], expected[..len]);
+            }
+        }
+        assert_eq!(LineBuffer::<4>::new().chunks(2).next(), None);
+    }
+
+    #[test]
+    fn test_drain_chunks() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        line_buffer.push_bytes(b"xxxxx\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 8]).unwrap();
+        line_buffer.push_bytes(b"ab\ncdef").unwrap();
+
+        let mut drain = line_buffer.drain_chunks(3);
+        assert_eq!(drain.next_chunk(), Some((&b"ab"[..], &b"\n"[..])));
+        // Only part of the chunk was acknowledged, so the rest comes round again.
+        drain.consume(2);
+        assert_eq!(drain.next_chunk(), Some((&b"\ncd"[..], &b""[..])));
+        drain.consume(3);
+        assert_eq!(drain.next_chunk(), Some((&b"ef"[..], &b""[..])));
+        drain.consume(10);
+        assert_eq!(drain.next_chunk(), None);
+        assert!(line_buffer.is_empty());
+        assert_eq!(line_buffer.line_count(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "chunk size must be > 0")]
+    fn test_chunks_zero_size() {
+        let _ = LineBuffer::<4>::new().chunks(0);
+    }
+
+    #[test]
+    fn test_contains() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        assert!(line_buffer.contains(b""));
+        assert!(!line_buffer.contains(b"a"));
+
+        line_buffer.push_bytes(b"abcde\n").unwrap();
+        assert!(line_buffer.contains(b"bcd"));
+        assert!(line_buffer.contains(b"e\n"));
+        line_buffer.read_line_bytes(&mut [0u8; 8]).unwrap();
+
+        line_buffer.push_bytes(b"Hello").unwrap();
+        assert!(line_buffer.end < line_buffer.start);
+
+        // "Hel" sits before the end of the storage and "lo" after it.
+        assert!(line_buffer.contains(b"ello"));
+        assert!(line_buffer.contains(b"Hello"));
+        assert!(line_buffer.contains(b""));
+        assert!(!line_buffer.contains(b"Hello!"));
+        assert!(!line_buffer.contains(b"olH"));
+        // Bytes already read are not searched.
+        assert!(!line_buffer.contains(b"abc"));
+    }
+
+    #[test]
+    fn test_find() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        assert_eq!(line_buffer.find(b""), Some(0));
+        assert_eq!(line_buffer.find(b"a"), None);
+
+        line_buffer.push_bytes(b"abcab\n").unwrap();
+        assert_eq!(line_buffer.find(b"ab"), Some(0));
+        assert_eq!(line_buffer.find(b"b\n"), Some(4));
+        line_buffer.read_line_bytes(&mut [0u8; 8]).unwrap();
+
+        line_buffer.push_bytes(b"Hello").unwrap();
+        assert!(line_buffer.end < line_buffer.start);
+
+        // Offsets count from the read position, not the start of the storage.
+        assert_eq!(line_buffer.find(b"H"), Some(0));
+        assert_eq!(line_buffer.find(b"llo"), Some(2));
+        assert_eq!(line_buffer.find(b"el"), Some(1));
+        assert_eq!(line_buffer.find(b"lo!"), None);
+        assert_eq!(line_buffer.find(b"Hello!"), None);
+    }
+
+    #[test]
+    fn test_is_valid_utf8() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        assert!(line_buffer.is_valid_utf8());
+
+        line_buffer.push_bytes(b"abcde\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 8]).unwrap();
+
+        // The three bytes of the euro sign straddle the end of the storage.
+        line_buffer.push_bytes("a€b".as_bytes()).unwrap();
+        assert!(line_buffer.end < line_buffer.start);
+        assert_eq!(line_buffer.contiguous_read_len(), 2);
+        assert!(line_buffer.is_valid_utf8());
+
+        // A continuation byte that continues nothing.
+        line_buffer.push_byte(0x80).unwrap();
+        assert!(!line_buffer.is_valid_utf8());
+
+        // A split character whose second half is invalid.
+        line_buffer.clear();
+        line_buffer.push_bytes(b"abcde\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 8]).unwrap();
+        line_buffer.push_bytes(b"a\xe2\x82x").unwrap();
+        assert!(!line_buffer.is_valid_utf8());
+
+        // Only the first byte of a character has arrived.
+        line_buffer.clear();
+        line_buffer.push_bytes(b"ab\xe2").unwrap();
+        assert!(!line_buffer.is_valid_utf8());
+    }
+
+    #[test]
+    fn test_recent_byte() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        assert_eq!(line_buffer.recent_byte(0), None);
+
+        line_buffer.push_bytes(b"abcde\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 8]).unwrap();
+        line_buffer.push_bytes(b"Hello").unwrap();
+        assert!(line_buffer.end < line_buffer.start);
+
+        assert_eq!(line_buffer.recent_byte(0), Some(b'o'));
+        assert_eq!(line_buffer.recent_byte(1), Some(b'l'));
+        // Across the wrap, back to the oldest byte.
+        assert_eq!(line_buffer.recent_byte(4), Some(b'H'));
+        assert_eq!(line_buffer.recent_byte(5), None);
+        assert_eq!(line_buffer.recent_byte(usize::MAX), None);
+    }
+
+    /// Stream lines of varying length through the buffer, reading each back as soon as it completes.
+    fn stream_lines<const N: usize, I: RingIndex>(rng: &mut Rng) {
+        let mut line_buffer = LineBuffer::<N, I>::new();
+        let mut line = [0u8; N];
+        let mut aux_buffer = [0u8; N];
+
+        for _ in 0..2000 {
+            let line_len = rng.below(N);
+            for byte in line[..line_len].iter_mut() {
+                *byte = b'a' + rng.below(26) as u8;
+            }
+            line_buffer.push_bytes(&line[..line_len]).unwrap();
+            line_buffer.push_byte(b'\n').unwrap();
+
+            let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+            assert_eq!(aux_buffer[..bytes_read], line[..line_len]);
+            assert!(line_buffer.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_wrap() {
+        let eight = LineBuffer::<8>::new();
+        assert_eq!(eight.wrap(7), 7);
+        assert_eq!(eight.wrap(8), 0);
+        assert_eq!(eight.wrap(15), 7);
+        let seven = LineBuffer::<7>::new();
+        assert_eq!(seven.wrap(6), 6);
+        assert_eq!(seven.wrap(7), 0);
+        assert_eq!(seven.wrap(13), 6);
+    }
+
+    /// Power-of-two and other capacities behave identically across many wrap-arounds.
+    #[test]
+    fn test_power_of_two_and_other_capacities() {
+        let mut rng = Rng(0xdead_beef);
+        stream_lines::<8, usize>(&mut rng);
+        stream_lines::<7, usize>(&mut rng);
+        stream_lines::<64, usize>(&mut rng);
+        stream_lines::<61, usize>(&mut rng);
+        stream_lines::<4096, usize>(&mut rng);
+        stream_lines::<4000, usize>(&mut rng);
+    }
+
+    static mut STATIC_BUFFER: LineBuffer<16> = LineBuffer::new();
+    static CRLF_BUFFER: LineBuffer<16> = LineBuffer::new_with_config(&LineBufferConfig {
+        terminator: Terminator::CarriageReturnNewline,
+        ..LineBufferConfig::DEFAULT
+    });
+
+    #[test]
+    fn test_static_buffer() {
+        // SAFETY: This is the only place the static is accessed.
+        let line_buffer = unsafe { &mut *core::ptr::addr_of_mut!(STATIC_BUFFER) };
+
+        line_buffer.push_bytes(b"Hello\n").unwrap();
+
+        let mut aux_buffer = [0u8; 16];
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..bytes_read], b"Hello");
+
+        assert!(CRLF_BUFFER.is_empty());
+        assert_eq!(
+            CRLF_BUFFER.config.terminator,
+            Terminator::CarriageReturnNewline
+        );
+    }
+
+    #[test]
+    fn test_read_line_with_escape() {
+        let mut line_buffer = LineBuffer::<32>::new_with_config(&LineBufferConfig {
+            escape: Some(b'\\'),
+            ..LineBufferConfig::DEFAULT
+        });
+
+        line_buffer.push_bytes(b"one\\\ntwo\nthree\n").unwrap();
+        assert_eq!(line_buffer.line_count(), 2);
+
+        let mut aux_buffer = [0u8; 32];
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..bytes_read], b"one\ntwo");
+
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..bytes_read], b"three");
+    }
+
+    /// An escaped escape byte is literal and does not escape the terminator after it.
+    #[test]
+    fn test_read_line_with_escaped_escape() {
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            escape: Some(b'\\'),
+            ..LineBufferConfig::DEFAULT
+        });
+
+        line_buffer.push_bytes(b"a\\\\\nb\n").unwrap();
+        assert_eq!(line_buffer.line_count(), 2);
+
+        let mut aux_buffer = [0u8; 16];
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..bytes_read], b"a\\");
+    }
+
+    #[test]
+    fn test_read_line_with_escaped_two_byte_terminator() {
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturnNewline,
+            escape: Some(b'\\'),
+            ..LineBufferConfig::DEFAULT
+        });
+
+        line_buffer.push_bytes(b"a\\\r\nb\r\n").unwrap();
+        assert_eq!(line_buffer.line_count(), 1);
+
+        let mut aux_buffer = [0u8; 16];
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..bytes_read], b"a\r\nb");
+        assert!(line_buffer.is_empty());
+    }
+
+    /// The escape byte sits at the last index of the storage and the escaped terminator at index 0.
+    #[test]
+    fn test_read_line_with_escape_at_wrap() {
+        let mut line_buffer = LineBuffer::<8>::new_with_config(&LineBufferConfig {
+            escape: Some(b'\\'),
+            ..LineBufferConfig::DEFAULT
+        });
+        let mut aux_buffer = [0u8; 8];
+
+        line_buffer.push_bytes(b"abcde\n").unwrap();
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+
+        line_buffer.push_bytes(b"x\\").unwrap();
+        assert_eq!(line_buffer.end, 0);
+        line_buffer.push_byte(b'\n').unwrap();
+        assert!(!line_buffer.has_complete_line());
+
+        line_buffer.push_bytes(b"y\n").unwrap();
+        assert!(line_buffer.has_complete_line());
+
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..bytes_read], b"x\ny");
+        assert!(line_buffer.is_empty());
+    }
+
+    #[test]
+    fn test_read_lines() {
+        let mut line_buffer = LineBuffer::<32>::new();
+        line_buffer.push_bytes(b"one\ntwo\nthree\nfour").unwrap();
+
+        let mut aux_buffer = [0u8; 32];
+        let info = line_buffer.read_lines(&mut aux_buffer, 10).unwrap();
+
+        assert_eq!(
+            info,
+            BatchInfo {
+                lines: 3,
+                bytes: 14
+            }
+        );
+        assert_eq!(&aux_buffer[..info.bytes], b"one\ntwo\nthree\n");
+
+        // The trailing partial line stays buffered.
+        assert_eq!(line_buffer.len(), 4);
+        line_buffer
+            .read_lines(&mut aux_buffer, 10)
+            .expect_err("Expected no lines error");
+        line_buffer.push_byte(b'\n').unwrap();
+        let info = line_buffer.read_lines(&mut aux_buffer, 10).unwrap();
+        assert_eq!(&aux_buffer[..info.bytes], b"four\n");
+    }
+
+    #[test]
+    fn test_read_lines_max_lines_and_separator() {
+        let mut line_buffer = LineBuffer::<32>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturnNewline,
+            batch_separator: BatchSeparator::NULL,
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"a\r\nbb\r\nccc\r\n").unwrap();
+
+        let mut aux_buffer = [0u8; 32];
+        let info = line_buffer.read_lines(&mut aux_buffer, 2).unwrap();
+        assert_eq!(info, BatchInfo { lines: 2, bytes: 5 });
+        assert_eq!(&aux_buffer[..info.bytes], b"a\0bb\0");
+        assert_eq!(line_buffer.line_count(), 1);
+
+        let info = line_buffer.read_lines(&mut aux_buffer, 0).unwrap();
+        assert_eq!(info, BatchInfo { lines: 0, bytes: 0 });
+        assert_eq!(line_buffer.line_count(), 1);
+    }
+
+    #[test]
+    fn test_read_paragraph() {
+        let mut line_buffer = LineBuffer::<64, u8>::new();
+        line_buffer
+            .push_bytes(b"\n\nHTTP/1.1 200 OK\nServer: x\nLength: 2\n\nHTTP/1.1 404")
+            .unwrap();
+
+        let mut aux_buffer = [0u8; 64];
+        assert_eq!(
+            line_buffer.read_paragraph(&mut aux_buffer[..20]),
+            Err(LineBufferRxError::AuxBufferTooSmall)
+        );
+        // The blank lines in front were skipped, but the record is still there.
+        assert_eq!(line_buffer.len(), 49);
+        let len = line_buffer.read_paragraph(&mut aux_buffer).unwrap();
+        assert_eq!(
+            &aux_buffer[..len],
+            b"HTTP/1.1 200 OK\nServer: x\nLength: 2\n"
+        );
+
+        // The next record stays whole until its blank line arrives.
+        line_buffer.push_bytes(b"\nServer: x\n").unwrap();
+        assert_eq!(
+            line_buffer.read_paragraph(&mut aux_buffer),
+            Err(LineBufferRxError::NoLines { pending: 23 })
+        );
+        assert_eq!(line_buffer.line_count(), 2);
+        line_buffer.push_bytes(b"Length: 0\n").unwrap();
+        assert!(line_buffer.read_paragraph(&mut aux_buffer).is_err());
+        line_buffer.push_bytes(b"\n").unwrap();
+        let len = line_buffer.read_paragraph(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"HTTP/1.1 404\nServer: x\nLength: 0\n");
+        assert!(line_buffer.is_empty());
+        assert_eq!(
+            line_buffer.read_paragraph(&mut aux_buffer),
+            Err(LineBufferRxError::BufferEmpty)
+        );
+    }
+
+    #[test]
+    fn test_read_paragraph_line_end() {
+        let config = || LineBufferConfig {
+            escape: Some(b'\\'),
+            strip_trailing_cr: true,
+            ..LineBufferConfig::DEFAULT
+        };
+        let mut line_buffer = LineBuffer::<32>::new_with_config(leak(config()));
+        line_buffer
+            .push_bytes(b"01234567890123456789012\n")
+            .unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 32]).unwrap();
+        // Wrapped, with an escaped newline and a CRLF blank line.
+        let record = b"\r\nA\\\nB\r\nC\n\r\nD";
+        line_buffer.push_bytes(record).unwrap();
+        assert!(!line_buffer.as_slices().1.is_empty());
+
+        let mut aux_buffer = [0u8; 32];
+        let len = line_buffer.read_paragraph(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"A\nB\r\nC\n");
+        assert_eq!(line_buffer.len(), 1);
+
+        let mut line_buffer = LineBuffer::<32>::new_with_config(leak(LineBufferConfig {
+            paragraph_line_end: ParagraphLineEnd::Newline,
+            ..config()
+        }));
+        line_buffer.push_bytes(record).unwrap();
+        let len = line_buffer.read_paragraph(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"A\nB\nC\n");
+
+        let mut line_buffer = LineBuffer::<32>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturnNewline,
+            paragraph_line_end: ParagraphLineEnd::Newline,
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer
+            .push_bytes(b"Host: a\r\nAccept: *\r\n\r\n")
+            .unwrap();
+        let len = line_buffer.read_paragraph(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"Host: a\nAccept: *\n");
+    }
+
+    #[test]
+    fn test_read_line_fields() {
+        let mut line_buffer = LineBuffer::<128>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturnNewline,
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer
+            .push_bytes(b"$GPRMC,123519,V,,,,,,,230394,,,N*53\r\n")
+            .unwrap();
+        line_buffer
+            .push_bytes(b"$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A\r\n")
+            .unwrap();
+        line_buffer.push_bytes(b"\r\nnext\r\n").unwrap();
+
+        let mut aux_buffer = [0u8; 128];
+        let mut fields = [&b"-"[..]; 16];
+        assert_eq!(
+            line_buffer.read_line_fields(&mut aux_buffer, b',', &mut fields),
+            Ok(13)
+        );
+        assert_eq!(
+            fields[..13],
+            [
+                &b"$GPRMC"[..],
+                b"123519",
+                b"V",
+                b"",
+                b"",
+                b"",
+                b"",
+                b"",
+                b"",
+                b"230394",
+                b"",
+                b"",
+                b"N*53"
+            ]
+        );
+        assert_eq!(fields[13], b"-");
+
+        // More fields than there is room for.
+        let mut few = [&b""[..]; 8];
+        assert_eq!(
+            line_buffer.read_line_fields(&mut aux_buffer, b',', &mut few),
+            Err(LineBufferRxError::TooManyFields { found: 12 })
+        );
+        assert_eq!(few[3], b"4807.038");
+        assert_eq!(few[7], b"022.4");
+
+        // An empty line is a single empty field.
+        let mut fields = [&b"-"[..]; 4];
+        assert_eq!(
+            line_buffer.read_line_fields(&mut aux_buffer, b',', &mut fields),
+            Ok(1)
+        );
+        assert_eq!(fields[0], b"");
+        let mut fields = [&b"-"[..]; 4];
+        assert_eq!(
+            line_buffer.read_line_fields(&mut aux_buffer, b',', &mut fields),
+            Ok(1)
+        );
+        assert_eq!(fields[0], b"next");
+        assert_eq!(
+            line_buffer.read_line_fields(&mut aux_buffer, b',', &mut []),
+            Err(LineBufferRxError::BufferEmpty)
+        );
+    }
+
+    #[test]
+    fn test_read_lines_exact_fit() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        line_buffer.push_bytes(b"ab\ncd\nef\n").unwrap();
+
+        // Room for exactly two lines and their separators.
+        let mut aux_buffer = [0u8; 6];
+        let info = line_buffer.read_lines(&mut aux_buffer, 10).unwrap();
+        assert_eq!(info, BatchInfo { lines: 2, bytes: 6 });
+        assert_eq!(&aux_buffer, b"ab\ncd\n");
+        assert_eq!(line_buffer.line_count(), 1);
+
+        // One byte short of fitting the line and its separator.
+        let mut aux_buffer = [0u8; 2];
+        assert!(matches!(
+            line_buffer.read_lines(&mut aux_buffer, 10),
+            Err(LineBufferRxError::AuxBufferTooSmall)
+        ));
+        assert_eq!(line_buffer.line_count(), 1);
+
+        let mut aux_buffer = [0u8; 3];
+        let info = line_buffer.read_lines(&mut aux_buffer, 10).unwrap();
+        assert_eq!(info, BatchInfo { lines: 1, bytes: 3 });
+        assert!(line_buffer.is_empty());
+    }
+
+    #[test]
+    fn test_len_and_remaining_capacity() {
+        let mut line_buffer = LineBuffer::<10>::new();
+        assert_eq!(line_buffer.len(), 0);
+        assert_eq!(line_buffer.remaining_capacity(), 10);
+
+        line_buffer.push_bytes(b"abc").unwrap();
+        assert_eq!(line_buffer.len(), 3);
+        assert_eq!(line_buffer.remaining_capacity(), 7);
+
+        line_buffer.push_bytes(b"defghij").unwrap();
+        assert_eq!(line_buffer.len(), 10);
+        assert_eq!(line_buffer.remaining_capacity(), 0);
+    }
+
+    #[test]
+    fn test_contiguous_write_len() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        assert_eq!(line_buffer.contiguous_write_len(), 8);
+
+        // Free region runs to the end of the storage without wrapping.
+        line_buffer.push_bytes(b"ab\n").unwrap();
+        assert_eq!(line_buffer.contiguous_write_len(), 5);
+
+        // Free region wraps: only the part up to the end of the storage is contiguous.
+        let mut aux_buffer = [0u8; 8];
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        line_buffer.push_bytes(b"cd").unwrap();
+        assert_eq!(line_buffer.remaining_capacity(), 6);
+        assert_eq!(line_buffer.contiguous_write_len(), 3);
+
+        // Written data wraps: the free region sits between end and start.
+        line_buffer.push_bytes(b"efgh").unwrap();
+        assert_eq!(line_buffer.end, 1);
+        assert_eq!(line_buffer.contiguous_write_len(), 2);
+
+        line_buffer.push_bytes(b"ij").unwrap();
+        assert!(line_buffer.is_full());
+        assert_eq!(line_buffer.contiguous_write_len(), 0);
+    }
+
+    #[test]
+    fn test_push_bytes_tee() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        let mut mirror = LineBuffer::<16>::new();
+        let mut aux_buffer = [0u8; 16];
+
+        line_buffer
+            .push_bytes_tee(b"ab\ncd\n", &mut mirror)
+            .unwrap();
+        assert_eq!(mirror.len(), 6);
+        assert_eq!(line_buffer.read_line_bytes(&mut [0u8; 8]), Ok(2));
+        assert_eq!(mirror.read_line_bytes(&mut aux_buffer), Ok(2));
+        assert_eq!(&aux_buffer[..2], b"ab");
+
+        // The mirror only gets what the primary accepted.
+        assert_eq!(
+            line_buffer.push_bytes_tee(b"efghijk", &mut mirror),
+            Err(LineBufferTxError::BufferFull {
+                requested: 7,
+                available: 5,
+                written: 5,
+            })
+        );
+        let mut out = [0u8; 16];
+        assert_eq!(mirror.peek_all(&mut out), 8);
+        assert_eq!(&out[..8], b"cd\nefghi");
+
+        // A full mirror doesn't fail the push.
+        let mut small_mirror = LineBuffer::<2>::new();
+        line_buffer.clear();
+        line_buffer
+            .push_bytes_tee(b"xyz\n", &mut small_mirror)
+            .unwrap();
+        assert_eq!(line_buffer.len(), 4);
+        assert!(small_mirror.is_full());
+    }
+
+    /// Feeding a stream in random chunks calls back with the same lines as splitting it whole.
+    #[test]
+    fn test_feed_matches_split_lines() {
+        let mut rng = Rng(0x00fe_edf0);
+        let mut data = [0u8; NAIVE_CAPACITY];
+
+        for _ in 0..100 {
+            // Lines of up to 12 bytes, so each one fits a buffer of 16.
+            let mut len = 0;
+            while len < NAIVE_CAPACITY - 13 {
+                for _ in 0..rng.below(13) {
+                    data[len] = [b'a', b'b', b'\r'][rng.below(3)];
+                    len += 1;
+                }
+                data[len] = b'\n';
+                len += 1;
+            }
+            let data = &data[..len];
+
+            let mut line_buffer = LineBuffer::<16>::new();
+            let mut expected = split_lines(data, Terminator::Newline);
+            let mut rest = data;
+            while !rest.is_empty() {
+                let (chunk, next) = rest.split_at(rng.below(rest.len() + 1));
+                let accepted = line_buffer
+                    .feed(chunk, |first, second| {
+                        let line = expected.next().unwrap();
+                        assert_eq!(&line[..first.len()], first);
+                        assert_eq!(&line[first.len()..], second);
+                    })
+                    .unwrap();
+                assert_eq!(accepted, chunk.len());
+                assert!(!line_buffer.has_complete_line());
+                rest = next;
+            }
+            assert_eq!(expected.next(), None);
+            assert!(line_buffer.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_feed_line_too_long() {
+        let mut line_buffer = LineBuffer::<4>::new();
+        let mut lines = 0;
+        assert_eq!(
+            line_buffer.feed(b"ab\ncdefg\n", |_, _| lines += 1),
+            Err(LineBufferTxError::BufferFull {
+                requested: 9,
+                available: 0,
+                written: 7,
+            })
+        );
+        assert_eq!(lines, 1);
+        assert!(line_buffer.is_full());
+
+        let mut line_buffer = LineBuffer::<4>::new_overwriting();
+        assert_eq!(line_buffer.feed(b"ab\ncdefg\nh", |_, _| lines += 1), Ok(10));
+        assert_eq!(lines, 3);
+        assert_eq!(line_buffer.len(), 1);
+
+        // A line over `max_line_length` is dropped without stopping the lines after it.
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            max_line_length: Some(3),
+            line_too_long: LineTooLongPolicy::Error,
+            ..LineBufferConfig::DEFAULT
+        });
+        let mut lines = 0;
+        assert_eq!(
+            line_buffer.feed(b"toolong\nok\n", |first, second| {
+                assert_eq!((first, second), (&b"ok"[..], &b""[..]));
+                lines += 1;
+            }),
+            Ok(11)
+        );
+        assert_eq!(lines, 1);
+        assert!(line_buffer.is_empty());
+    }
+
+    #[test]
+    fn test_append_from() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        let mut other = LineBuffer::<8>::new();
+        let mut out = [0u8; 8];
+
+        // Move a wrapped buffer's bytes over in full.
+        other.push_bytes(b"xxxxx\n").unwrap();
+        other.read_line_bytes(&mut out).unwrap();
+        other.push_bytes(b"ab\ncd").unwrap();
+        assert!(other.end.get() < other.start.get());
+        line_buffer.push_byte(b'>').unwrap();
+        assert_eq!(line_buffer.append_from(&mut other), Ok(5));
+        assert!(other.is_empty());
+        assert_eq!(line_buffer.peek_all(&mut out), 6);
+        assert_eq!(&out[..6], b">ab\ncd");
+        assert_eq!(line_buffer.line_count(), 1);
+
+        // Only part of the next one fits, and the rest stays put.
+        other.push_bytes(b"efg\nhi").unwrap();
+        assert_eq!(
+            line_buffer.append_from(&mut other),
+            Err(LineBufferTxError::BufferFull {
+                requested: 6,
+                available: 2,
+                written: 2,
+            })
+        );
+        assert!(line_buffer.is_full());
+        assert_eq!(other.peek_all(&mut out), 4);
+        assert_eq!(&out[..4], b"g\nhi");
+        assert_eq!(other.line_count(), 1);
+
+        // An empty source moves nothing.
+        let mut empty = LineBuffer::<4>::new();
+        assert_eq!(line_buffer.append_from(&mut empty), Ok(0));
+
+        // Overflow policies that drop old bytes don't apply, so only what fits moves.
+        for overflow in [
+            OverflowPolicy::OverwriteOldest,
+            OverflowPolicy::DropOldestLine,
+        ] {
+            let mut line_buffer = LineBuffer::<8>::new_with_config(leak(LineBufferConfig {
+                overflow,
+                ..LineBufferConfig::DEFAULT
+            }));
+            line_buffer
+                .push_bytes(
+                    b"ab
+cde",
+                )
+                .unwrap();
+            let mut other = LineBuffer::<16>::new();
+            other.push_bytes(b"0123456789").unwrap();
+            assert_eq!(
+                line_buffer.append_from(&mut other),
+                Err(LineBufferTxError::BufferFull {
+                    requested: 10,
+                    available: 2,
+                    written: 2,
+                })
+            );
+            assert_eq!(line_buffer.peek_all(&mut out), 8);
+            assert_eq!(
+                &out,
+                b"ab
+cde01"
+            );
+            assert_eq!(other.len(), 8);
+        }
+    }
+
+    #[test]
+    fn test_push_from_iter() {
+        let mut line_buffer = LineBuffer::<16>::new();
+
+        let accepted = line_buffer
+            .push_from_iter(b"Hello\nWorld".iter().copied())
+            .unwrap();
+        assert_eq!(accepted, 11);
+        assert_eq!(line_buffer.line_count(), 1);
+
+        let mut aux_buffer = [0u8; 16];
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..bytes_read], b"Hello");
+    }
+
+    /// An iterator longer than the free space fills the buffer exactly, across the wrap.
+    #[test]
+    fn test_push_from_iter_overflow() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        line_buffer.push_bytes(b"abcde\n").unwrap();
+        let mut aux_buffer = [0u8; 8];
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        line_buffer.push_bytes(b"x").unwrap();
+
+        let mut iter = (b'0'..=b'9').chain(core::iter::once(b'\n'));
+        match line_buffer.push_from_iter(&mut iter) {
+            Err(LineBufferTxError::Overflow { accepted, rejected }) => {
+                assert_eq!(accepted, 7);
+                assert_eq!(rejected, b'7');
+            }
+            result => panic!("Expected overflow error, got {:?}", result),
+        }
+        assert!(line_buffer.is_full());
+        assert_eq!(iter.next(), Some(b'8'));
+
+        let mut out = [0u8; 8];
+        line_buffer.peek_all(&mut out);
+        assert_eq!(&out, b"x0123456");
+    }
+
+    #[test]
+    fn test_push_from_iter_exact_fit() {
+        let mut line_buffer = LineBuffer::<4>::new();
+
+        assert_eq!(line_buffer.push_from_iter(*b"ab\nc").unwrap(), 4);
+        assert!(line_buffer.is_full());
+        assert_eq!(line_buffer.line_count(), 1);
+
+        assert_eq!(line_buffer.push_from_iter(core::iter::empty()).unwrap(), 0);
+        assert!(matches!(
+            line_buffer.push_from_iter(*b"d"),
+            Err(LineBufferTxError::Overflow {
+                accepted: 0,
+                rejected: b'd'
+            })
+        ));
+    }
+
+    #[test]
+    fn test_contiguous_read_len() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        assert_eq!(line_buffer.contiguous_read_len(), 0);
+
+        line_buffer.push_bytes(b"ab\ncd").unwrap();
+        assert_eq!(line_buffer.contiguous_read_len(), 5);
+
+        // Readable region wraps: only the part up to the end of the storage is contiguous.
+        let mut aux_buffer = [0u8; 8];
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        line_buffer.push_bytes(b"efgh").unwrap();
+        assert_eq!(line_buffer.len(), 6);
+        assert_eq!(line_buffer.contiguous_read_len(), 5);
+        assert_eq!(line_buffer.as_slices().0.len(), 5);
+
+        line_buffer.push_bytes(b"ij").unwrap();
+        assert!(line_buffer.is_full());
+        assert_eq!(line_buffer.contiguous_read_len(), 5);
+    }
+
+    #[test]
+    fn test_static_line_buffer_take_twice() {
+        static RX: StaticLineBuffer<16> = StaticLineBuffer::new();
+        let line_buffer = RX.take().unwrap();
+        assert!(RX.take().is_none());
+        assert!(RX.take().is_none());
+
+        line_buffer.push_bytes(b"AT\r\n").unwrap();
+        let mut aux_buffer = [0u8; 16];
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(3));
+    }
+
+    #[test]
+    fn test_local_line_buffer_shared_by_two_components() {
+        struct Uart<'a> {
+            rx: &'a LocalLineBuffer<16>,
+        }
+        struct Parser<'a> {
+            rx: &'a LocalLineBuffer<16>,
+            lines: usize,
+        }
+
+        let rx = LocalLineBuffer::<16>::new();
+        let uart = Uart { rx: &rx };
+        let mut parser = Parser { rx: &rx, lines: 0 };
+
+        for chunk in [&b"AT"[..], b"\r\nOK\r", b"\n"] {
+            uart.rx.push_bytes(chunk).unwrap();
+            while parser.rx.has_complete_line() {
+                parser.rx.with_line(|_, _| parser.lines += 1).unwrap();
+            }
+        }
+        assert_eq!(parser.lines, 2);
+        assert!(rx.is_empty());
+
+        uart.rx.push_byte(b'x').unwrap();
+        assert_eq!(parser.rx.len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "LocalLineBuffer used again from inside one of its own closures")]
+    fn test_local_line_buffer_reentrant_read_panics() {
+        let rx = LocalLineBuffer::<16>::new();
+        rx.push_bytes(b"a\nb\n").unwrap();
+        let _ = rx.with_line(|_, _| rx.read_line_bytes(&mut [0u8; 16]));
+    }
+
+    /// A line handler sees every line exactly once, however the pushes split it up.
+    #[test]
+    fn test_line_handler_fires_once_per_line() {
+        use core::sync::atomic::{AtomicUsize, Ordering};
+
+        static LINES: AtomicUsize = AtomicUsize::new(0);
+        static BYTES: AtomicUsize = AtomicUsize::new(0);
+        fn on_line(first: &[u8], second: &[u8]) {
+            assert!(!first.contains(&b'\n') && !second.contains(&b'\n'));
+            LINES.fetch_add(1, Ordering::Relaxed);
+            BYTES.fetch_add(first.len() + second.len(), Ordering::Relaxed);
+        }
+
+        for consume in [false, true] {
+            LINES.store(0, Ordering::Relaxed);
+            BYTES.store(0, Ordering::Relaxed);
+            let mut line_buffer = LineBuffer::<16>::new_with_config(leak(LineBufferConfig {
+                terminator: Terminator::CarriageReturnNewline,
+                line_handler: Some(LineHandler { on_line, consume }),
+                ..LineBufferConfig::DEFAULT
+            }));
+
+            // The terminator is split across pushes, and across the end of the storage.
+            line_buffer.push_bytes(b"0123456789\r").unwrap();
+            assert_eq!(LINES.load(Ordering::Relaxed), 0);
+            line_buffer.push_byte(b'\n').unwrap();
+            assert_eq!(LINES.load(Ordering::Relaxed), 1);
+            if !consume {
+                line_buffer.read_line_bytes(&mut [0u8; 16]).unwrap();
+            }
+            line_buffer.push_bytes(b"ab\r\ncd").unwrap();
+            line_buffer.push_from_iter(*b"\r").unwrap();
+            line_buffer.push_bytes(b"\n\r\n").unwrap();
+            assert_eq!(LINES.load(Ordering::Relaxed), 4);
+            assert_eq!(BYTES.load(Ordering::Relaxed), 14);
+            assert_eq!(line_buffer.line_count(), if consume { 0 } else { 3 });
+            assert_eq!(line_buffer.is_empty(), consume);
+        }
+    }
+
+    #[test]
+    fn test_small_index_size() {
+        use core::mem::{align_of, size_of};
+
+        let overhead = |size: usize| size - 64;
+        let usize_overhead = overhead(size_of::<LineBuffer<64>>());
+        let u8_overhead = overhead(size_of::<LineBuffer<64, u8>>());
+        let u16_overhead = overhead(size_of::<LineBuffer<64, u16>>());
+
+        // A pointer to the shared config, the 12 bytes of idle-flush state, and a byte each for
+        // start, end, empty, lines, matched, escaped, rewindable, rewind_lost, high_watermark,
+        // flow, flow_change, truncated, in_line_handler, mid_line, terminator_left, scan_pos,
+        // config_changed and the lines, matched and escaped of scan_pos_state, padded to the
+        // pointer's alignment: 40 bytes with 64-bit pointers, 36 with 32-bit ones.
+        let max_overhead = (size_of::<usize>() + 12 + 20).next_multiple_of(align_of::<usize>());
+        #[cfg(feature = "stats")]
+        let max_overhead = max_overhead + size_of::<LineBufferStats>();
+        assert!(u8_overhead <= max_overhead);
+        // The u16 fields can end up in the same padding as the u8 ones, but never take less.
+        assert!(u8_overhead <= u16_overhead);
+        assert!(u16_overhead < usize_overhead);
+    }
+
+    /// A buffer with `u8` indices behaves exactly like one with `usize` indices.
+    #[test]
+    fn test_small_index_matches_usize_index() {
+        let mut rng = Rng(0x0bad_cafe);
+        let mut small = LineBuffer::<9, u8>::new();
+        let mut large = LineBuffer::<9>::new();
+
+        for _ in 0..20000 {
+            match rng.below(8) {
+                0..=2 => {
+                    let byte = rng.byte();
+                    assert_eq!(small.push_byte(byte).is_ok(), large.push_byte(byte).is_ok());
+                }
+                3 | 4 => {
+                    let byte = rng.byte();
+                    assert_eq!(
+                        small.push_byte_overwrite(byte),
+                        large.push_byte_overwrite(byte)
+                    );
+                }
+                5 | 6 => {
+                    let mut small_aux = [0u8; 9];
+                    let mut large_aux = [0u8; 9];
+                    let small_read = small.read_line_bytes(&mut small_aux).ok();
+                    let large_read = large.read_line_bytes(&mut large_aux).ok();
+                    assert_eq!(small_read, large_read);
+                    assert_eq!(small_aux, large_aux);
+                }
+                _ => {
+                    small.clear();
+                    large.clear();
+                }
+            }
+
+            assert_eq!(small.len(), large.len());
+            assert_eq!(small.line_count(), large.line_count());
+            assert_eq!(small.line_count(), naive_line_count(&small));
+        }
+
+        stream_lines::<255, u8>(&mut rng);
+        stream_lines::<300, u16>(&mut rng);
+    }
+
+    /// Hands out its chunks one `read` at a time, then fails.
+    #[cfg(feature = "embedded-io")]
+    struct MockReader<'a> {
+        chunks: &'a [&'a [u8]],
+    }
+
+    #[cfg(feature = "embedded-io")]
+    impl embedded_io::ErrorType for MockReader<'_> {
+        type Error = embedded_io::ErrorKind;
+    }
+
+    #[cfg(feature = "embedded-io")]
+    impl embedded_io::Read for MockReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            let (chunk, rest) = self
+                .chunks
+                .split_first()
+                .ok_or(embedded_io::ErrorKind::TimedOut)?;
+            let len = chunk.len().min(buf.len());
+            buf[..len].copy_from_slice(&chunk[..len]);
+            self.chunks = rest;
+            Ok(len)
+        }
+    }
+
+    /// Delivers its script one entry per `read`, keeping whatever doesn't fit for the next one.
+    /// An empty entry is a moment with nothing ready, and the end of the script is an error.
+    #[cfg(feature = "embedded-io")]
+    struct ScriptedReader<'a> {
+        script: &'a [&'a [u8]],
+        /// Bytes of the first entry already delivered.
+        offset: usize,
+    }
+
+    #[cfg(feature = "embedded-io")]
+    impl embedded_io::ErrorType for ScriptedReader<'_> {
+        type Error = embedded_io::ErrorKind;
+    }
+
+    #[cfg(feature = "embedded-io")]
+    impl embedded_io::ReadReady for ScriptedReader<'_> {
+        fn read_ready(&mut self) -> Result<bool, Self::Error> {
+            match self.script.split_first() {
+                Some((&[], rest)) => {
+                    self.script = rest;
+                    Ok(false)
+                }
+                Some(_) => Ok(true),
+                None => Err(embedded_io::ErrorKind::BrokenPipe),
+            }
+        }
+    }
+
+    #[cfg(feature = "embedded-io")]
+    impl embedded_io::Read for ScriptedReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            let (chunk, rest) = self.script.split_first().unwrap();
+            let chunk = &chunk[self.offset..];
+            let len = chunk.len().min(buf.len());
+            buf[..len].copy_from_slice(&chunk[..len]);
+            self.offset += len;
+            if len == chunk.len() {
+                self.script = rest;
+                self.offset = 0;
+            }
+            Ok(len)
+        }
+    }
+
+    #[cfg(feature = "embedded-io")]
+    #[test]
+    fn test_fill_from() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        line_buffer.push_bytes(b"xxxx\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 8]).unwrap();
+        let mut aux_buffer = [0u8; 8];
+
+        // The first chunk ends exactly at the end of the storage, and `fghij` straddles it.
+        let mut src = ScriptedReader {
+            script: &[b"ab\n", b"c", b"de\n", b"", b"fghij\n", b"", b""],
+            offset: 0,
+        };
+        assert_eq!(line_buffer.fill_from(&mut src), Ok(7));
+        assert_eq!(line_buffer.line_count(), 2);
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(2));
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(3));
+        assert_eq!(&aux_buffer[..3], b"cde");
+
+        // Nothing was ready, so the rest waits for the next call.
+        assert_eq!(line_buffer.fill_from(&mut src), Ok(6));
+        assert!(line_buffer.end.get() < line_buffer.start.get());
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(5));
+        assert_eq!(&aux_buffer[..5], b"fghij");
+        assert_eq!(line_buffer.fill_from(&mut src), Ok(0));
+
+        // A full buffer stops the fill, leaving the rest of the chunk with the reader.
+        let mut src = ScriptedReader {
+            script: &[b"0123", b"456789\n"],
+            offset: 0,
+        };
+        assert_eq!(line_buffer.fill_from(&mut src), Ok(8));
+        assert!(line_buffer.is_full());
+        line_buffer.clear();
+        assert_eq!(
+            line_buffer.fill_from(&mut src),
+            Err(FillError {
+                error: embedded_io::ErrorKind::BrokenPipe,
+                filled: 3,
+            })
+        );
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(2));
+        assert_eq!(&aux_buffer[..2], b"89");
+    }
+
+    #[cfg(feature = "embedded-io")]
+    #[test]
+    fn test_read_from() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        line_buffer.push_bytes(b"xxxx\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 8]).unwrap();
+
+        let mut src = MockReader {
+            chunks: &[b"ab\ncdef", b"gh", b"ij"],
+        };
+        // Only the three bytes up to the end of the storage are offered to the first read.
+        assert_eq!(line_buffer.read_from(&mut src), Ok(3));
+        assert_eq!(line_buffer.line_count(), 1);
+        assert_eq!(line_buffer.read_from(&mut src), Ok(2));
+        assert_eq!(line_buffer.read_from(&mut src), Ok(2));
+        assert_eq!(line_buffer.len(), 7);
+        assert_eq!(
+            line_buffer.read_from(&mut src),
+            Err(embedded_io::ErrorKind::TimedOut)
+        );
+
+        let mut aux_buffer = [0u8; 8];
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"ab");
+        let mut out = [0u8; 8];
+        assert_eq!(line_buffer.peek_all(&mut out), 4);
+        assert_eq!(&out[..4], b"ghij");
+
+        // A full buffer doesn't touch the reader.
+        line_buffer.push_bytes(b"klmn").unwrap();
+        let mut src = MockReader { chunks: &[] };
+        assert_eq!(line_buffer.read_from(&mut src), Ok(0));
+    }
+
+    /// Takes at most `chunk` bytes per `write`, and fails once `fail_at` bytes have been written.
+    #[cfg(feature = "embedded-io")]
+    struct MockWriter {
+        out: [u8; 64],
+        len: usize,
+        chunk: usize,
+        fail_at: usize,
+    }
+
+    #[cfg(feature = "embedded-io")]
+    impl MockWriter {
+        fn new(chunk: usize, fail_at: usize) -> Self {
+            Self {
+                out: [0; 64],
+                len: 0,
+                chunk,
+                fail_at,
+            }
+        }
+
+        fn written(&self) -> &[u8] {
+            &self.out[..self.len]
+        }
+    }
+
+    #[cfg(feature = "embedded-io")]
+    impl embedded_io::ErrorType for MockWriter {
+        type Error = embedded_io::ErrorKind;
+    }
+
+    #[cfg(feature = "embedded-io")]
+    impl embedded_io::Write for MockWriter {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            if self.len == self.fail_at {
+                return Err(embedded_io::ErrorKind::BrokenPipe);
+            }
+            let len = buf.len().min(self.chunk).min(self.fail_at - self.len);
+            self.out[self.len..self.len + len].copy_from_slice(&buf[..len]);
+            self.len += len;
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "embedded-io")]
+    #[test]
+    fn test_drain_to() {
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturnNewline,
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"xxxxxxxxxx\r\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 16]).unwrap();
+        line_buffer.push_bytes(b"ab\r\ncde\r\nf").unwrap();
+
+        // Short writes are carried on with, across the wrap and the terminator.
+        let mut dst = MockWriter::new(2, 64);
+        assert_eq!(
+            line_buffer.drain_to(&mut dst),
+            Ok(DrainReport { lines: 2, bytes: 9 })
+        );
+        assert_eq!(dst.written(), b"ab\r\ncde\r\n");
+        assert_eq!(line_buffer.len(), 1);
+
+        // Nothing complete, nothing written.
+        assert_eq!(
+            line_buffer.drain_to(&mut dst),
+            Ok(DrainReport { lines: 0, bytes: 0 })
+        );
+        assert_eq!(dst.len, 9);
+    }
+
+    #[cfg(feature = "embedded-io")]
+    #[test]
+    fn test_drain_to_writer_errors() {
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            drain_terminator: Some(Terminator::NULL),
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"one\ntwo\n").unwrap();
+
+        // The writer fails part way into the second line, which stays buffered.
+        let mut dst = MockWriter::new(3, 6);
+        assert_eq!(
+            line_buffer.drain_to(&mut dst),
+            Err(DrainError {
+                error: Some(embedded_io::ErrorKind::BrokenPipe),
+                report: DrainReport { lines: 1, bytes: 4 },
+                written: 2,
+            })
+        );
+        assert_eq!(dst.written(), b"one\0tw");
+        assert_eq!(line_buffer.len(), 4);
+        assert_eq!(line_buffer.line_count(), 1);
+
+        // Failing on a terminator boundary keeps the line too.
+        let mut dst = MockWriter::new(8, 3);
+        assert_eq!(
+            line_buffer.drain_to(&mut dst),
+            Err(DrainError {
+                error: Some(embedded_io::ErrorKind::BrokenPipe),
+                report: DrainReport { lines: 0, bytes: 0 },
+                written: 3,
+            })
+        );
+        assert_eq!(line_buffer.len(), 4);
+
+        let mut dst = MockWriter::new(8, 64);
+        assert_eq!(
+            line_buffer.drain_to(&mut dst),
+            Ok(DrainReport { lines: 1, bytes: 4 })
+        );
+        assert_eq!(dst.written(), b"two\0");
+        assert!(line_buffer.is_empty());
+    }
+
+    #[test]
+    fn test_line_scanner() {
+        let scanner = LineScanner::new(Terminator::CarriageReturnNewline);
+        assert_eq!(scanner.next_line(b""), None);
+        assert_eq!(scanner.next_line(b"ab\r"), None);
+        assert_eq!(scanner.next_line(b"ab\r\ncd"), Some((&b"ab"[..], 4)));
+        assert_eq!(scanner.next_line(b"\r\r\n"), Some((&b"\r"[..], 3)));
+
+        let scanner = LineScanner::from_config(&LineBufferConfig {
+            escape: Some(b'\\'),
+            ..LineBufferConfig::DEFAULT
+        });
+        assert_eq!(scanner.next_line(b"a\\\nb\n"), Some((&b"a\\\nb"[..], 5)));
+        assert_eq!(scanner.next_line(b"a\\\n"), None);
+
+        let scanner = LineScanner::new(Terminator::None);
+        assert_eq!(scanner.next_line(b""), None);
+        assert_eq!(scanner.next_line(b"abc"), Some((&b"abc"[..], 3)));
+    }
+
+    /// `split_lines` produces exactly the lines a `LineBuffer` reads from the same bytes.
+    #[test]
+    fn test_split_lines_matches_line_buffer() {
+        let mut rng = Rng(0x0d1f_f5ed);
+        let terminators = [
+            Terminator::None,
+            Terminator::CarriageReturn,
+            Terminator::Newline,
+            Terminator::NULL,
+            Terminator::CarriageReturnNewline,
+            Terminator::NewlineCarriageReturn,
+            Terminator::Sequence(b"\r\r"),
+            Terminator::Sequence(b"ab\n"),
+        ];
+        let mut data = [0u8; NAIVE_CAPACITY];
+        let mut aux_buffer = [0u8; NAIVE_CAPACITY];
+
+        for _ in 0..200 {
+            for terminator in terminators {
+                let len = rng.below(NAIVE_CAPACITY);
+                data[..len].iter_mut().for_each(|byte| *byte = rng.byte());
+                let mut line_buffer =
+                    LineBuffer::<NAIVE_CAPACITY>::new_with_config(leak(LineBufferConfig {
+                        terminator,
+                        ..LineBufferConfig::DEFAULT
+                    }));
+                line_buffer.push_bytes(&data[..len]).unwrap();
+
+                let mut lines = split_lines(&data[..len], terminator);
+                for line in &mut lines {
+                    let read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+                    assert_eq!(line, &aux_buffer[..read]);
+                }
+                assert!(!line_buffer.has_complete_line());
+                let rest = contents(&line_buffer, &mut aux_buffer);
+                assert_eq!(lines.remainder(), &aux_buffer[..rest]);
+            }
+        }
+    }
+
+    /// Scanning data as it arrives in a contiguous buffer finds the same lines as a `LineBuffer`
+    /// fed the same chunks.
+    #[test]
+    fn test_line_scanner_matches_line_buffer() {
+        let mut rng = Rng(0x1ead_5ca7);
+        for terminator in [Terminator::Newline, Terminator::CarriageReturnNewline] {
+            for escape in [None, Some(b'\\')] {
+                let config = LineBufferConfig {
+                    terminator,
+                    escape,
+                    ..LineBufferConfig::DEFAULT
+                };
+                let scanner = LineScanner::from_config(&config);
+                let mut line_buffer = LineBuffer::<NAIVE_CAPACITY>::new_with_config(leak(config));
+                let mut data = [0u8; 2048];
+                data.iter_mut().for_each(|byte| *byte = rng.byte());
+
+                let (mut cursor, mut filled) = (0, 0);
+                while filled < data.len() {
+                    let chunk = (1 + rng.below(16)).min(data.len() - filled);
+                    line_buffer
+                        .push_bytes(&data[filled..filled + chunk])
+                        .unwrap();
+                    filled += chunk;
+
+                    while let Some((line, consumed)) = scanner.next_line(&data[cursor..filled]) {
+                        line_buffer
+                            .with_line(|first, second| {
+                                assert_eq!(first.len() + second.len(), line.len());
+                                assert_eq!(&line[..first.len()], first);
+                                assert_eq!(&line[first.len()..], second);
+                            })
+                            .unwrap();
+                        cursor += consumed;
+                    }
+                    assert!(!line_buffer.has_complete_line());
+                    assert_eq!(line_buffer.len(), filled - cursor);
+                }
+            }
+        }
+    }
+
+    /// A buffer with the terminator fixed by its type behaves exactly like one configured with it.
+    #[test]
+    fn test_typed_terminator_matches_config() {
+        let mut rng = Rng(0x5eed_1e55);
+        let mut typed =
+            LineBuffer::<9, usize, CrlfTerminator>::new_with_config(&LineBufferConfig {
+                escape: Some(b'\\'),
+                ..LineBufferConfig::DEFAULT
+            });
+        let mut configured = LineBuffer::<9>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturnNewline,
+            escape: Some(b'\\'),
+            ..LineBufferConfig::DEFAULT
+        });
+
+        for _ in 0..20000 {
+            match rng.below(8) {
+                0..=2 => {
+                    let byte = rng.byte();
+                    assert_eq!(
+                        typed.push_byte(byte).is_ok(),
+                        configured.push_byte(byte).is_ok()
+                    );
+                }
+                3 | 4 => {
+                    let byte = rng.byte();
+                    assert_eq!(
+                        typed.push_byte_overwrite(byte),
+                        configured.push_byte_overwrite(byte)
+                    );
+                }
+                5 | 6 => {
+                    let mut typed_aux = [0u8; 9];
+                    let mut configured_aux = [0u8; 9];
+                    assert_eq!(
+                        typed.read_line_bytes(&mut typed_aux),
+                        configured.read_line_bytes(&mut configured_aux)
+                    );
+                    assert_eq!(typed_aux, configured_aux);
+                }
+                _ => {
+                    typed.clear();
+                    configured.clear();
+                }
+            }
+
+            assert_eq!(typed.len(), configured.len());
+            assert_eq!(typed.line_count(), configured.line_count());
+        }
+    }
+
+    #[test]
+    fn test_typed_terminator_ignores_config() {
+        let mut storage = [0u8; 16];
+        let mut line_buffer = LineBufferSlice::<usize, NewlineTerminator>::new_typed(
+            &mut storage,
+            &LineBufferConfig::DEFAULT,
+        );
+        line_buffer.push_bytes(b"a\rb\nc\r").unwrap();
+        assert_eq!(line_buffer.line_count(), 1);
+
+        // Changing the configured terminator doesn't trigger a rescan or change the framing.
+        line_buffer.set_config(leak(LineBufferConfig {
+            terminator: Terminator::CarriageReturn,
+            ..*line_buffer.config()
+        }));
+        assert_eq!(line_buffer.line_count(), 1);
+        let mut aux_buffer = [0u8; 16];
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"a\rb");
+        assert!(!line_buffer.has_complete_line());
+
+        let mut line_buffer = LineBuffer::<16, u8, NullTerminator>::new();
+        line_buffer.push_bytes(b"one\0two").unwrap();
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"one");
+    }
+
+    #[test]
+    fn test_read_line_stripping() {
+        let mut line_buffer = LineBuffer::<32>::new();
+        line_buffer.push_bytes(b"AT+CMD:value\nOK\n").unwrap();
+
+        let mut out = [0u8; 32];
+        assert_eq!(
+            line_buffer
+                .read_line_stripping(b"AT+CMD:", &mut out)
+                .unwrap(),
+            Some(5)
+        );
+        assert_eq!(&out[..5], b"value");
+        assert_eq!(line_buffer.line_count(), 1);
+
+        // A prefix matching the whole line leaves nothing to copy.
+        assert_eq!(
+            line_buffer.read_line_stripping(b"OK", &mut out).unwrap(),
+            Some(0)
+        );
+        assert!(line_buffer.is_empty());
+    }
+
+    #[test]
+    fn test_read_line_stripping_mismatch() {
+        let mut line_buffer = LineBuffer::<32>::new();
+        line_buffer.push_bytes(b"ERROR\n").unwrap();
+
+        let mut out = [0u8; 32];
+        assert_eq!(
+            line_buffer
+                .read_line_stripping(b"AT+CMD:", &mut out)
+                .unwrap(),
+            None
+        );
+        // A prefix longer than the line doesn't match either.
+        assert_eq!(
+            line_buffer
+                .read_line_stripping(b"ERROR!", &mut out)
+                .unwrap(),
+            None
+        );
+
+        // The line was left unconsumed.
+        assert_eq!(line_buffer.line_count(), 1);
+        let mut aux_buffer = [0u8; 32];
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..bytes_read], b"ERROR");
+
+        line_buffer
+            .read_line_stripping(b"AT", &mut out)
+            .expect_err("Expected buffer empty error");
+    }
+
+    #[test]
+    fn test_advance_match() {
+        // Overlapping pattern: after "aa" a third `a` keeps two bytes matched.
+        assert_eq!(advance_match(b"aab", 0, b'a', false), 1);
+        assert_eq!(advance_match(b"aab", 1, b'a', false), 2);
+        assert_eq!(advance_match(b"aab", 2, b'a', false), 2);
+        assert_eq!(advance_match(b"aab", 2, b'b', false), 3);
+        assert_eq!(advance_match(b"aab", 1, b'b', false), 0);
+
+        // Falling back through a border: "aba" + `a` still ends with "a".
+        assert_eq!(advance_match(b"abab", 3, b'a', false), 1);
+        assert_eq!(advance_match(b"abab", 2, b'a', false), 3);
+        assert_eq!(advance_match(b"abab", 3, b'b', false), 4);
+
+        // Ignoring case, the border of "aBa" is found whatever the case.
+        assert_eq!(advance_match(b"abab", 3, b'A', true), 1);
+        assert_eq!(advance_match(b"ABab", 3, b'b', true), 4);
+        assert_eq!(advance_match(b"abab", 0, b'A', false), 0);
+    }
+
+    #[test]
+    fn test_read_line_with_case_insensitive_sequence() {
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::SequenceCI(b"end"),
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"0123456789").unwrap();
+        line_buffer.clear();
+        // Split across pushes and the wrap.
+        line_buffer.push_bytes(b"helloEN").unwrap();
+        assert!(!line_buffer.has_complete_line());
+        line_buffer.push_bytes(b"DworldeNd").unwrap();
+        assert_eq!(line_buffer.line_count(), 2);
+        assert!(line_buffer.ends_with_terminator());
+
+        let mut aux_buffer = [0u8; 16];
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(5));
+        assert_eq!(&aux_buffer[..5], b"hello");
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(5));
+        assert_eq!(&aux_buffer[..5], b"world");
+
+        // Only letters are folded.
+        let scanner = LineScanner::new(Terminator::SequenceCI(b"#end\n"));
+        assert_eq!(scanner.next_line(b"a#END\n"), Some((&b"a"[..], 6)));
+        assert_eq!(scanner.next_line(b"a#END\r"), None);
+
+        // A change from the exact sequence to this one is a change of terminator.
+        line_buffer.set_config(leak(LineBufferConfig {
+            terminator: Terminator::Sequence(b"end"),
+            ..*line_buffer.config()
+        }));
+        line_buffer.push_bytes(b"fooEND").unwrap();
+        assert!(!line_buffer.has_complete_line());
+        line_buffer.set_config(leak(LineBufferConfig {
+            terminator: Terminator::SequenceCI(b"end"),
+            ..*line_buffer.config()
+        }));
+        assert!(line_buffer.has_complete_line());
+    }
+
+    #[test]
+    fn test_read_line_with_overlapping_sequence() {
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::Sequence(b"aab"),
+            ..LineBufferConfig::DEFAULT
+        });
+
+        line_buffer.push_bytes(b"aaab").unwrap();
+        assert_eq!(line_buffer.line_count(), 1);
+
+        let mut aux_buffer = [0u8; 16];
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..bytes_read], b"a");
+        assert!(line_buffer.is_empty());
+    }
+
+    /// A sequence terminator pushed one byte at a time, landing across the wrap.
+    #[test]
+    fn test_read_line_with_sequence_split_across_pushes_and_wrap() {
+        let mut line_buffer = LineBuffer::<8>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::Sequence(b"END"),
+            ..LineBufferConfig::DEFAULT
+        });
+        let mut aux_buffer = [0u8; 8];
+
+        line_buffer.push_bytes(b"abcEND").unwrap();
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+
+        for byte in b"xEEN" {
+            line_buffer.push_byte(*byte).unwrap();
+            assert!(!line_buffer.has_complete_line());
+        }
+        line_buffer.push_byte(b'D').unwrap();
+        assert!(line_buffer.has_complete_line());
+        assert!(line_buffer.end < line_buffer.start);
+
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..bytes_read], b"xE");
+        assert!(line_buffer.is_empty());
+    }
+
+    #[test]
+    fn test_count_matching() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        line_buffer.push_bytes(b"abcde\n").unwrap();
+        let mut aux_buffer = [0u8; 8];
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+
+        // Wraps around the end of the storage.
+        line_buffer.push_bytes(b"h\x00i\x7f\x1b!").unwrap();
+        assert!(line_buffer.end < line_buffer.start);
+
+        let non_printable = line_buffer.count_matching(|byte| !(0x20..0x7f).contains(&byte));
+        assert_eq!(non_printable, 3);
+        assert_eq!(line_buffer.count_matching(|_| true), 6);
+        assert_eq!(line_buffer.len(), 6);
+
+        line_buffer.clear();
+        assert_eq!(line_buffer.count_matching(|_| true), 0);
+    }
+
+    #[test]
+    fn test_read_line_vectored() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        line_buffer.push_bytes(b"0123456789\n").unwrap();
+        let mut aux_buffer = [0u8; 16];
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+
+        // The next line wraps around the end of the storage.
+        line_buffer.push_bytes(b"header:body\n").unwrap();
+        assert!(line_buffer.end < line_buffer.start);
+
+        let mut first = [0u8; 3];
+        let mut second = [0u8; 4];
+        let mut third = [0u8; 8];
+        let bytes_read = line_buffer
+            .read_line_vectored(&mut [&mut first, &mut second, &mut third])
+            .unwrap();
+
+        assert_eq!(bytes_read, 11);
+        assert_eq!(&first, b"hea");
+        assert_eq!(&second, b"der:");
+        assert_eq!(&third[..4], b"body");
+        assert!(line_buffer.is_empty());
+    }
+
+    #[test]
+    fn test_read_line_vectored_too_small() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        line_buffer.push_bytes(b"header:body\n").unwrap();
+
+        let mut first = [0u8; 3];
+        let mut second = [0u8; 7];
+        assert!(matches!(
+            line_buffer.read_line_vectored(&mut [&mut first, &mut second]),
+            Err(LineBufferRxError::AuxBufferTooSmall)
+        ));
+        assert_eq!(line_buffer.line_count(), 1);
+
+        // An exact fit succeeds.
+        let mut second = [0u8; 8];
+        assert_eq!(
+            line_buffer
+                .read_line_vectored(&mut [&mut first, &mut second])
+                .unwrap(),
+            11
+        );
+    }
+
+    #[test]
+    fn test_read_line_scatter() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        line_buffer.push_bytes(b"hdr\nheader:body\n").unwrap();
+
+        // Fits entirely in the first buffer.
+        let mut first = [0u8; 6];
+        let mut second = [0u8; 8];
+        assert_eq!(
+            line_buffer
+                .read_line_scatter(&mut first, &mut second)
+                .unwrap(),
+            (3, 0)
+        );
+        assert_eq!(&first[..3], b"hdr");
+
+        // Spills over into the second.
+        assert_eq!(
+            line_buffer
+                .read_line_scatter(&mut first, &mut second)
+                .unwrap(),
+            (6, 5)
+        );
+        assert_eq!(&first, b"header");
+        assert_eq!(&second[..5], b":body");
+        assert!(line_buffer.is_empty());
+    }
+
+    /// A buffer holding a short line, an over-long line that wraps around the end of the
+    /// storage, and another short line.
+    fn with_long_line(line_too_long: LineTooLongPolicy) -> LineBuffer<16> {
+        let mut line_buffer = LineBuffer::<16>::new();
+        line_buffer.push_bytes(b"xxxxxxxxx\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 16]).unwrap();
+        line_buffer.set_config(leak(LineBufferConfig {
+            max_line_length: Some(4),
+            line_too_long,
+            ..*line_buffer.config()
+        }));
+        line_buffer.push_bytes(b"ab\nabcdefghi\nc\n").unwrap();
+        assert!(line_buffer.end < line_buffer.start);
+        line_buffer
+    }
+
+    #[test]
+    fn test_require_utf8() {
+        let mut line_buffer = LineBuffer::<32>::new_with_config(&LineBufferConfig {
+            require_utf8: true,
+            escape: Some(b'\\'),
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"\\\\ab\xffcd\nok\n").unwrap();
+
+        // The escape byte isn't counted, since it isn't part of what is read.
+        let mut aux_buffer = [0u8; 32];
+        for _ in 0..2 {
+            assert_eq!(
+                line_buffer.read_line_bytes(&mut aux_buffer),
+                Err(LineBufferRxError::InvalidUtf8 { valid_up_to: 3 })
+            );
+            assert_eq!(line_buffer.line_count(), 2);
+        }
+        // Byte-level access can still take the line.
+        assert_eq!(line_buffer.consume_line_if(|_, _| true), Ok(true));
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(2));
+
+        line_buffer.set_config(leak(LineBufferConfig {
+            invalid_utf8: InvalidUtf8Policy::Discard,
+            ..*line_buffer.config()
+        }));
+        line_buffer.push_bytes(b"\xe2\x82\nnext\n").unwrap();
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(4));
+        assert_eq!(&aux_buffer[..4], b"next");
+        assert!(line_buffer.is_empty());
+    }
+
+    #[test]
+    fn test_require_utf8_split_across_wrap() {
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            require_utf8: true,
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"0123456789abcd\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 16]).unwrap();
+        // The euro sign's three bytes straddle the end of the storage.
+        line_buffer.push_bytes("€5\n".as_bytes()).unwrap();
+        assert_eq!(line_buffer.as_slices().0, b"\xe2");
+
+        let mut aux_buffer = [0u8; 16];
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(4));
+        assert_eq!(&aux_buffer[..4], "€5".as_bytes());
+    }
+
+    #[test]
+    fn test_truncate_at_char_boundary() {
+        // Each character starts at byte 3 and is cut just before its last byte.
+        for (text, max) in [("abc\u{e9}z", 4), ("abc\u{20ac}z", 5), ("abc\u{1f600}z", 6)] {
+            for (truncate_at_char_boundary, kept) in [(false, max), (true, 3)] {
+                let mut line_buffer = LineBuffer::<16>::new_with_config(leak(LineBufferConfig {
+                    max_line_length: Some(max),
+                    line_too_long: LineTooLongPolicy::Truncate,
+                    truncate_at_char_boundary,
+                    ..LineBufferConfig::DEFAULT
+                }));
+                line_buffer.push_bytes(text.as_bytes()).unwrap();
+                line_buffer.push_bytes(b"\n").unwrap();
+
+                let mut aux_buffer = [0u8; 16];
+                let info = line_buffer.read_line_info(&mut aux_buffer).unwrap();
+                assert_eq!(
+                    info,
+                    LineInfo {
+                        len: kept,
+                        truncated: true
+                    }
+                );
+                assert_eq!(
+                    core::str::from_utf8(&aux_buffer[..kept]).is_ok(),
+                    truncate_at_char_boundary
+                );
+            }
+        }
+
+        // A cut that already falls between characters keeps everything it can.
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            max_line_length: Some(5),
+            line_too_long: LineTooLongPolicy::Truncate,
+            truncate_at_char_boundary: true,
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer
+            .push_bytes("abc\u{e9}z\u{20ac}\n".as_bytes())
+            .unwrap();
+        let mut aux_buffer = [0u8; 16];
+        assert_eq!(
+            line_buffer.read_line_info(&mut aux_buffer).unwrap(),
+            LineInfo {
+                len: 5,
+                truncated: true
+            }
+        );
+        assert_eq!(&aux_buffer[..5], "abc\u{e9}".as_bytes());
+    }
+
+    #[test]
+    fn test_strip_trailing_cr() {
+        let mut aux_buffer = [0u8; 16];
+        for (strip, expected) in [(false, &b"hi\r"[..]), (true, b"hi")] {
+            let mut line_buffer = LineBuffer::<16>::new_with_config(leak(LineBufferConfig {
+                strip_trailing_cr: strip,
+                ..LineBufferConfig::DEFAULT
+            }));
+            line_buffer.push_bytes(b"hi\r\nthere\n").unwrap();
+            let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+            assert_eq!(&aux_buffer[..bytes_read], expected);
+            let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+            assert_eq!(&aux_buffer[..bytes_read], b"there");
+            assert!(line_buffer.is_empty());
+        }
+
+        // Other terminators, a lone `\r` and an escaped `\r` are left alone.
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::NULL,
+            strip_trailing_cr: true,
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"hi\r\0").unwrap();
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(3));
+
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            escape: Some(b'\\'),
+            strip_trailing_cr: true,
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"\r\na\\\r\nb\\\\\r\n").unwrap();
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(0));
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(2));
+        assert_eq!(&aux_buffer[..2], b"a\r");
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(2));
+        assert_eq!(&aux_buffer[..2], b"b\\");
+    }
+
+    #[test]
+    fn test_has_dangling_terminator() {
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturnNewline,
+            escape: Some(b'\\'),
+            ..LineBufferConfig::DEFAULT
+        });
+        assert!(!line_buffer.has_dangling_terminator());
+        line_buffer.push_bytes(b"hi\r").unwrap();
+        assert!(line_buffer.has_dangling_terminator());
+        line_buffer.push_byte(b'\r').unwrap();
+        assert!(line_buffer.has_dangling_terminator());
+        line_buffer.push_byte(b'\n').unwrap();
+        assert!(!line_buffer.has_dangling_terminator());
+        assert!(line_buffer.has_complete_line());
+
+        // An escaped `\r` starts no terminator.
+        line_buffer.push_bytes(b"a\\\r").unwrap();
+        assert!(!line_buffer.has_dangling_terminator());
+
+        // A change of terminator is taken into account.
+        line_buffer.set_config(leak(LineBufferConfig {
+            terminator: Terminator::Newline,
+            ..*line_buffer.config()
+        }));
+        assert!(!line_buffer.has_dangling_terminator());
+        line_buffer.set_config(leak(LineBufferConfig {
+            terminator: Terminator::Sequence(b"END"),
+            ..*line_buffer.config()
+        }));
+        line_buffer.push_bytes(b"EN").unwrap();
+        assert!(line_buffer.has_dangling_terminator());
+        line_buffer.push_byte(b'D').unwrap();
+        assert!(!line_buffer.has_dangling_terminator());
+    }
+
+    #[test]
+    fn test_peek_next_terminator() {
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            strip_trailing_cr: true,
+            ..LineBufferConfig::DEFAULT
+        });
+        assert_eq!(line_buffer.peek_next_terminator(), None);
+        line_buffer.push_bytes(b"a\nb\r\n\r\nc\r").unwrap();
+
+        let mut aux_buffer = [0u8; 16];
+        let mut endings = [None; 4];
+        for ending in endings.iter_mut() {
+            *ending = line_buffer.peek_next_terminator();
+            let _ = line_buffer.read_line_bytes(&mut aux_buffer);
+        }
+        assert_eq!(
+            endings,
+            [
+                Some(Terminator::Newline),
+                Some(Terminator::CarriageReturnNewline),
+                Some(Terminator::CarriageReturnNewline),
+                None,
+            ]
+        );
+
+        // Without stripping, the `\r` is part of the line.
+        line_buffer.set_config(leak(LineBufferConfig {
+            strip_trailing_cr: false,
+            ..*line_buffer.config()
+        }));
+        line_buffer.push_byte(b'\n').unwrap();
+        assert_eq!(
+            line_buffer.peek_next_terminator(),
+            Some(Terminator::Newline)
+        );
+
+        let mut line_buffer = LineBuffer::<16, usize, CrlfTerminator>::new();
+        line_buffer.push_bytes(b"AT\r\n").unwrap();
+        assert_eq!(
+            line_buffer.peek_next_terminator(),
+            Some(Terminator::CarriageReturnNewline)
+        );
+    }
+
+    #[test]
+    fn test_read_line_bytes_kind() {
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            strip_trailing_cr: true,
+            max_line_length: Some(2),
+            line_too_long: LineTooLongPolicy::Truncate,
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"abc\r\nd\n\r\ne").unwrap();
+
+        let mut aux_buffer = [0u8; 16];
+        assert_eq!(
+            line_buffer.read_line_bytes_kind(&mut aux_buffer),
+            Ok((2, Terminator::CarriageReturnNewline))
+        );
+        assert_eq!(&aux_buffer[..2], b"ab");
+        assert_eq!(
+            line_buffer.read_line_bytes_kind(&mut aux_buffer),
+            Ok((1, Terminator::Newline))
+        );
+        assert_eq!(
+            line_buffer.read_line_bytes_kind(&mut [0u8; 0]),
+            Ok((0, Terminator::CarriageReturnNewline))
+        );
+        assert_eq!(
+            line_buffer.read_line_bytes_kind(&mut aux_buffer),
+            Err(LineBufferRxError::NoLines { pending: 1 })
+        );
+
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturn,
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"a\n\r").unwrap();
+        assert_eq!(
+            line_buffer.read_line_bytes_kind(&mut aux_buffer),
+            Ok((2, Terminator::CarriageReturn))
+        );
+    }
+
+    #[test]
+    fn test_skip_comment_lines() {
+        let config = LineBufferConfig::builder()
+            .escape(Some(b'\\'))
+            .strip_trailing_cr(true)
+            .skip_empty_lines(true)
+            .skip_comment_prefixes(Some(b"#;"))
+            .max_line_length(Some(8))
+            .build()
+            .unwrap();
+        let mut line_buffer = LineBuffer::<64>::new_with_config(leak(config));
+        line_buffer
+            .push_bytes(b"# a long header comment\n\nkey=1\r\n  ; note\r\n\r\n\\#=2\n \n")
+            .unwrap();
+
+        // Comments and empty lines go, however long; an escaped `#` and a space stay.
+        let mut aux_buffer = [0u8; 64];
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"key=1");
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"#=2");
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b" ");
+
+        // Only comments left, with one wrapping around the end of the storage.
+        line_buffer
+            .push_bytes(b"\n# 0123456789012345678901234567\n;x")
+            .unwrap();
+        assert!(!line_buffer.as_slices().1.is_empty());
+        assert_eq!(
+            line_buffer.read_line_bytes(&mut aux_buffer),
+            Err(LineBufferRxError::NoLines { pending: 2 })
+        );
+        line_buffer.push_bytes(b"\nkey=3\n").unwrap();
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"key=3");
+
+        // Without `skip_empty_lines`, empty lines are read as usual.
+        line_buffer.set_config(leak(LineBufferConfig {
+            skip_empty_lines: false,
+            ..*line_buffer.config()
+        }));
+        line_buffer.push_bytes(b"#\n\n").unwrap();
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(0));
+        assert!(line_buffer.is_empty());
+    }
+
+    #[cfg(feature = "stats")]
+    #[test]
+    fn test_skipped_lines_stats() {
+        let mut line_buffer = LineBuffer::<32>::new_with_config(&LineBufferConfig {
+            skip_empty_lines: true,
+            skip_comment_prefixes: Some(b"#"),
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"# note\n\nkey=1\n").unwrap();
+        let mut aux_buffer = [0u8; 32];
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+
+        // Skipped lines aren't lost, so they stay out of the dropped bytes.
+        let stats = line_buffer.stats();
+        assert_eq!((stats.bytes_skipped, stats.lines_skipped), (8, 2));
+        assert_eq!((stats.bytes_read, stats.lines_read), (6, 1));
+        assert_eq!(line_buffer.dropped_bytes(), 0);
+    }
+
+    #[test]
+    fn test_skip_empty_lines_with_leave_terminator() {
+        let mut line_buffer = LineBuffer::<32>::new_with_config(&LineBufferConfig {
+            skip_empty_lines: true,
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"key\n\nvalue\n").unwrap();
+
+        // The terminator left behind isn't an empty line to skip, so it stays put.
+        let mut aux_buffer = [0u8; 32];
+        assert_eq!(
+            line_buffer.read_line_leave_terminator(&mut aux_buffer),
+            Ok(3)
+        );
+        assert_eq!(
+            line_buffer.read_line_leave_terminator(&mut aux_buffer),
+            Ok(0)
+        );
+        assert_eq!(
+            line_buffer.read_line_leave_terminator(&mut aux_buffer),
+            Ok(0)
+        );
+        assert_eq!(line_buffer.line_count(), 3);
+
+        // Reading it as a line consumes it, and the real empty line after it is skipped.
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(0));
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"value");
+        assert!(line_buffer.is_empty());
+    }
+
+    #[test]
+    fn test_strip_trailing_cr_with_line_too_long() {
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            strip_trailing_cr: true,
+            max_line_length: Some(2),
+            line_too_long: LineTooLongPolicy::Truncate,
+            ..LineBufferConfig::DEFAULT
+        });
+        let mut aux_buffer = [0u8; 16];
+        line_buffer.push_bytes(b"hi\r\nhello\r\n").unwrap();
+        assert_eq!(
+            line_buffer.read_line_info(&mut aux_buffer),
+            Ok(LineInfo {
+                len: 2,
+                truncated: false
+            })
+        );
+        assert_eq!(
+            line_buffer.read_line_info(&mut aux_buffer),
+            Ok(LineInfo {
+                len: 2,
+                truncated: true
+            })
+        );
+        assert_eq!(&aux_buffer[..2], b"he");
+        assert!(line_buffer.is_empty());
+    }
+
+    #[test]
+    fn test_line_too_long_truncate() {
+        let mut line_buffer = with_long_line(LineTooLongPolicy::Truncate);
+        let mut out = [0u8; 16];
+
+        let info = line_buffer.read_line_info(&mut out).unwrap();
+        assert_eq!((&out[..info.len], info.truncated), (&b"ab"[..], false));
+        // Too small an output buffer leaves the truncated line in place.
+        assert!(matches!(
+            line_buffer.read_line_info(&mut [0u8; 3]),
+            Err(LineBufferRxError::AuxBufferTooSmall)
+        ));
+        assert_eq!(line_buffer.len(), 7);
+        let info = line_buffer.read_line_info(&mut out).unwrap();
+        assert_eq!((&out[..info.len], info.truncated), (&b"abcd"[..], true));
+        let info = line_buffer.read_line_info(&mut out).unwrap();
+        assert_eq!((&out[..info.len], info.truncated), (&b"c"[..], false));
+        assert!(line_buffer.is_empty());
+    }
+
+    #[test]
+    fn test_line_too_long_discard() {
+        let mut line_buffer = with_long_line(LineTooLongPolicy::Discard);
+        let mut out = [0u8; 16];
+
+        let len = line_buffer.read_line_bytes(&mut out).unwrap();
+        assert_eq!(&out[..len], b"ab");
+        let len = line_buffer.read_line_bytes(&mut out).unwrap();
+        assert_eq!(&out[..len], b"c");
+        assert!(line_buffer.is_empty());
+
+        // Once dropped, the terminator of the long line doesn't leave a stray empty line.
+        line_buffer.push_bytes(b"abcdef\n").unwrap();
+        assert_eq!(
+            line_buffer.read_line_bytes(&mut out),
+            Err(LineBufferRxError::NoLines { pending: 0 })
+        );
+    }
+
+    #[test]
+    fn test_line_too_long_error() {
+        let mut line_buffer = with_long_line(LineTooLongPolicy::Error);
+        let mut out = [0u8; 16];
+
+        let len = line_buffer.read_line_bytes(&mut out).unwrap();
+        assert_eq!(&out[..len], b"ab");
+        assert_eq!(
+            line_buffer.read_line_bytes(&mut out),
+            Err(LineBufferRxError::LineTooLong { len: 9 })
+        );
+        let len = line_buffer.read_line_bytes(&mut out).unwrap();
+        assert_eq!(&out[..len], b"c");
+
+        // A batch stops in front of the long line, which the next batch reports.
+        let mut line_buffer = with_long_line(LineTooLongPolicy::Error);
+        let info = line_buffer.read_lines(&mut out, 3).unwrap();
+        assert_eq!(info.lines, 1);
+        assert_eq!(
+            line_buffer.read_lines(&mut out, 3),
+            Err(LineBufferRxError::LineTooLong { len: 9 })
+        );
+        assert_eq!(line_buffer.read_lines(&mut out, 3).unwrap().lines, 1);
+    }
+
+    #[test]
+    fn test_line_too_long_truncate_escape() {
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            escape: Some(b'\\'),
+            max_line_length: Some(3),
+            line_too_long: LineTooLongPolicy::Truncate,
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"ab\\\ncd\nef\n").unwrap();
+
+        // The cut falls between the escape byte and the newline it escapes, so the escape goes too.
+        line_buffer
+            .with_line(|first, second| {
+                assert_eq!(first, b"ab");
+                assert!(second.is_empty());
+            })
+            .unwrap();
+        let mut out = [0u8; 16];
+        let len = line_buffer.read_line_bytes(&mut out).unwrap();
+        assert_eq!(&out[..len], b"ef");
+    }
+
+    #[test]
+    fn test_read_until_subsequence() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        let mut out = [0u8; 8];
+        assert_eq!(
+            line_buffer.read_until_subsequence(b"||", &mut out),
+            Err(LineBufferRxError::BufferEmpty)
+        );
+
+        // Half of the needle isn't enough.
+        line_buffer.push_bytes(b"a\nb|").unwrap();
+        assert_eq!(
+            line_buffer.read_until_subsequence(b"||", &mut out),
+            Err(LineBufferRxError::NoLines { pending: 4 })
+        );
+        line_buffer.push_bytes(b"|c").unwrap();
+        assert_eq!(
+            line_buffer.read_until_subsequence(b"||", &mut [0u8; 2]),
+            Err(LineBufferRxError::AuxBufferTooSmall)
+        );
+        assert_eq!(line_buffer.len(), 6);
+
+        // The terminator is copied like any other byte, and the line it ended is gone with it.
+        assert_eq!(line_buffer.read_until_subsequence(b"||", &mut out), Ok(3));
+        assert_eq!(&out[..3], b"a\nb");
+        assert_eq!(line_buffer.len(), 1);
+        assert_eq!(line_buffer.line_count(), 0);
+
+        // Now the needle straddles the end of the storage.
+        line_buffer.push_bytes(b"d|").unwrap();
+        assert_eq!(line_buffer.end, 0);
+        line_buffer.push_bytes(b"|ef").unwrap();
+        assert_eq!(line_buffer.read_until_subsequence(b"||", &mut out), Ok(2));
+        assert_eq!(&out[..2], b"cd");
+        let mut rest = [0u8; 8];
+        assert_eq!(line_buffer.peek_all(&mut rest), 2);
+        assert_eq!(&rest[..2], b"ef");
+    }
+
+    #[test]
+    fn test_read_header_block() {
+        const RESPONSE: &[u8] = b"HTTP/1.0 200 OK\r\nContent-Length: 2\r\n\r\nhi";
+        let mut line_buffer = LineBuffer::<64, u8>::new();
+        line_buffer.push_bytes(&[b'x'; 50]).unwrap();
+        line_buffer.discard_to(50);
+
+        // One byte at a time, across the end of the storage.
+        let mut out = [0u8; 64];
+        let header_len = RESPONSE.len() - 2;
+        for (i, byte) in RESPONSE[..header_len].iter().enumerate() {
+            assert_eq!(
+                line_buffer.read_header_block(&mut out),
+                match i {
+                    0 => Err(LineBufferRxError::BufferEmpty),
+                    _ => Err(LineBufferRxError::NoLines { pending: i }),
+                }
+            );
+            line_buffer.push_byte(*byte).unwrap();
+        }
+        assert!(!line_buffer.as_slices().1.is_empty());
+        assert_eq!(
+            line_buffer.read_header_block(&mut out[..10]),
+            Err(LineBufferRxError::AuxBufferTooSmall)
+        );
+        assert_eq!(line_buffer.len(), header_len);
+        let len = line_buffer.read_header_block(&mut out).unwrap();
+        assert_eq!(&out[..len], &RESPONSE[..header_len - 2]);
+        assert!(line_buffer.is_empty());
+
+        // The delimiter split 3+1 across pushes, then the body read byte-exactly.
+        line_buffer.push_bytes(&RESPONSE[..header_len - 1]).unwrap();
+        assert!(line_buffer.read_header_block(&mut out).is_err());
+        line_buffer.push_bytes(&RESPONSE[header_len - 1..]).unwrap();
+        let len = line_buffer.read_header_block(&mut out).unwrap();
+        assert_eq!(&out[..len], &RESPONSE[..header_len - 2]);
+        line_buffer.read_exact_bytes(2, &mut out).unwrap();
+        assert_eq!(&out[..2], b"hi");
+
+        // An empty line right away is an empty block.
+        line_buffer.push_bytes(b"\r\nnext").unwrap();
+        assert_eq!(line_buffer.read_header_block(&mut out), Ok(0));
+        assert_eq!(line_buffer.len(), 4);
+    }
+
+    #[test]
+    fn test_read_exact_bytes() {
+        let mut line_buffer = LineBuffer::<512, u16>::new();
+        line_buffer.push_bytes(&[b'x'; 400]).unwrap();
+        line_buffer.discard_to(400);
+
+        let mut body = [0u8; 300];
+        for (i, byte) in body.iter_mut().enumerate() {
+            *byte = (i * 7) as u8;
+        }
+        assert!(body.iter().filter(|&&byte| byte == b'\n').count() > 1);
+        line_buffer.push_bytes(b"LEN 300\n").unwrap();
+        line_buffer.push_bytes(&body[..150]).unwrap();
+
+        let mut aux_buffer = [0u8; 512];
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"LEN 300");
+        assert_eq!(
+            line_buffer.read_exact_bytes(300, &mut aux_buffer),
+            Err(LineBufferRxError::NotEnoughData { available: 150 })
+        );
+        assert_eq!(line_buffer.available(), 150);
+
+        // The rest of the body, and the next line, across the end of the storage.
+        line_buffer.push_bytes(&body[150..]).unwrap();
+        line_buffer.push_bytes(b"OK\n").unwrap();
+        assert!(!line_buffer.as_slices().1.is_empty());
+        assert_eq!(
+            line_buffer.read_exact_bytes(300, &mut aux_buffer[..299]),
+            Err(LineBufferRxError::AuxBufferTooSmall)
+        );
+        assert_eq!(line_buffer.available(), 303);
+        line_buffer.read_exact_bytes(300, &mut aux_buffer).unwrap();
+        assert_eq!(aux_buffer[..300], body);
+
+        assert_eq!(line_buffer.line_count(), 1);
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"OK");
+        assert_eq!(line_buffer.read_exact_bytes(0, &mut []), Ok(()));
+    }
+
+    #[test]
+    fn test_visit_lines() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        line_buffer.push_bytes(b"xxxx\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 16]).unwrap();
+        line_buffer.push_bytes(b"a\nb\nccc\nd\ne\nf").unwrap();
+
+        // The third line, which wraps, fails.
+        let mut seen = 0;
+        let result = line_buffer.visit_lines(|first, second| {
+            seen += 1;
+            match first.len() + second.len() {
+                3 => Err(seen),
+                _ => Ok(()),
+            }
+        });
+        assert_eq!(
+            result,
+            Err(VisitError {
+                error: 3,
+                handled: 2
+            })
+        );
+        assert_eq!(line_buffer.line_count(), 3);
+        let mut aux_buffer = [0u8; 16];
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(3));
+        assert_eq!(&aux_buffer[..3], b"ccc");
+
+        // Everything else goes through, leaving the partial line.
+        assert_eq!(line_buffer.visit_lines(|_, _| Ok::<(), ()>(())), Ok(2));
+        assert_eq!(line_buffer.len(), 1);
+        assert_eq!(line_buffer.visit_lines(|_, _| Err(())), Ok(0));
+    }
+
+    #[test]
+    fn test_read_line_leave_terminator() {
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturnNewline,
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"xxxxxxxxxx\r\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 16]).unwrap();
+        // The first line wraps and so does its terminator.
+        line_buffer.push_bytes(b"abcd\r\nef\r\ng").unwrap();
+
+        let mut aux_buffer = [0u8; 16];
+        assert_eq!(
+            line_buffer.read_line_leave_terminator(&mut aux_buffer),
+            Ok(4)
+        );
+        assert_eq!(&aux_buffer[..4], b"abcd");
+        assert_eq!(line_buffer.len(), 7);
+        assert_eq!(line_buffer.line_count(), 2);
+        assert_eq!(line_buffer.peek_range(0, &mut aux_buffer[..2]), 2);
+        assert_eq!(&aux_buffer[..2], b"\r\n");
+
+        // Stuck on the terminator until a normal read takes it.
+        assert_eq!(
+            line_buffer.read_line_leave_terminator(&mut aux_buffer),
+            Ok(0)
+        );
+        assert_eq!(line_buffer.len(), 7);
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(0));
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(2));
+        assert_eq!(&aux_buffer[..2], b"ef");
+        assert_eq!(
+            line_buffer.read_line_leave_terminator(&mut aux_buffer),
+            Err(LineBufferRxError::NoLines { pending: 1 })
+        );
+        assert_eq!(line_buffer.len(), 1);
+    }
+
+    #[test]
+    fn test_make_contiguous() {
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            escape: Some(b'\\'),
+            ..LineBufferConfig::DEFAULT
+        });
+        assert!(line_buffer.make_contiguous().is_empty());
+
+        line_buffer.push_bytes(b"0123456789\nab").unwrap();
+        let mut aux_buffer = [0u8; 16];
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        line_buffer.push_bytes(b"c\\\nd\nefgh").unwrap();
+        assert!(!line_buffer.as_slices().1.is_empty());
+        assert_eq!(line_buffer.line_count(), 1);
+
+        assert_eq!(line_buffer.make_contiguous(), b"abc\\\nd\nefgh");
+        assert!(line_buffer.as_slices().1.is_empty());
+        assert_eq!(line_buffer.line_count(), 1);
+        // Already contiguous, so nothing moves.
+        assert_eq!(line_buffer.make_contiguous(), b"abc\\\nd\nefgh");
+
+        // Carry on pushing and reading as normal.
+        line_buffer.push_bytes(b"\nxyz").unwrap();
+        assert_eq!(line_buffer.len(), 15);
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(5));
+        assert_eq!(&aux_buffer[..5], b"abc\nd");
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(4));
+        assert_eq!(&aux_buffer[..4], b"efgh");
+        line_buffer.push_bytes(b"\n").unwrap();
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(3));
+        assert_eq!(&aux_buffer[..3], b"xyz");
+        assert!(line_buffer.is_empty());
+    }
+
+    #[test]
+    fn test_make_contiguous_keeps_rewind() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        line_buffer.push_bytes(b"abcde\n").unwrap();
+        let mut aux_buffer = [0u8; 8];
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        line_buffer.push_bytes(b"fg").unwrap();
+        line_buffer.make_contiguous();
+
+        line_buffer.rewind().unwrap();
+        line_buffer.push_bytes(b"\n").unwrap_err();
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(5));
+        assert_eq!(&aux_buffer[..5], b"abcde");
+        assert_eq!(line_buffer.make_contiguous(), b"fg");
+    }
+
+    #[test]
+    fn test_next_line_str() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        line_buffer.push_bytes("héllo\nwörld\n".as_bytes()).unwrap();
+
+        // Contiguous, so the line is borrowed where it is.
+        let storage = line_buffer.buffer.as_ptr();
+        let line = line_buffer.next_line_str().unwrap();
+        assert_eq!(line.as_str(), "héllo");
+        assert_eq!(line.as_ptr(), storage);
+        line.consume();
+
+        // Dropping the line without consuming it leaves it buffered.
+        assert_eq!(&*line_buffer.next_line_str().unwrap(), "wörld");
+        assert_eq!(line_buffer.line_count(), 1);
+        line_buffer.next_line_str().unwrap().consume();
+        assert_eq!(
+            line_buffer.next_line_str().err(),
+            Some(LineBufferRxError::BufferEmpty)
+        );
+    }
+
+    #[test]
+    fn test_next_line_str_wrapped() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        line_buffer.push_bytes(b"0123456789\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 16]).unwrap();
+        line_buffer.push_bytes("wräpped\nmore".as_bytes()).unwrap();
+        assert!(!line_buffer.as_slices().1.is_empty());
+
+        let line = line_buffer.next_line_str().unwrap();
+        assert_eq!(line.as_str(), "wräpped");
+        line.consume();
+        assert!(line_buffer.as_slices().1.is_empty());
+        line_buffer.push_bytes(b"\n").unwrap();
+        assert_eq!(line_buffer.next_line_str().as_deref(), Ok("more"));
+    }
+
+    #[test]
+    fn test_next_line_str_invalid_utf8() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        line_buffer.push_bytes(b"ok\n\xffbad\n").unwrap();
+        line_buffer.next_line_str().unwrap().consume();
+
+        assert_eq!(
+            line_buffer.next_line_str().err(),
+            Some(LineBufferRxError::InvalidUtf8 { valid_up_to: 0 })
+        );
+        let mut aux_buffer = [0u8; 16];
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(4));
+        assert_eq!(&aux_buffer[..4], b"\xffbad");
+    }
+
+    #[test]
+    fn test_read_line_chunk() {
+        let mut line_buffer = LineBuffer::<128>::new();
+        let line: [u8; 100] = core::array::from_fn(|i| b'a' + (i % 26) as u8);
+        line_buffer.push_bytes(&line).unwrap();
+        line_buffer.push_bytes(b"\nnext\n").unwrap();
+
+        let mut aux_buffer = [0u8; 16];
+        let mut read = [0u8; 100];
+        for call in 0..7 {
+            let chunk = line_buffer.read_line_chunk(&mut aux_buffer).unwrap();
+            read[call * 16..call * 16 + chunk.len].copy_from_slice(&aux_buffer[..chunk.len]);
+            assert_eq!(
+                chunk,
+                LineChunk {
+                    len: if call < 6 { 16 } else { 4 },
+                    finished: call == 6,
+                }
+            );
+            if call == 2 {
+                // The rest of the line can't be read as a line of its own.
+                assert_eq!(
+                    line_buffer.read_line_bytes(&mut [0u8; 128]),
+                    Err(LineBufferRxError::MidLine)
+                );
+                assert_eq!(line_buffer.line_count(), 2);
+            }
+        }
+        assert_eq!(read, line);
+        let mut next = [0u8; 128];
+        assert_eq!(line_buffer.read_line_bytes(&mut next), Ok(4));
+        assert_eq!(&next[..4], b"next");
+    }
+
+    #[test]
+    fn test_read_line_chunk_escape() {
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            escape: Some(b'\\'),
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"ab\\\ncd\n").unwrap();
+
+        let mut aux_buffer = [0u8; 3];
+        assert_eq!(
+            line_buffer.read_line_chunk(&mut aux_buffer),
+            Ok(LineChunk {
+                len: 3,
+                finished: false
+            })
+        );
+        assert_eq!(&aux_buffer, b"ab\n");
+        assert_eq!(line_buffer.len(), 3);
+        assert_eq!(
+            line_buffer.read_line_chunk(&mut aux_buffer),
+            Ok(LineChunk {
+                len: 2,
+                finished: true
+            })
+        );
+        assert_eq!(&aux_buffer[..2], b"cd");
+        assert!(line_buffer.is_empty());
+
+        // Clearing gives up on the line part way through.
+        line_buffer.push_bytes(b"efgh\n").unwrap();
+        line_buffer.read_line_chunk(&mut aux_buffer).unwrap();
+        line_buffer.clear();
+        line_buffer.push_bytes(b"ij\n").unwrap();
+        assert_eq!(line_buffer.read_line_bytes(&mut [0u8; 16]), Ok(2));
+    }
+
+    #[test]
+    fn test_consume_line_if() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        line_buffer.push_bytes(b"0123456789\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 16]).unwrap();
+        line_buffer.push_bytes(b"skip me\nkeep\n").unwrap();
+
+        // The first line wraps, so the predicate sees it in two pieces.
+        let is_skip = |first: &[u8], second: &[u8]| {
+            let mut line = [0u8; 16];
+            line[..first.len()].copy_from_slice(first);
+            line[first.len()..first.len() + second.len()].copy_from_slice(second);
+            line.starts_with(b"skip")
+        };
+        assert_eq!(line_buffer.consume_line_if(is_skip), Ok(true));
+        assert_eq!(line_buffer.consume_line_if(is_skip), Ok(false));
+        assert_eq!(line_buffer.consume_line_if(is_skip), Ok(false));
+
+        let mut aux_buffer = [0u8; 16];
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(4));
+        assert_eq!(&aux_buffer[..4], b"keep");
+
+        line_buffer.push_bytes(b"partial").unwrap();
+        assert_eq!(
+            line_buffer.consume_line_if(|_, _| true),
+            Err(LineBufferRxError::NoLines { pending: 7 })
+        );
+    }
+
+    #[test]
+    fn test_read_line_str_lossy() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        line_buffer.push_bytes(b"0123456789\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 16]).unwrap();
+        // Two stray bytes, then a character split by the wrap.
+        line_buffer.push_bytes(b"a\xffb\x80\xc3\xa9\n").unwrap();
+        assert_eq!(line_buffer.as_slices().1, b"\xa9\n");
+
+        let mut aux_buffer = [0u8; 16];
+        assert_eq!(
+            line_buffer.read_line_str_lossy(&mut aux_buffer),
+            Ok("a\u{FFFD}b\u{FFFD}é")
+        );
+        assert!(line_buffer.is_empty());
+
+        // Room for the raw bytes, but not once they're replaced.
+        line_buffer.push_bytes(b"ab\xff\xfe\n").unwrap();
+        assert_eq!(
+            line_buffer.read_line_str_lossy(&mut aux_buffer[..6]),
+            Err(LineBufferRxError::AuxBufferTooSmall)
+        );
+        assert_eq!(line_buffer.len(), 5);
+        assert_eq!(
+            line_buffer.read_line_str_lossy(&mut aux_buffer[..8]),
+            Ok("ab\u{FFFD}\u{FFFD}")
+        );
+    }
+
+    /// `copy_lossy` agrees with `String::from_utf8_lossy`, which goes by the same rules.
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_copy_lossy_matches_from_utf8_lossy() {
+        let mut rng = Rng(0x1eaf_cafe);
+        let edges = [
+            0x41, 0x7f, 0x80, 0x8f, 0x90, 0x9f, 0xa0, 0xbf, 0xc0, 0xc1, 0xc2, 0xdf, 0xe0, 0xe1,
+            0xec, 0xed, 0xee, 0xef, 0xf0, 0xf1, 0xf3, 0xf4, 0xf5, 0xff,
+        ];
+        for _ in 0..20000 {
+            let len = rng.below(8);
+            let bytes: [u8; 8] = core::array::from_fn(|_| edges[rng.below(edges.len())]);
+            let bytes = &bytes[..len];
+
+            let expected = alloc::string::String::from_utf8_lossy(bytes);
+            let mut out = [0u8; 24];
+            let copied = copy_lossy(bytes.iter().copied(), &mut out).unwrap();
+            assert_eq!(&out[..copied], expected.as_bytes(), "{bytes:x?}");
+            assert_eq!(
+                copy_lossy(bytes.iter().copied(), &mut out[..expected.len().max(1) - 1]),
+                if expected.is_empty() { Some(0) } else { None }
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_line_status() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        let mut out = [0u8; 16];
+        line_buffer.push_bytes(b"ab\ncd\n").unwrap();
+        assert_eq!(line_buffer.read_line_status(&mut out), Ok((2, true)));
+        assert_eq!(&out[..2], b"ab");
+        assert_eq!(line_buffer.read_line_status(&mut out), Ok((2, false)));
+        assert_eq!(&out[..2], b"cd");
+        assert!(line_buffer.is_empty());
+
+        // A partial line left behind doesn't count as more.
+        line_buffer.push_bytes(b"ef\ngh").unwrap();
+        assert_eq!(line_buffer.read_line_status(&mut out), Ok((2, false)));
+        assert_eq!(line_buffer.len(), 2);
+        assert_eq!(
+            line_buffer.read_line_status(&mut out),
+            Err(LineBufferRxError::NoLines { pending: 2 })
+        );
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn test_read_line_to() {
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            escape: Some(b'\\'),
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"xxxxxxxxxxx\n").unwrap();
+        line_buffer.read_line_bytes(&mut [0u8; 16]).unwrap();
+
+        // A character split across the end of the storage, and an escaped terminator.
+        line_buffer.push_bytes("°C\\\nok\n".as_bytes()).unwrap();
+        assert!(line_buffer.end.get() < line_buffer.start.get());
+        let mut line = heapless::String::<16>::new();
+        assert_eq!(line_buffer.read_line_to(&mut line), Ok(6));
+        assert_eq!(line, "°C\nok");
+        assert!(line_buffer.is_empty());
+
+        // A sink without room leaves the line buffered.
+        line_buffer.push_bytes(b"abcdef\n").unwrap();
+        let mut line = heapless::String::<4>::new();
+        assert_eq!(
+            line_buffer.read_line_to(&mut line),
+            Err(LineBufferRxError::AuxBufferTooSmall)
+        );
+        assert_eq!(line_buffer.len(), 7);
+        line_buffer.clear();
+
+        assert_eq!(
+            line_buffer.read_line_to(&mut line),
+            Err(LineBufferRxError::BufferEmpty)
+        );
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn test_read_line_to_invalid_utf8() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        let mut line = heapless::String::<16>::new();
+        // A stray continuation byte, then a character cut short by the terminator.
+        line_buffer.push_bytes(b"ab\x80cd\nx\xc2\nok\n").unwrap();
+        for valid_up_to in [2, 1] {
+            assert_eq!(
+                line_buffer.read_line_to(&mut line),
+                Err(LineBufferRxError::InvalidUtf8 { valid_up_to })
+            );
+            assert!(line.is_empty());
+            line_buffer.read_line_bytes(&mut [0u8; 16]).unwrap();
+        }
+        assert_eq!(line_buffer.read_line_to(&mut line), Ok(2));
+        assert_eq!(line, "ok");
+    }
+
+    #[test]
+    fn test_read_line_scatter_too_small() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        line_buffer.push_bytes(b"header:body\n").unwrap();
+
+        assert!(matches!(
+            line_buffer.read_line_scatter(&mut [0u8; 6], &mut [0u8; 4]),
+            Err(LineBufferRxError::AuxBufferTooSmall)
+        ));
+        assert_eq!(line_buffer.line_count(), 1);
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn test_read_line_vec() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        line_buffer.push_bytes(b"OK\nERROR\n").unwrap();
+
+        let line = line_buffer.read_line_vec::<8>().unwrap();
+        assert_eq!(&line[..], b"OK");
+        let line = line_buffer.read_line_vec::<5>().unwrap();
+        assert_eq!(&line[..], b"ERROR");
+        assert!(matches!(
+            line_buffer.read_line_vec::<8>(),
+            Err(LineBufferRxError::BufferEmpty)
+        ));
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn test_read_line_vec_too_small() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        line_buffer.push_bytes(b"header:body\n").unwrap();
+
+        assert!(matches!(
+            line_buffer.read_line_vec::<4>(),
+            Err(LineBufferRxError::AuxBufferTooSmall)
+        ));
+        assert_eq!(line_buffer.line_count(), 1);
+        assert_eq!(
+            &line_buffer.read_line_vec::<11>().unwrap()[..],
+            b"header:body"
+        );
+    }
+
+    #[test]
+    fn test_poll_slowly_arriving_line() {
+        let mut line_buffer = LineBuffer::<2048>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturnNewline,
+            ..LineBufferConfig::DEFAULT
+        });
+        let mut aux_buffer = [0u8; 2048];
+
+        for _ in 0..2045 {
+            line_buffer.push_byte(b'a').unwrap();
+            assert!(matches!(
+                line_buffer.read_line_bytes(&mut aux_buffer),
+                Err(LineBufferRxError::NoLines { .. })
+            ));
+        }
+
+        // Half a terminator is remembered as a partial match, not skipped over.
+        line_buffer.push_byte(b'\r').unwrap();
+        assert!(!line_buffer.has_complete_line());
+        assert_eq!(line_buffer.scan.matched.get(), 1);
+        line_buffer.push_byte(b'\n').unwrap();
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer).unwrap(), 2045);
+        assert!(line_buffer.is_empty());
+    }
+
+    #[test]
+    fn test_poll_after_config_change_rescans_once() {
+        let mut line_buffer = LineBuffer::<64>::new();
+        let mut aux_buffer = [0u8; 64];
+        line_buffer.push_bytes(b"partial\r").unwrap();
+
+        line_buffer.set_config(leak(LineBufferConfig {
+            terminator: Terminator::CarriageReturnNewline,
+            ..*line_buffer.config()
+        }));
+        assert!(line_buffer.scan_is_stale());
+        assert!(matches!(
+            line_buffer.read_line_bytes(&mut aux_buffer),
+            Err(LineBufferRxError::NoLines { .. })
+        ));
+        // The rescan was kept, so further polls only look at new bytes.
+        assert!(!line_buffer.scan_is_stale());
+        assert_eq!(line_buffer.scan.matched.get(), 1);
+
+        line_buffer.push_byte(b'\n').unwrap();
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer).unwrap(), 7);
+        assert_eq!(&aux_buffer[..7], b"partial");
+    }
+
+    #[test]
+    fn test_peek_line_hash() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        let mut aux_buffer = [0u8; 16];
+        assert!(matches!(
+            line_buffer.peek_line_hash(),
+            Err(LineBufferRxError::BufferEmpty)
+        ));
+
+        // Wrap the second copy of the line around the end of the storage.
+        line_buffer.push_bytes(b"xxxxxxxxxx\n").unwrap();
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        line_buffer.push_bytes(b"RDY\nRDY\nBUSY\n").unwrap();
+
+        let first = line_buffer.peek_line_hash().unwrap();
+        // Peeking leaves the line in place.
+        assert_eq!(line_buffer.peek_line_hash().unwrap(), first);
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(line_buffer.peek_line_hash().unwrap(), first);
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_ne!(line_buffer.peek_line_hash().unwrap(), first);
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+
+        line_buffer.push_bytes(b"RDY").unwrap();
+        assert!(matches!(
+            line_buffer.peek_line_hash(),
+            Err(LineBufferRxError::NoLines { .. })
+        ));
+    }
+
+    #[test]
+    fn test_find_and_count_byte_against_scalar() {
+        let mut rng = Rng(0x5eed_1234);
+        let mut data = [0u8; 96];
+        for _ in 0..2000 {
+            for byte in data.iter_mut() {
+                *byte = rng.byte();
+            }
+            // Vary the alignment and length, so every head, word and tail split is covered.
+            let from = rng.below(16);
+            let to = from + rng.below(data.len() - from + 1);
+            let haystack = &data[from..to];
+            for needle in [b'\n', b'\r', b'\0', b'z'] {
+                assert_eq!(
+                    find_byte(haystack, needle),
+                    haystack.iter().position(|byte| *byte == needle)
+                );
+                assert_eq!(
+                    count_byte(haystack, needle),
+                    haystack.iter().filter(|byte| **byte == needle).count()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_overwrite_drops_oldest_line() {
+        let mut line_buffer = LineBuffer::<12>::new_with_config(&LineBufferConfig {
+            overflow: OverflowPolicy::DropOldestLine,
+            ..LineBufferConfig::DEFAULT
+        });
+        let mut aux_buffer = [0u8; 12];
+        line_buffer.push_bytes(b"one\ntwo\nthre").unwrap();
+        assert!(line_buffer.is_full());
+
+        // The whole of "one\n" goes, not just the "o".
+        assert_eq!(line_buffer.push_byte_overwrite(b'e'), Some(b'o'));
+        assert_eq!(line_buffer.len(), 9);
+        assert_eq!(line_buffer.line_count(), 1);
+        for byte in b"\nfo" {
+            assert_eq!(line_buffer.push_byte_overwrite(*byte), None);
+        }
+        assert_eq!(line_buffer.push_byte_overwrite(b'u'), Some(b't'));
+
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"three");
+        assert!(matches!(
+            line_buffer.read_line_bytes(&mut aux_buffer),
+            Err(LineBufferRxError::NoLines { .. })
+        ));
+        assert_eq!(line_buffer.len(), 3);
+    }
+
+    #[test]
+    fn test_overwrite_keeps_partial_line() {
+        let mut line_buffer = LineBuffer::<4>::new_with_config(&LineBufferConfig {
+            overflow: OverflowPolicy::DropOldestLine,
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"abcd").unwrap();
+
+        // A partial line is never cut, so the byte is refused as `push_bytes` would refuse it.
+        assert_eq!(line_buffer.push_byte_overwrite(b'e'), None);
+        let mut out = [0u8; 4];
+        assert_eq!(line_buffer.peek_all(&mut out), 4);
+        assert_eq!(&out, b"abcd");
+        #[cfg(feature = "stats")]
+        assert_eq!(line_buffer.stats().bytes_rejected, 1);
+
+        // Any other policy drops the oldest byte.
+        line_buffer.set_config(leak(LineBufferConfig {
+            overflow: OverflowPolicy::Reject,
+            ..*line_buffer.config()
+        }));
+        assert_eq!(line_buffer.push_byte_overwrite(b'e'), Some(b'a'));
+        assert_eq!(line_buffer.push_byte_overwrite(b'\n'), Some(b'b'));
+        let len = line_buffer.read_line_bytes(&mut out).unwrap();
+        assert_eq!(&out[..len], b"cde");
+    }
+
+    #[test]
+    fn test_overflow_overwrite_oldest() {
+        let mut line_buffer = LineBuffer::<8>::new_with_config(&LineBufferConfig {
+            overflow: OverflowPolicy::OverwriteOldest,
+            ..LineBufferConfig::DEFAULT
+        });
+        let mut aux_buffer = [0u8; 8];
+        line_buffer.push_bytes(b"abc\n").unwrap();
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        line_buffer.push_bytes(b"one\ntwo\n").unwrap();
+        assert!(line_buffer.is_full());
+
+        // The new line is written across the end of the storage, over "one\ntw".
+        line_buffer.push_bytes(b"hello\n").unwrap();
+        assert_eq!(line_buffer.end, 2);
+        assert!(line_buffer.is_full());
+        assert_eq!(line_buffer.line_count(), 2);
+
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"o");
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"hello");
+        assert!(line_buffer.is_empty());
+    }
+
+    #[test]
+    fn test_new_overwriting() {
+        let mut line_buffer = LineBuffer::<8>::new_overwriting();
+        assert_eq!(line_buffer.config.terminator, Terminator::Newline);
+        line_buffer.push_bytes(b"abc\ndef\n").unwrap();
+        assert!(line_buffer.is_full());
+
+        // Further bytes wrap over the oldest ones instead of failing.
+        line_buffer.push_bytes(b"gh\n").unwrap();
+        assert!(line_buffer.is_full());
+        let mut out = [0u8; 8];
+        assert_eq!(line_buffer.peek_all(&mut out), 8);
+        assert_eq!(&out, b"\ndef\ngh\n");
+        line_buffer.push_byte(b'i').unwrap();
+        assert_eq!(line_buffer.peek_all(&mut out), 8);
+        assert_eq!(&out, b"def\ngh\ni");
+    }
+
+    #[test]
+    fn test_overflow_overwrite_oldest_push_paths() {
+        let mut line_buffer = LineBuffer::<8>::new_with_config(&LineBufferConfig {
+            overflow: OverflowPolicy::OverwriteOldest,
+            ..LineBufferConfig::DEFAULT
+        });
+        let mut aux_buffer = [0u8; 8];
+
+        // More than the whole capacity at once keeps only the tail.
+        line_buffer.push_bytes(b"0123456789\n").unwrap();
+        assert_eq!(line_buffer.len(), 8);
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"3456789");
+
+        line_buffer.push_bytes(b"abcdefgh").unwrap();
+        line_buffer.push_byte(b'\n').unwrap();
+        assert_eq!(line_buffer.len(), 8);
+        assert_eq!(
+            line_buffer.push_from_iter(b"xy\n".iter().copied()).unwrap(),
+            3
+        );
+        assert_eq!(line_buffer.len(), 8);
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"efgh");
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"xy");
+    }
+
+    #[test]
+    fn test_overflow_drop_oldest_line() {
+        let mut line_buffer = LineBuffer::<12>::new_with_config(&LineBufferConfig {
+            overflow: OverflowPolicy::DropOldestLine,
+            ..LineBufferConfig::DEFAULT
+        });
+        let mut aux_buffer = [0u8; 12];
+        line_buffer.push_bytes(b"one\ntwo\nthr").unwrap();
+
+        // Only one byte is free, so "one\n" goes to make room for three.
+        line_buffer.push_bytes(b"ee\n").unwrap();
+        assert_eq!(line_buffer.len(), 10);
+        line_buffer.peek_lines(1, |first, second| {
+            assert_eq!(first, b"two");
+            assert!(second.is_empty());
+        });
+
+        line_buffer.push_bytes(b"four").unwrap();
+        assert_eq!(line_buffer.len(), 10);
+
+        // Dropping "three\n" as well would still leave no room, so nothing is dropped.
+        assert!(matches!(
+            line_buffer.push_bytes(b"123456789"),
+            Err(LineBufferTxError::BufferFull { .. })
+        ));
+        assert_eq!(line_buffer.len(), 10);
+        assert_eq!(line_buffer.line_count(), 1);
+
+        // More than the whole capacity can never fit.
+        assert!(matches!(
+            line_buffer.push_bytes(&[b'x'; 13]),
+            Err(LineBufferTxError::BufferFull { .. })
+        ));
+
+        line_buffer.push_bytes(b"567890\n").unwrap();
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"four567890");
+        assert!(line_buffer.is_empty());
+        #[cfg(feature = "stats")]
+        assert_eq!(line_buffer.stats().lines_dropped, 3);
+    }
+
+    #[test]
+    fn test_overflow_drop_oldest_line_partial_front() {
+        let mut line_buffer = LineBuffer::<8>::new_with_config(&LineBufferConfig {
+            overflow: OverflowPolicy::DropOldestLine,
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"ab\ncdefg").unwrap();
+
+        // "ab\n" is dropped, then only the partial "cdefg..." remains and it is never cut.
+        line_buffer.push_from_iter(*b"hij").unwrap();
+        assert_eq!(line_buffer.len(), 8);
+        assert!(matches!(
+            line_buffer.push_byte(b'k'),
+            Err(LineBufferTxError::BufferFull { .. })
+        ));
+        assert!(matches!(
+            line_buffer.push_from_iter(*b"k"),
+            Err(LineBufferTxError::Overflow {
+                accepted: 0,
+                rejected: b'k'
+            })
+        ));
+        let mut out = [0u8; 8];
+        assert_eq!(line_buffer.peek_all(&mut out), 8);
+        assert_eq!(&out, b"cdefghij");
+    }
+
+    #[test]
+    fn test_overflow_drop_oldest_line_randomized() {
+        let mut rng = Rng(0x2545_f491);
+        let mut line_buffer = LineBuffer::<32>::new_with_config(&LineBufferConfig {
+            overflow: OverflowPolicy::DropOldestLine,
+            ..LineBufferConfig::DEFAULT
+        });
+        let mut line = [0u8; 40];
+        let mut aux_buffer = [0u8; 32];
+
+        for _ in 0..2000 {
+            // Each line is `<`, a run of one repeated byte, `>`, so a cut-off line is easy to spot.
+            let run = rng.below(line.len() - 2);
+            let fill = b'a' + rng.below(26) as u8;
+            line[0] = b'<';
+            line[1..=run].fill(fill);
+            line[run + 1] = b'>';
+            line[run + 2] = b'\n';
+            let fits = run + 3 <= 32;
+            assert_eq!(line_buffer.push_bytes(&line[..run + 3]).is_ok(), fits);
+
+            line_buffer.peek_lines(1, |first, second| {
+                let mut bytes = first.iter().chain(second);
+                assert_eq!(bytes.next(), Some(&b'<'));
+                let body = bytes.next().unwrap();
+                assert!(bytes.all(|byte| byte == body || *byte == b'>'));
+                assert_eq!(first.iter().chain(second).last(), Some(&b'>'));
+            });
+            if rng.below(4) == 0 {
+                let _ = line_buffer.read_line_bytes(&mut aux_buffer);
+            }
+        }
+    }
+
+    #[test]
+    fn test_with_line_wrapped() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        line_buffer.push_bytes(b"xxxxxxxxxxxx\n").unwrap();
+        line_buffer.with_line(|_, _| ()).unwrap();
+        line_buffer.push_bytes(b"ch=42\nnext").unwrap();
+
+        let value = line_buffer
+            .with_line(|first, second| {
+                assert_eq!(first, b"ch=");
+                assert_eq!(second, b"42");
+                second
+                    .iter()
+                    .fold(0u32, |n, byte| n * 10 + (byte - b'0') as u32)
+            })
+            .unwrap();
+        assert_eq!(value, 42);
+        assert_eq!(line_buffer.len(), 4);
+
+        let mut called = false;
+        assert!(matches!(
+            line_buffer.with_line(|_, _| called = true),
+            Err(LineBufferRxError::NoLines { .. })
+        ));
+        assert!(!called);
+        line_buffer.clear();
+        assert!(matches!(
+            line_buffer.with_line(|_, _| called = true),
+            Err(LineBufferRxError::BufferEmpty)
+        ));
+        assert!(!called);
+    }
+
+    #[test]
+    fn test_try_with_line_consumes_only_on_success() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        line_buffer.push_bytes(b"abc\n123\n").unwrap();
+
+        let parse = |first: &[u8], _: &[u8]| {
+            core::str::from_utf8(first)
+                .ok()
+                .and_then(|text| text.parse::<u32>().ok())
+                .ok_or("not a number")
+        };
+        assert_eq!(
+            line_buffer.try_with_line(parse).unwrap(),
+            Err("not a number")
+        );
+        assert_eq!(line_buffer.line_count(), 2);
+        assert_eq!(
+            line_buffer.try_with_line(parse).unwrap(),
+            Err("not a number")
+        );
+
+        line_buffer.with_line(|_, _| ()).unwrap();
+        assert_eq!(line_buffer.try_with_line(parse).unwrap(), Ok(123));
+        assert!(line_buffer.is_empty());
+    }
+
+    #[test]
+    fn test_unread() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        let mut aux_buffer = [0u8; 8];
+        line_buffer.push_bytes(b"cd").unwrap();
+
+        // Unreading across the start of the storage wraps back to the end.
+        line_buffer.unread(b"ab\n").unwrap();
+        assert_eq!(line_buffer.start.get(), 5);
+        assert_eq!(line_buffer.line_count(), 1);
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"ab");
+
+        line_buffer.push_bytes(b"\n").unwrap();
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"cd");
+        assert!(line_buffer.is_empty());
+    }
+
+    #[test]
+    fn test_unread_buffer_full() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        line_buffer.push_bytes(b"line\n").unwrap();
+
+        assert!(matches!(
+            line_buffer.unread(b"1234"),
+            Err(LineBufferTxError::BufferFull { .. })
+        ));
+        assert_eq!(line_buffer.len(), 5);
+        line_buffer.unread(b"123").unwrap();
+        assert!(line_buffer.is_full());
+
+        let mut aux_buffer = [0u8; 8];
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"123line");
+    }
+
+    /// Stream lines through a slice-backed buffer, checking every read against the reference scan.
+    fn slice_matches_naive_scan(storage: &mut [u8], rng: &mut Rng) {
+        let terminators = [
+            Terminator::Newline,
+            Terminator::CarriageReturnNewline,
+            Terminator::Sequence(b"aab"),
+            Terminator::None,
+        ];
+        let capacity = storage.len();
+        let mut line_buffer = LineBufferSlice::new(storage);
+        assert_eq!(line_buffer.capacity(), capacity);
+
+        for _ in 0..5000 {
+            match rng.below(12) {
+                0..=4 => {
+                    let _ = line_buffer.push_byte(rng.byte());
+                }
+                5 | 6 => {
+                    let chunk = [rng.byte(), rng.byte(), rng.byte()];
+                    let _ = line_buffer.push_bytes(&chunk[..rng.below(4)]);
+                }
+                7 | 8 => {
+                    let expected = naive_find_line(&line_buffer);
+                    let mut expected_line = [0u8; NAIVE_CAPACITY];
+                    contents(&line_buffer, &mut expected_line);
+                    let mut aux_buffer = [0u8; NAIVE_CAPACITY];
+                    match line_buffer.read_line_bytes(&mut aux_buffer) {
+                        Ok(bytes_read) => {
+                            assert_eq!(Some(bytes_read), expected.map(|(len, _)| len));
+                            assert_eq!(aux_buffer[..bytes_read], expected_line[..bytes_read]);
+                        }
+                        Err(_) => assert_eq!(expected, None),
+                    }
+                }
+                9 => line_buffer.set_config(leak(LineBufferConfig {
+                    terminator: terminators[rng.below(terminators.len())],
+                    ..*line_buffer.config()
+                })),
+                10 => {
+                    line_buffer.push_byte_overwrite(rng.byte());
+                }
+                _ => line_buffer.clear(),
+            }
+
+            assert!(line_buffer.len() <= capacity);
+            assert_eq!(line_buffer.find_line(), naive_find_line(&line_buffer));
+            assert_eq!(line_buffer.line_count(), naive_line_count(&line_buffer));
+        }
+    }
+
+    #[test]
+    fn test_slice_matches_naive_scan() {
+        let mut rng = Rng(0x51ce_0001);
+        let mut storage = [0u8; 64];
+        for capacity in [7, 8, 13, 64] {
+            slice_matches_naive_scan(&mut storage[..capacity], &mut rng);
+        }
+    }
+
+    #[test]
+    fn test_slice_read_line() {
+        let mut storage = [0u8; 7];
+        let mut line_buffer = LineBufferSlice::new_with_config(
+            &mut storage,
+            &LineBufferConfig {
+                terminator: Terminator::CarriageReturnNewline,
+                ..LineBufferConfig::DEFAULT
+            },
+        );
+        let mut aux_buffer = [0u8; 7];
+
+        // Wrap the second line around the end of the slice.
+        line_buffer.push_bytes(b"abc\r\n").unwrap();
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer).unwrap(), 3);
+        line_buffer.push_bytes(b"defg\r\nh").unwrap();
+        assert!(line_buffer.is_full());
+        assert!(matches!(
+            line_buffer.push_byte(b'x'),
+            Err(LineBufferTxError::BufferFull { .. })
+        ));
+
+        // A smaller aux buffer is fine as long as the line fits.
+        assert!(matches!(
+            line_buffer.read_line_bytes(&mut aux_buffer[..3]),
+            Err(LineBufferRxError::AuxBufferTooSmall)
+        ));
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer[..4]).unwrap();
+        assert_eq!(&aux_buffer[..bytes_read], b"defg");
+        assert_eq!(line_buffer.len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "buffer does not fit in the index type")]
+    fn test_slice_too_long_for_index() {
+        let mut storage = [0u8; 300];
+        let _ = LineBufferSlice::<u8>::new_typed(&mut storage, &LineBufferConfig::DEFAULT);
+    }
+
+    #[test]
+    fn test_no_terminator_streams_buffered_bytes() {
+        let mut line_buffer = LineBuffer::<8>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::None,
+            ..LineBufferConfig::DEFAULT
+        });
+        let mut aux_buffer = [0u8; 8];
+        assert!(matches!(
+            line_buffer.read_line_bytes(&mut aux_buffer),
+            Err(LineBufferRxError::BufferEmpty)
+        ));
+
+        // Each read drains whatever arrived since the last one, terminator bytes included.
+        line_buffer.push_bytes(b"ab\n").unwrap();
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..bytes_read], b"ab\n");
+        assert!(line_buffer.is_empty());
+        assert!(matches!(
+            line_buffer.read_line_bytes(&mut aux_buffer),
+            Err(LineBufferRxError::BufferEmpty)
+        ));
 
-        line_buffer
-            .push_byte(75)
-            .expect_err("Expected buffer full error");
+        line_buffer.push_byte(b'c').unwrap();
+        assert_eq!(line_buffer.line_count(), 1);
+        line_buffer.push_bytes(b"de").unwrap();
+        assert_eq!(line_buffer.line_count(), 1);
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..bytes_read], b"cde");
+
+        // Wrapped data comes out in one read, in order.
+        line_buffer.push_bytes(b"fghijklm").unwrap();
+        assert!(line_buffer.is_full());
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..bytes_read], b"fghijklm");
+        assert!(!line_buffer.has_complete_line());
     }
 
-    /// Test pushing multiple bytes at a time, up until and including when the buffer is full.
     #[test]
-    fn test_push_bytes() {
-        // Test adding a full capacity of bytes:
-        let mut line_buffer = LineBuffer::<10>::new();
+    fn test_no_terminator_push_read_push_read() {
+        let mut line_buffer = LineBuffer::<8>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::None,
+            ..LineBufferConfig::DEFAULT
+        });
+        let mut aux_buffer = [0u8; 8];
 
-        line_buffer
-            .push_bytes(&[65, 66, 67, 68, 69, 70, 71, 72, 73, 74])
-            .unwrap();
+        line_buffer.push_bytes(b"abcde").unwrap();
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(5));
+        assert_eq!(&aux_buffer[..5], b"abcde");
+        assert_eq!(
+            line_buffer.read_line_bytes(&mut aux_buffer),
+            Err(LineBufferRxError::BufferEmpty)
+        );
 
-        assert_eq!(line_buffer.buffer, [65, 66, 67, 68, 69, 70, 71, 72, 73, 74]);
-        assert_eq!(line_buffer.start, 0);
-        assert_eq!(line_buffer.end, 0);
-        assert_eq!(line_buffer.empty, false);
-        assert_eq!(line_buffer.is_full(), true);
+        // The next push wraps, and still comes back whole.
+        line_buffer.push_bytes(b"fghij").unwrap();
+        assert!(line_buffer.end.get() < line_buffer.start.get());
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(5));
+        assert_eq!(&aux_buffer[..5], b"fghij");
+        assert!(line_buffer.is_empty());
+        assert_eq!(
+            line_buffer.read_line_bytes(&mut aux_buffer),
+            Err(LineBufferRxError::BufferEmpty)
+        );
 
-        line_buffer
-            .push_bytes(&[75])
-            .expect_err("Expected buffer full error");
+        // Bytes pushed after a read wait for the next one, even a single byte.
+        line_buffer.push_byte(b'k').unwrap();
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(1));
+        assert_eq!(aux_buffer[0], b'k');
+        line_buffer.push_bytes(b"lm").unwrap();
+        line_buffer.push_bytes(b"no").unwrap();
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(4));
+        assert_eq!(&aux_buffer[..4], b"lmno");
+        assert_eq!(
+            line_buffer.read_line_bytes(&mut aux_buffer),
+            Err(LineBufferRxError::BufferEmpty)
+        );
+    }
 
-        // Test adding more than the capacity of bytes:
-        let mut line_buffer = LineBuffer::<10>::new();
+    #[test]
+    fn test_no_terminator_drops_escape_bytes() {
+        let mut line_buffer = LineBuffer::<8>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::None,
+            escape: Some(b'\\'),
+            ..LineBufferConfig::DEFAULT
+        });
+        let mut aux_buffer = [0u8; 8];
+        line_buffer.push_bytes(b"a\\\\b").unwrap();
+        assert_eq!(line_buffer.read_line_bytes(&mut aux_buffer), Ok(3));
+        assert_eq!(&aux_buffer[..3], b"a\\b");
+        assert!(line_buffer.is_empty());
+    }
 
-        line_buffer
-            .push_bytes(&[65, 66, 67, 68, 69, 70, 71, 72, 73, 74, 75])
-            .expect_err("Expected buffer full error");
+    /// With no terminator, interleaved pushes and reads hand back every byte exactly once, in order.
+    #[test]
+    fn test_no_terminator_never_returns_no_lines() {
+        let mut rng = Rng(0x0000_fee1);
+        for terminator in [Terminator::None, Terminator::Sequence(b"")] {
+            let mut line_buffer = LineBuffer::<16>::new_with_config(leak(LineBufferConfig {
+                terminator,
+                ..LineBufferConfig::DEFAULT
+            }));
+            let mut aux_buffer = [0u8; 16];
+            let (mut pushed, mut read) = (0u8, 0u8);
+
+            for _ in 0..5000 {
+                if rng.below(3) == 0 {
+                    match line_buffer.read_line_bytes(&mut aux_buffer) {
+                        Ok(bytes_read) => {
+                            assert!(bytes_read > 0);
+                            for byte in &aux_buffer[..bytes_read] {
+                                assert_eq!(*byte, read);
+                                read = read.wrapping_add(1);
+                            }
+                        }
+                        Err(error) => {
+                            assert!(matches!(error, LineBufferRxError::BufferEmpty));
+                            assert_eq!(pushed, read);
+                        }
+                    }
+                } else {
+                    for _ in 0..rng.below(4) {
+                        if line_buffer.push_byte(pushed).is_ok() {
+                            pushed = pushed.wrapping_add(1);
+                        }
+                    }
+                }
+                assert!(line_buffer.is_empty() || line_buffer.has_complete_line());
+            }
+        }
     }
 
+    #[cfg(feature = "alloc")]
     #[test]
-    fn test_is_empty() {
-        let mut line_buffer = LineBuffer::<10>::new();
+    fn test_vec_grows_mid_wrap() {
+        let mut line_buffer = LineBufferVec::new(4);
+        let mut aux_buffer = [0u8; 64];
 
-        assert_eq!(line_buffer.is_empty(), true);
+        // Wrap the data, then push past the end so the buffer has to grow with it wrapped.
+        line_buffer.push_bytes(b"ab\n").unwrap();
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        line_buffer.push_bytes(b"cd").unwrap();
+        assert!(line_buffer.end.get() < line_buffer.start.get());
+        line_buffer.push_bytes(b"e\nfgh").unwrap();
+        assert_eq!(line_buffer.capacity(), 8);
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..bytes_read], b"cde");
 
-        line_buffer.push_byte(65).unwrap();
+        // And again, one byte at a time.
+        line_buffer.push_bytes(b"\nijkl").unwrap();
+        assert!(line_buffer.is_full());
+        assert!(line_buffer.end.get() <= line_buffer.start.get());
+        line_buffer.push_byte(b'm').unwrap();
+        line_buffer.push_byte(b'\n').unwrap();
+        assert_eq!(line_buffer.capacity(), 16);
+        assert_eq!(line_buffer.line_count(), 2);
 
-        assert_eq!(line_buffer.is_empty(), false);
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..bytes_read], b"fgh");
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..bytes_read], b"ijklm");
+        assert!(line_buffer.is_empty());
     }
 
+    #[cfg(feature = "alloc")]
     #[test]
-    fn test_is_full() {
-        let mut line_buffer = LineBuffer::<10>::new();
+    fn test_vec_full_at_max_capacity() {
+        let mut line_buffer = LineBufferVec::with_max_capacity(2, 6);
+        line_buffer.push_bytes(b"abcd").unwrap();
+        assert_eq!(line_buffer.capacity(), 4);
 
-        assert_eq!(line_buffer.is_full(), false);
+        // Growth stops at the maximum, and the bytes that fit are kept.
+        assert!(matches!(
+            line_buffer.push_bytes(b"efgh"),
+            Err(LineBufferTxError::BufferFull { .. })
+        ));
+        assert_eq!(line_buffer.capacity(), 6);
+        assert!(line_buffer.is_full());
+        assert!(matches!(
+            line_buffer.push_byte(b'\n'),
+            Err(LineBufferTxError::BufferFull { .. })
+        ));
+        assert_eq!(line_buffer.push_byte_overwrite(b'\n'), Some(b'a'));
+        let mut aux_buffer = [0u8; 6];
+        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..bytes_read], b"bcdef");
+    }
 
-        for i in 0..10 {
-            line_buffer.push_byte(i as u8).expect("Failed to push byte");
-        }
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_vec_growth_capped_by_index() {
+        let mut line_buffer = LineBufferVec::<u8>::new_typed(200, None, &LineBufferConfig::DEFAULT);
+        line_buffer.push_bytes(&[b'a'; 200]).unwrap();
+        assert!(line_buffer.push_bytes(&[b'a'; 100]).is_err());
+        assert_eq!(line_buffer.capacity(), u8::MAX as usize);
+    }
 
-        assert_eq!(line_buffer.is_full(), true);
+    /// Growing repeatedly while wrapped never loses or reorders bytes.
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_vec_growth_preserves_order() {
+        let mut rng = Rng(0x6a09_e667);
+        let mut line_buffer = LineBufferVec::new(0);
+        let mut aux_buffer = [0u8; 4096];
+        let (mut pushed, mut read) = (0u8, 0u8);
+        let mut growths = 0;
 
-        line_buffer
-            .push_byte(10)
-            .expect_err("Expected buffer full error");
+        for _ in 0..3000 {
+            let capacity = line_buffer.capacity();
+            match rng.below(4) {
+                0 => {
+                    if let Ok(bytes_read) = line_buffer.read_line_bytes(&mut aux_buffer) {
+                        for byte in &aux_buffer[..bytes_read] {
+                            assert_eq!(*byte, b'a' + read);
+                            read = (read + 1) % 26;
+                        }
+                    }
+                }
+                1 => {
+                    let count = rng.below(3);
+                    let iter = (0..count).map(|i| b'a' + (pushed + i as u8) % 26);
+                    line_buffer.push_from_iter(iter).unwrap();
+                    pushed = (pushed + count as u8) % 26;
+                }
+                2 => {
+                    let mut chunk = [0u8; 5];
+                    for byte in chunk.iter_mut() {
+                        *byte = b'a' + pushed;
+                        pushed = (pushed + 1) % 26;
+                    }
+                    line_buffer.push_bytes(&chunk).unwrap();
+                }
+                _ => line_buffer.push_byte(b'\n').unwrap(),
+            }
+            if line_buffer.capacity() > capacity {
+                growths += 1;
+            }
+            assert_eq!(line_buffer.line_count(), naive_line_count(&line_buffer));
+        }
+        assert!(growths >= 5);
     }
 
     #[test]
-    fn test_clear() {
-        let mut line_buffer = LineBuffer::<10>::new();
-
-        line_buffer.push_byte(65).unwrap();
+    fn test_rewind() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        let mut aux_buffer = [0u8; 8];
+        line_buffer.push_bytes(b"ab\ncd\n").unwrap();
 
-        assert_eq!(line_buffer.buffer, [65, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
-        assert_eq!(line_buffer.start, 0);
-        assert_eq!(line_buffer.end, 1);
-        assert_eq!(line_buffer.empty, false);
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert!(line_buffer.is_empty());
+        // Writes into space that was never read from leave the history intact.
+        line_buffer.push_bytes(b"e").unwrap();
 
-        line_buffer.clear();
+        line_buffer.rewind().unwrap();
+        assert_eq!(line_buffer.len(), 7);
+        assert_eq!(line_buffer.line_count(), 2);
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"ab");
 
-        // We expect the contents of the buffer to remain the same, but the pointers to be reset.
-        assert_eq!(line_buffer.buffer, [65, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
-        assert_eq!(line_buffer.start, 0);
-        assert_eq!(line_buffer.end, 0);
-        assert_eq!(line_buffer.empty, true);
-        assert_eq!(line_buffer.is_empty(), true);
+        // Rewinding again goes back to the same place.
+        line_buffer.rewind().unwrap();
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"ab");
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"cd");
+        assert_eq!(line_buffer.len(), 1);
     }
 
     #[test]
-    fn test_read_line() {
-        let mut line_buffer = LineBuffer::<10>::new();
-
-        line_buffer.push_bytes(b"Hello\n").unwrap();
+    fn test_rewind_after_overwrite() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        let mut aux_buffer = [0u8; 8];
+        line_buffer.push_bytes(b"ab\ncd\n").unwrap();
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
 
-        let mut aux_buffer = [0u8; 10];
-        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        // Two bytes fit in space that was never used, the third lands on the "a".
+        line_buffer.push_bytes(b"ef").unwrap();
+        line_buffer.rewind().unwrap();
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        line_buffer.push_bytes(b"g").unwrap();
+        assert!(matches!(
+            line_buffer.rewind(),
+            Err(RewindError::Overwritten)
+        ));
 
-        assert_eq!(bytes_read, 5);
-        assert_eq!(aux_buffer, [b'H', b'e', b'l', b'l', b'o', 0, 0, 0, 0, 0]);
-        assert_eq!(line_buffer.is_empty(), true);
+        // Nothing moved, and a clear starts a fresh history.
+        let len = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(&aux_buffer[..len], b"cd");
+        line_buffer.clear();
+        line_buffer.push_bytes(b"h\n").unwrap();
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        line_buffer.rewind().unwrap();
+        assert_eq!(line_buffer.len(), 2);
     }
 
     #[test]
-    fn test_read_line_when_empty() {
-        let mut line_buffer = LineBuffer::<10>::new();
+    fn test_config_builder() {
+        const CONFIG: LineBufferConfig = match LineBufferConfig::builder()
+            .terminator(Terminator::Sequence(b"END"))
+            .escape(Some(b'\\'))
+            .batch_separator(BatchSeparator::NULL)
+            .overflow(OverflowPolicy::DropOldestLine)
+            .strip_trailing_cr(true)
+            .build()
+        {
+            Ok(config) => config,
+            Err(_) => panic!(),
+        };
+        assert_eq!(CONFIG.terminator, Terminator::Sequence(b"END"));
+        assert_eq!(CONFIG.escape, Some(b'\\'));
+        assert_eq!(CONFIG.batch_separator, BatchSeparator::NULL);
+        assert_eq!(CONFIG.overflow, OverflowPolicy::DropOldestLine);
+        const { assert!(CONFIG.strip_trailing_cr) }
 
-        let mut aux_buffer = [0u8; 10];
-        line_buffer
-            .read_line_bytes(&mut aux_buffer)
-            .expect_err("Expected buffer empty error");
+        let config = LineBufferConfig::builder().build().unwrap();
+        assert_eq!(config.terminator, LineBufferConfig::DEFAULT.terminator);
+        let config = LineBufferConfig::builder()
+            .terminator(Terminator::None)
+            .build()
+            .unwrap();
+        assert_eq!(config.terminator, Terminator::None);
     }
 
     #[test]
-    fn test_read_line_with_null_terminator() {
-        let mut line_buffer = LineBuffer::<11>::new_with_config(LineBufferConfig {
-            terminator: Terminator::NULL,
+    fn test_config_builder_errors() {
+        assert_eq!(
+            LineBufferConfig::builder()
+                .terminator(Terminator::Sequence(b""))
+                .build()
+                .err(),
+            Some(ConfigError::EmptySequence)
+        );
+        assert_eq!(
+            LineBufferConfig::builder()
+                .terminator(Terminator::CarriageReturnNewline)
+                .escape(Some(b'\r'))
+                .build()
+                .err(),
+            Some(ConfigError::EscapeInTerminator)
+        );
+        assert_eq!(
+            LineBufferConfig::builder()
+                .terminator(Terminator::SequenceCI(b"END"))
+                .escape(Some(b'e'))
+                .build()
+                .err(),
+            Some(ConfigError::EscapeInTerminator)
+        );
+    }
+
+    #[test]
+    fn test_read_line_exact() {
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            escape: Some(b'\\'),
+            ..LineBufferConfig::DEFAULT
         });
+        line_buffer.push_bytes(b"abc\na\\\nd\nabcd\n").unwrap();
+        let mut out = [0u8; 3];
 
-        line_buffer.push_bytes(b"Hello\0World").unwrap();
+        line_buffer.read_line_exact(&mut out, 3).unwrap();
+        assert_eq!(&out, b"abc");
+        // The length is counted after escape bytes are dropped.
+        line_buffer.read_line_exact(&mut out, 3).unwrap();
+        assert_eq!(&out, b"a\nd");
 
-        let mut aux_buffer = [0u8; 11];
-        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert!(matches!(
+            line_buffer.read_line_exact(&mut out, 3),
+            Err(LineBufferRxError::UnexpectedLineLength {
+                found: 4,
+                expected: 3
+            })
+        ));
+        // The mismatched line was consumed.
+        assert!(line_buffer.is_empty());
+    }
 
-        assert_eq!(bytes_read, 5);
-        assert_eq!(aux_buffer, [b'H', b'e', b'l', b'l', b'o', 0, 0, 0, 0, 0, 0]);
-        assert_eq!(line_buffer.is_empty(), false);
+    #[test]
+    fn test_read_line_exact_out_too_small() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        line_buffer.push_bytes(b"abcd\n").unwrap();
+
+        let mut out = [0u8; 3];
+        assert!(matches!(
+            line_buffer.read_line_exact(&mut out, 4),
+            Err(LineBufferRxError::AuxBufferTooSmall)
+        ));
+        assert_eq!(line_buffer.line_count(), 1);
+        let mut out = [0u8; 4];
+        line_buffer.read_line_exact(&mut out, 4).unwrap();
+        assert_eq!(&out, b"abcd");
     }
 
+    #[cfg(feature = "stats")]
     #[test]
-    fn test_read_line_when_full() {
-        let mut line_buffer = LineBuffer::<6>::new();
+    fn test_stats() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        let mut aux_buffer = [0u8; 8];
+        assert_eq!(*line_buffer.stats(), LineBufferStats::default());
 
-        line_buffer.push_bytes(b"Hello\n").unwrap();
+        line_buffer.push_bytes(b"abc\nde").unwrap();
+        line_buffer.push_byte(b'\n').unwrap();
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        line_buffer.push_from_iter(*b"fghij").unwrap();
+        // Full at 8 bytes, so this evicts the "d".
+        assert_eq!(line_buffer.push_byte_overwrite(b'k'), Some(b'd'));
+        line_buffer.with_line(|_, _| ()).unwrap();
+        assert_eq!(line_buffer.len(), 6);
 
-        let mut aux_buffer = [0u8; 6];
-        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(
+            *line_buffer.stats(),
+            LineBufferStats {
+                bytes_pushed: 13,
+                bytes_read: 6,
+                lines_read: 2,
+                bytes_dropped: 1,
+                bytes_rejected: 0,
+                lines_dropped: 0,
+                bytes_skipped: 0,
+                lines_skipped: 0,
+                max_line_len: 3,
+                max_fill: 8,
+            }
+        );
 
-        assert_eq!(bytes_read, 5);
-        assert_eq!(aux_buffer, [b'H', b'e', b'l', b'l', b'o', 0]);
-        assert_eq!(line_buffer.is_empty(), true);
+        line_buffer.reset_stats();
+        line_buffer.clear();
+        line_buffer.push_bytes(b"xy\n").unwrap();
+        let mut record = [0u8; 4];
+        assert!(line_buffer.read_line_exact(&mut record, 4).is_err());
+        assert_eq!(
+            *line_buffer.stats(),
+            LineBufferStats {
+                bytes_pushed: 3,
+                bytes_read: 3,
+                lines_read: 1,
+                bytes_dropped: 6,
+                bytes_rejected: 0,
+                lines_dropped: 0,
+                bytes_skipped: 0,
+                lines_skipped: 0,
+                max_line_len: 2,
+                max_fill: 6,
+            }
+        );
+    }
 
-        line_buffer.push_bytes(b"World\n").unwrap();
+    #[cfg(feature = "stats")]
+    #[test]
+    fn test_dropped_bytes_rejecting() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        line_buffer.push_bytes(b"abc\nde").unwrap();
+        assert!(line_buffer.push_bytes(b"fghij").is_err());
+        assert_eq!(line_buffer.dropped_bytes(), 3);
+        assert!(line_buffer.push_byte(b'k').is_err());
+        assert!(line_buffer.push_from_iter(*b"lm").is_err());
+        assert_eq!(line_buffer.dropped_bytes(), 5);
+        assert_eq!(line_buffer.stats().bytes_rejected, 5);
 
-        let mut aux_buffer = [0u8; 6];
-        let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        // Bytes that stay with the caller's other buffer aren't lost.
+        let mut other = LineBuffer::<4>::new();
+        other.push_bytes(b"xyz").unwrap();
+        assert!(line_buffer.append_from(&mut other).is_err());
+        assert_eq!(line_buffer.dropped_bytes(), 5);
 
-        assert_eq!(bytes_read, 5);
-        assert_eq!(aux_buffer, [b'W', b'o', b'r', b'l', b'd', 0]);
-        assert_eq!(line_buffer.is_empty(), true);
+        line_buffer.reset_dropped();
+        assert_eq!(line_buffer.dropped_bytes(), 0);
+        assert_eq!(line_buffer.stats().bytes_pushed, 8);
+        line_buffer.read_line_bytes(&mut [0u8; 8]).unwrap();
+        assert!(line_buffer.feed(b"nopqrstuv", |_, _| ()).is_err());
+        assert_eq!(line_buffer.dropped_bytes(), 5);
     }
 
+    #[cfg(feature = "stats")]
     #[test]
-    fn test_read_multiple_lines_after_bulk_insert() {
-        let mut line_buffer = LineBuffer::<1024>::new();
+    fn test_dropped_bytes_overwriting() {
+        let mut line_buffer = LineBuffer::<8>::new_overwriting();
+        line_buffer.push_bytes(b"abc\ndefgh\n").unwrap();
+        assert_eq!(line_buffer.dropped_bytes(), 2);
+        line_buffer.push_byte(b'i').unwrap();
+        line_buffer.push_from_iter(*b"jk").unwrap();
+        assert_eq!(line_buffer.dropped_bytes(), 5);
+        assert_eq!(line_buffer.stats().bytes_rejected, 0);
 
-        for _i in 0..10 {
-            line_buffer.push_bytes(b"Hello World\n").unwrap();
-        }
-        line_buffer.push_bytes(b"Hello World").unwrap();
+        let mut line_buffer = LineBuffer::<8>::new_with_config(&LineBufferConfig {
+            overflow: OverflowPolicy::DropOldestLine,
+            ..LineBufferConfig::DEFAULT
+        });
+        line_buffer.push_bytes(b"ab\ncd\n").unwrap();
+        line_buffer.push_bytes(b"efg\n").unwrap();
+        assert_eq!(line_buffer.dropped_bytes(), 3);
+        assert_eq!(line_buffer.stats().lines_dropped, 1);
+        line_buffer.reset_dropped();
+        assert_eq!(line_buffer.dropped_bytes(), 0);
+        assert_eq!(line_buffer.stats().lines_dropped, 0);
+    }
 
-        for _i in 0..10 {
-            let mut aux_buffer = [0u8; 1024];
-            let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
-            assert_eq!(bytes_read, 11);
-            assert_eq!(
-                aux_buffer[..11],
-                [b'H', b'e', b'l', b'l', b'o', b' ', b'W', b'o', b'r', b'l', b'd']
-            );
+    #[test]
+    fn test_terminator_from_bytes() {
+        let terminators = [
+            Terminator::None,
+            Terminator::CarriageReturn,
+            Terminator::Newline,
+            Terminator::NULL,
+            Terminator::CarriageReturnNewline,
+            Terminator::NewlineCarriageReturn,
+        ];
+        for terminator in terminators {
+            let bytes: &'static [u8] = terminator.into();
+            assert_eq!(Terminator::try_from(bytes), Ok(terminator));
         }
+        assert_eq!(
+            Terminator::try_from(&b"\r\n"[..]),
+            Ok(Terminator::CarriageReturnNewline)
+        );
 
-        assert_eq!(line_buffer.is_empty(), false);
+        assert_eq!(Terminator::try_from(&b"END"[..]), Err(UnknownTerminator));
+        assert_eq!(Terminator::try_from(&b"\n\n"[..]), Err(UnknownTerminator));
+        let bytes: &[u8] = Terminator::Sequence(b"END").into();
+        assert_eq!(bytes, b"END");
     }
 
     #[test]
-    fn test_read_multiple_lines_while_inserting() {
-        let mut line_buffer = LineBuffer::<15>::new();
+    fn test_high_watermark() {
+        let mut line_buffer = LineBuffer::<100>::new();
+        let mut aux_buffer = [0u8; 100];
+        assert_eq!(line_buffer.high_watermark(), 0);
 
-        for _i in 0..100 {
-            line_buffer.push_bytes(b"Hello World\n").unwrap();
-            let mut aux_buffer = [0u8; 15];
-            let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
-            assert_eq!(bytes_read, 11);
-            assert_eq!(
-                aux_buffer[..11],
-                [b'H', b'e', b'l', b'l', b'o', b' ', b'W', b'o', b'r', b'l', b'd']
-            );
-        }
+        line_buffer.push_bytes(&[b'a'; 69]).unwrap();
+        line_buffer.push_byte(b'\n').unwrap();
+        assert_eq!(line_buffer.high_watermark(), 70);
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        assert_eq!(line_buffer.high_watermark(), 70);
 
-        assert_eq!(line_buffer.is_empty(), true);
+        // Wrapped pushes are measured the same way.
+        line_buffer.push_from_iter([b'b'; 39]).unwrap();
+        line_buffer.push_byte(b'\n').unwrap();
+        assert_eq!(line_buffer.len(), 40);
+        assert_eq!(line_buffer.high_watermark(), 70);
+
+        line_buffer.reset_high_watermark();
+        assert_eq!(line_buffer.high_watermark(), 40);
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        line_buffer.unread(b"xyz").unwrap();
+        assert_eq!(line_buffer.high_watermark(), 40);
+        line_buffer.clear();
+        assert_eq!(line_buffer.high_watermark(), 40);
     }
 
     #[test]
-    fn test_read_no_lines() {
-        let mut line_buffer = LineBuffer::<10>::new();
-
-        line_buffer.push_bytes(b"Hello").unwrap();
-
-        let mut aux_buffer = [0u8; 10];
-        line_buffer
-            .read_line_bytes(&mut aux_buffer)
-            .expect_err("Expected no lines error");
+    fn test_peek_lines() {
+        let mut line_buffer = LineBuffer::<16>::new_with_config(&LineBufferConfig {
+            terminator: Terminator::CarriageReturnNewline,
+            ..LineBufferConfig::DEFAULT
+        });
+        let mut aux_buffer = [0u8; 16];
+        // Wrap the second line around the end of the storage.
+        line_buffer.push_bytes(b"xxxxxxxx\r\n").unwrap();
+        line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+        line_buffer.push_bytes(b"one\r\ntwo\r\nsix\r\n").unwrap();
 
-        line_buffer.push_byte(b'\n').unwrap();
+        let mut peeked = [[0u8; 3]; 3];
+        let mut count = 0;
+        line_buffer.peek_lines(2, |first, second| {
+            peeked[count][..first.len()].copy_from_slice(first);
+            peeked[count][first.len()..].copy_from_slice(second);
+            count += 1;
+        });
+        assert_eq!(count, 2);
+        assert_eq!(&peeked[..2], &[*b"one", *b"two"]);
 
-        let mut aux_buffer = [0u8; 10];
+        // Nothing was consumed.
+        assert_eq!(line_buffer.line_count(), 3);
         let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
-
-        assert_eq!(bytes_read, 5);
-        assert_eq!(aux_buffer, [b'H', b'e', b'l', b'l', b'o', 0, 0, 0, 0, 0]);
+        assert_eq!(&aux_buffer[..bytes_read], b"one");
     }
 
     #[test]
-    fn test_read_line_as_str() {
-        let mut line_buffer = LineBuffer::<18>::new();
+    fn test_peek_lines_stops_at_partial_line() {
+        let mut line_buffer = LineBuffer::<16>::new();
+        line_buffer.push_bytes(b"a\n\nbc").unwrap();
 
-        line_buffer.push_bytes(b"Hello\nHello\nHello\n").unwrap();
-        assert_eq!(line_buffer.is_empty(), false);
-        assert_eq!(line_buffer.is_full(), true);
+        let mut lengths = [usize::MAX; 4];
+        let mut count = 0;
+        line_buffer.peek_lines(4, |first, second| {
+            lengths[count] = first.len() + second.len();
+            count += 1;
+        });
+        assert_eq!(count, 2);
+        assert_eq!(&lengths[..2], &[1, 0]);
+        assert_eq!(line_buffer.len(), 5);
+    }
+
+    #[test]
+    fn test_flow_control_hysteresis() {
+        let mut line_buffer = LineBuffer::<20>::new_with_config(leak(LineBufferConfig {
+            flow_thresholds: Some(FlowThresholds::percent(20, 75, 25)),
+            ..LineBufferConfig::DEFAULT
+        }));
+        let mut aux_buffer = [0u8; 20];
 
         for _ in 0..3 {
-            let mut aux_buffer = [0u8; 18];
-            let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
-            let line = core::str::from_utf8(&aux_buffer[..bytes_read]).unwrap();
-            assert_eq!(bytes_read, 5);
-            assert_eq!(line, "Hello");
-        }
+            let mut pauses = 0;
+            let mut resumes = 0;
+            let mut poll = |line_buffer: &mut LineBuffer<20>| match line_buffer.poll_flow_change() {
+                Some(FlowChange::Pause) => pauses += 1,
+                Some(FlowChange::Resume) => resumes += 1,
+                None => {}
+            };
 
-        assert_eq!(line_buffer.is_empty(), true);
+            // Fill a byte at a time past the high threshold.
+            for byte in b"ab\ncdefghijklmnop\nq" {
+                line_buffer.push_byte(*byte).unwrap();
+                poll(&mut line_buffer);
+            }
+            assert_eq!(line_buffer.flow_state(), FlowState::Paused);
+            // Draining a little isn't enough to resume.
+            line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+            poll(&mut line_buffer);
+            assert_eq!(line_buffer.flow_state(), FlowState::Paused);
+            line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+            poll(&mut line_buffer);
+            assert_eq!(line_buffer.flow_state(), FlowState::Flowing);
+
+            // And again with a bulk push, drained by a read and a clear.
+            line_buffer.push_bytes(b"rstuvwxyzABCDE\n").unwrap();
+            poll(&mut line_buffer);
+            assert_eq!(line_buffer.flow_state(), FlowState::Paused);
+            line_buffer.push_bytes(b"FG").unwrap();
+            line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
+            poll(&mut line_buffer);
+            line_buffer.clear();
+            poll(&mut line_buffer);
+
+            assert_eq!(line_buffer.flow_state(), FlowState::Flowing);
+            assert_eq!((pauses, resumes), (2, 2));
+        }
     }
 
     #[test]
-    fn test_read_line_with_carriage_return_newline() {
-        let mut line_buffer = LineBuffer::<21>::new_with_config(LineBufferConfig {
-            terminator: Terminator::CarriageReturnNewline,
+    fn test_flow_control_changes_cancel_out() {
+        let mut line_buffer = LineBuffer::<8>::new_with_config(&LineBufferConfig {
+            flow_thresholds: Some(FlowThresholds { high: 6, low: 2 }),
+            ..LineBufferConfig::DEFAULT
         });
+        line_buffer.push_bytes(b"abcdef").unwrap();
+        assert_eq!(line_buffer.flow_state(), FlowState::Paused);
+        line_buffer.clear();
+        assert_eq!(line_buffer.flow_state(), FlowState::Flowing);
+        assert_eq!(line_buffer.poll_flow_change(), None);
 
-        line_buffer
-            .push_bytes(b"Hello\r\nHello\r\nHello\r\n")
-            .unwrap();
-        assert_eq!(line_buffer.is_empty(), false);
-        assert_eq!(line_buffer.is_full(), true);
+        assert_eq!(
+            LineBufferConfig::builder()
+                .flow_thresholds(Some(FlowThresholds { high: 4, low: 4 }))
+                .build()
+                .err(),
+            Some(ConfigError::FlowThresholdsOverlap)
+        );
+    }
 
-        for _ in 0..3 {
-            let mut aux_buffer = [0u8; 21];
-            let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
-            let line = core::str::from_utf8(&aux_buffer[..bytes_read]).unwrap();
-            assert_eq!(bytes_read, 5);
-            assert_eq!(line, "Hello");
+    /// Format `value` into `out`, returning the formatted text.
+    fn display<'a>(value: &dyn core::fmt::Display, out: &'a mut [u8]) -> &'a str {
+        struct Writer<'a>(&'a mut [u8], usize);
+
+        impl core::fmt::Write for Writer<'_> {
+            fn write_str(&mut self, s: &str) -> core::fmt::Result {
+                let end = self.1 + s.len();
+                self.0
+                    .get_mut(self.1..end)
+                    .ok_or(core::fmt::Error)?
+                    .copy_from_slice(s.as_bytes());
+                self.1 = end;
+                Ok(())
+            }
         }
 
-        assert_eq!(line_buffer.is_empty(), true);
+        let mut writer = Writer(out, 0);
+        core::fmt::write(&mut writer, format_args!("{value}")).unwrap();
+        let len = writer.1;
+        core::str::from_utf8(&out[..len]).unwrap()
     }
 
     #[test]
-    fn test_read_line_with_newline_carriage_return() {
-        let mut line_buffer = LineBuffer::<21>::new_with_config(LineBufferConfig {
-            terminator: Terminator::NewlineCarriageReturn,
-        });
+    fn test_error_payloads() {
+        let mut line_buffer = LineBuffer::<8>::new();
+        line_buffer.push_bytes(b"abc").unwrap();
 
-        line_buffer
-            .push_bytes(b"Hello\n\rHello\n\rHello\n\r")
-            .unwrap();
-        assert_eq!(line_buffer.is_empty(), false);
-        assert_eq!(line_buffer.is_full(), true);
+        // No terminator yet, so everything buffered is pending.
+        assert_eq!(
+            line_buffer.read_line_bytes(&mut [0u8; 8]),
+            Err(LineBufferRxError::NoLines { pending: 3 })
+        );
+        assert_eq!(
+            line_buffer.peek_line_hash(),
+            Err(LineBufferRxError::NoLines { pending: 3 })
+        );
 
-        for _ in 0..3 {
-            let mut aux_buffer = [0u8; 21];
-            let bytes_read = line_buffer.read_line_bytes(&mut aux_buffer).unwrap();
-            let line = core::str::from_utf8(&aux_buffer[..bytes_read]).unwrap();
-            assert_eq!(bytes_read, 5);
-            assert_eq!(line, "Hello");
-        }
+        // A bulk push that only partly fits stores what it can and says how much that was.
+        assert_eq!(
+            line_buffer.push_bytes(b"defghij\n"),
+            Err(LineBufferTxError::BufferFull {
+                requested: 8,
+                available: 5,
+                written: 5,
+            })
+        );
+        assert_eq!(line_buffer.len(), 8);
+        assert_eq!(
+            line_buffer.push_byte(b'\n'),
+            Err(LineBufferTxError::BufferFull {
+                requested: 1,
+                available: 0,
+                written: 0,
+            })
+        );
 
-        assert_eq!(line_buffer.is_empty(), true);
+        line_buffer.clear();
+        line_buffer.push_bytes(b"abcdef").unwrap();
+        assert_eq!(
+            line_buffer.unread(b"xyz"),
+            Err(LineBufferTxError::BufferFull {
+                requested: 3,
+                available: 2,
+                written: 0,
+            })
+        );
+
+        // Rejected without dropping anything, as the partial line at the front can't go.
+        line_buffer.set_config(leak(LineBufferConfig {
+            overflow: OverflowPolicy::DropOldestLine,
+            ..*line_buffer.config()
+        }));
+        assert_eq!(
+            line_buffer.push_bytes(b"xyz"),
+            Err(LineBufferTxError::BufferFull {
+                requested: 3,
+                available: 2,
+                written: 0,
+            })
+        );
+        assert_eq!(line_buffer.len(), 6);
+
+        // Both stay cheap to copy around.
+        assert!(size_of::<LineBufferTxError>() <= 4 * size_of::<usize>());
+        assert!(size_of::<LineBufferRxError>() <= 3 * size_of::<usize>());
+    }
+
+    #[test]
+    fn test_error_display() {
+        let mut out = [0u8; 64];
+        let cases: [(&dyn core::error::Error, &str); 12] = [
+            (
+                &LineBufferTxError::BufferFull {
+                    requested: 5,
+                    available: 2,
+                    written: 2,
+                },
+                "line buffer is full: 2 of 5 bytes written, 2 were free",
+            ),
+            (
+                &LineBufferTxError::Overflow {
+                    accepted: 3,
+                    rejected: b'x',
+                },
+                "line buffer filled up after accepting 3 bytes",
+            ),
+            (&LineBufferRxError::BufferEmpty, "line buffer is empty"),
+            (
+                &LineBufferRxError::NoLines { pending: 3 },
+                "no complete line is buffered, 3 bytes pending",
+            ),
+            (
+                &LineBufferRxError::AuxBufferTooSmall,
+                "output buffer is too small for the line",
+            ),
+            (
+                &LineBufferRxError::UnexpectedLineLength {
+                    found: 5,
+                    expected: 4,
+                },
+                "line is 5 bytes long, expected 4",
+            ),
+            (
+                &LineBufferRxError::LineTooLong { len: 9 },
+                "line is 9 bytes long, over the maximum line length",
+            ),
+            (
+                &RewindError::Overwritten,
+                "bytes to rewind over have been overwritten",
+            ),
+            (&ConfigError::EmptySequence, "terminator sequence is empty"),
+            (
+                &ConfigError::EscapeInTerminator,
+                "escape byte is part of the terminator",
+            ),
+            (
+                &ConfigError::FlowThresholdsOverlap,
+                "low flow threshold is not below the high threshold",
+            ),
+            (&UnknownTerminator, "not a recognized terminator sequence"),
+        ];
+        for (error, expected) in cases {
+            assert_eq!(display(error, &mut out), expected);
+            assert!(error.source().is_none());
+        }
     }
 }
@@ -0,0 +1,7 @@
+//! Misuse that should be rejected at compile time rather than at runtime.
+
+#[test]
+fn compile_fail() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,7 @@
+use strlinebuf::LineBuffer;
+
+static RX_BUFFER: LineBuffer<0> = LineBuffer::new();
+
+fn main() {
+    let _ = RX_BUFFER.is_empty();
+}